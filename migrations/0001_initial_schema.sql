@@ -0,0 +1,125 @@
+-- Schema as it stood when versioned migrations were introduced. Every statement is
+-- idempotent because existing deployments are baselined by re-running this file.
+
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY,
+    nickname TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    token_version INTEGER NOT NULL DEFAULT 0,
+    nickname_canonical TEXT NOT NULL CONSTRAINT users_nickname_canonical_key UNIQUE,
+    nickname_skeleton TEXT NOT NULL CONSTRAINT users_nickname_skeleton_key UNIQUE,
+    suspended_at TIMESTAMPTZ NULL
+);
+
+-- Local parts keep their case, so uniqueness is enforced on the lowercased address.
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (lower(email));
+
+CREATE TABLE IF NOT EXISTS invites (
+    id UUID PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    email TEXT NULL,
+    is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    max_uses INTEGER NOT NULL CHECK (max_uses > 0),
+    used_count INTEGER NOT NULL DEFAULT 0 CHECK (used_count >= 0),
+    expires_at TIMESTAMPTZ NULL,
+    created_by UUID NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ NULL
+);
+
+CREATE TABLE IF NOT EXISTS password_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    used_at TIMESTAMPTZ NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Statement-level triggers notify 'feature_flags_changed' (FEATURE_FLAGS_CHANNEL) so every
+-- instance reloads its cache, including after manual edits.
+CREATE TABLE IF NOT EXISTS feature_flags (
+    key TEXT PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    rollout_percentage SMALLINT NOT NULL DEFAULT 0 CHECK (rollout_percentage BETWEEN 0 AND 100),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS feature_flag_users (
+    flag_key TEXT NOT NULL REFERENCES feature_flags(key) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (flag_key, user_id)
+);
+
+CREATE OR REPLACE FUNCTION notify_feature_flags_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('feature_flags_changed', TG_TABLE_NAME);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS feature_flags_notify ON feature_flags;
+CREATE TRIGGER feature_flags_notify
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON feature_flags
+FOR EACH STATEMENT EXECUTE FUNCTION notify_feature_flags_changed();
+
+DROP TRIGGER IF EXISTS feature_flag_users_notify ON feature_flag_users;
+CREATE TRIGGER feature_flag_users_notify
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON feature_flag_users
+FOR EACH STATEMENT EXECUTE FUNCTION notify_feature_flags_changed();
+
+-- Overrides of runtime settings plus their change history; the trigger notifies
+-- 'settings_changed' (SETTINGS_CHANNEL).
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value JSONB NOT NULL,
+    updated_by UUID NULL REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS settings_history (
+    id BIGSERIAL PRIMARY KEY,
+    key TEXT NOT NULL,
+    old_value JSONB NULL,
+    new_value JSONB NULL,
+    changed_by UUID NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS settings_history_key_idx ON settings_history (key, id);
+
+CREATE OR REPLACE FUNCTION notify_settings_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('settings_changed', TG_TABLE_NAME);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS settings_notify ON settings;
+CREATE TRIGGER settings_notify
+AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON settings
+FOR EACH STATEMENT EXECUTE FUNCTION notify_settings_changed();
+
+-- Actor and target ids are deliberately not foreign keys: events must outlive the users they mention.
+CREATE TABLE IF NOT EXISTS audit_events (
+    id BIGSERIAL PRIMARY KEY,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    event_type TEXT NOT NULL,
+    actor_id UUID NULL,
+    target_id UUID NULL,
+    ip TEXT NULL,
+    request_id TEXT NULL,
+    payload JSONB NOT NULL DEFAULT '{}'::jsonb,
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL UNIQUE
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at);
+CREATE INDEX IF NOT EXISTS audit_events_event_type_idx ON audit_events (event_type, id);
+CREATE INDEX IF NOT EXISTS audit_events_actor_id_idx ON audit_events (actor_id, id);
+CREATE INDEX IF NOT EXISTS audit_events_target_id_idx ON audit_events (target_id, id);
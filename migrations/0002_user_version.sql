@@ -0,0 +1,4 @@
+-- Optimistic concurrency for user edits. `version` increases whenever the
+-- profile, suspension or role changes; the API sends it as the ETag and rejects
+-- updates made against an older one.
+ALTER TABLE users ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
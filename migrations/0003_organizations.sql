@@ -0,0 +1,34 @@
+-- Organizations let several teams share one deployment. Accounts stay global;
+-- membership carries a role per organization, and invites add existing accounts.
+CREATE TABLE organizations (
+    id UUID PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    created_by UUID NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE organization_members (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role TEXT NOT NULL CHECK (role IN ('owner', 'admin', 'member')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX organization_members_user_id_idx ON organization_members (user_id);
+
+CREATE TABLE organization_invites (
+    id UUID PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    code TEXT NOT NULL UNIQUE,
+    email TEXT NULL,
+    role TEXT NOT NULL CHECK (role IN ('admin', 'member')),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_by UUID NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    accepted_by UUID NULL REFERENCES users(id) ON DELETE SET NULL,
+    accepted_at TIMESTAMPTZ NULL
+);
+
+CREATE INDEX organization_invites_organization_id_idx ON organization_invites (organization_id);
@@ -0,0 +1,69 @@
+-- Tenant tables are filtered by PostgreSQL itself, so a query that forgets its
+-- organization filter returns nothing instead of another tenant's rows. Requests
+-- set the context below per transaction (`db::tenant`); without it every policy
+-- denies. FORCE applies the policies to the table owner as well; superusers and
+-- BYPASSRLS roles still skip them.
+CREATE FUNCTION swarm_current_user() RETURNS UUID
+LANGUAGE sql STABLE
+AS $$ SELECT NULLIF(current_setting('swarm.current_user', true), '')::uuid $$;
+
+CREATE FUNCTION swarm_current_org() RETURNS UUID
+LANGUAGE sql STABLE
+AS $$ SELECT NULLIF(current_setting('swarm.current_org', true), '')::uuid $$;
+
+ALTER TABLE organizations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE organizations FORCE ROW LEVEL SECURITY;
+
+CREATE POLICY organizations_select ON organizations FOR SELECT
+    USING (
+        id = swarm_current_org()
+        OR EXISTS (
+            SELECT 1
+            FROM organization_members m
+            WHERE m.organization_id = organizations.id AND m.user_id = swarm_current_user()
+        )
+    );
+
+CREATE POLICY organizations_insert ON organizations FOR INSERT
+    WITH CHECK (created_by = swarm_current_user());
+
+ALTER TABLE organization_members ENABLE ROW LEVEL SECURITY;
+ALTER TABLE organization_members FORCE ROW LEVEL SECURITY;
+
+-- Own memberships in every organization, all members of the current one.
+CREATE POLICY organization_members_select ON organization_members FOR SELECT
+    USING (user_id = swarm_current_user() OR organization_id = swarm_current_org());
+
+-- Outside the current organization, only by redeeming an invite for that role.
+CREATE POLICY organization_members_insert ON organization_members FOR INSERT
+    WITH CHECK (
+        organization_id = swarm_current_org()
+        OR (
+            user_id = swarm_current_user()
+            AND EXISTS (
+                SELECT 1
+                FROM organization_invites i
+                WHERE i.organization_id = organization_members.organization_id
+                  AND i.accepted_by = swarm_current_user()
+                  AND i.role = organization_members.role
+            )
+        )
+    );
+
+ALTER TABLE organization_invites ENABLE ROW LEVEL SECURITY;
+ALTER TABLE organization_invites FORCE ROW LEVEL SECURITY;
+
+-- The invite code is the capability: it unlocks exactly one invite for redemption.
+CREATE POLICY organization_invites_select ON organization_invites FOR SELECT
+    USING (
+        organization_id = swarm_current_org()
+        OR accepted_by = swarm_current_user()
+        OR code = current_setting('swarm.invite_code', true)
+    );
+
+CREATE POLICY organization_invites_insert ON organization_invites FOR INSERT
+    WITH CHECK (organization_id = swarm_current_org());
+
+CREATE POLICY organization_invites_redeem ON organization_invites FOR UPDATE
+    USING (code = current_setting('swarm.invite_code', true))
+    WITH CHECK (accepted_by = swarm_current_user());
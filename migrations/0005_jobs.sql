@@ -0,0 +1,35 @@
+-- Durable background jobs. Workers claim due rows with FOR UPDATE SKIP LOCKED and
+-- hold them for a lease; a job whose worker died is claimed again once the lease
+-- runs out. Succeeded jobs are deleted, jobs out of attempts stay as 'dead'.
+CREATE TABLE jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'running', 'dead')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL CHECK (max_attempts > 0),
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- At most one pending job per key, e.g. for recurring maintenance.
+    unique_key TEXT NULL,
+    locked_by TEXT NULL,
+    locked_until TIMESTAMPTZ NULL,
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX jobs_due_idx ON jobs (run_at, id) WHERE status = 'pending';
+CREATE INDEX jobs_lease_idx ON jobs (locked_until) WHERE status = 'running';
+CREATE INDEX jobs_status_idx ON jobs (status, id);
+CREATE UNIQUE INDEX jobs_unique_key_idx ON jobs (unique_key) WHERE status = 'pending';
+
+CREATE FUNCTION notify_jobs_enqueued() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('jobs_enqueued', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER jobs_notify
+AFTER INSERT ON jobs
+FOR EACH STATEMENT EXECUTE FUNCTION notify_jobs_enqueued();
@@ -0,0 +1,28 @@
+-- Domain events appended in the same transaction as the change they describe and
+-- published to in-process subscribers by one dispatcher at a time. Delivered events
+-- are deleted; a failed event is retried later and holds back the events after it
+-- for the same aggregate, so each aggregate's events are delivered in order.
+CREATE TABLE outbox (
+    id BIGSERIAL PRIMARY KEY,
+    aggregate_type TEXT NOT NULL,
+    aggregate_id UUID NOT NULL,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT NULL
+);
+
+CREATE INDEX outbox_aggregate_idx ON outbox (aggregate_type, aggregate_id, id);
+
+CREATE FUNCTION notify_outbox_appended() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('outbox_appended', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER outbox_notify
+AFTER INSERT ON outbox
+FOR EACH STATEMENT EXECUTE FUNCTION notify_outbox_appended();
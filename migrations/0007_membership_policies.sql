@@ -0,0 +1,62 @@
+-- Being scoped to an organization is not enough to add members or issue invites:
+-- the caller's own membership must carry the role the handlers require. An owner
+-- row is only inserted by the organization's creator into a still empty
+-- organization; invites never grant ownership.
+CREATE FUNCTION swarm_current_org_role() RETURNS TEXT
+LANGUAGE sql STABLE
+AS $$
+    SELECT role
+    FROM organization_members
+    WHERE organization_id = swarm_current_org() AND user_id = swarm_current_user()
+$$;
+
+DROP POLICY organization_members_insert ON organization_members;
+
+CREATE POLICY organization_members_insert ON organization_members FOR INSERT
+    WITH CHECK (
+        (
+            organization_id = swarm_current_org()
+            AND role = 'owner'
+            AND user_id = swarm_current_user()
+            AND EXISTS (
+                SELECT 1
+                FROM organizations o
+                WHERE o.id = organization_members.organization_id
+                  AND o.created_by = swarm_current_user()
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM organization_members m
+                WHERE m.organization_id = organization_members.organization_id
+            )
+        )
+        OR (
+            organization_id = swarm_current_org()
+            AND (
+                (role = 'member' AND swarm_current_org_role() IN ('owner', 'admin'))
+                OR (role = 'admin' AND swarm_current_org_role() = 'owner')
+            )
+        )
+        OR (
+            user_id = swarm_current_user()
+            AND role <> 'owner'
+            AND EXISTS (
+                SELECT 1
+                FROM organization_invites i
+                WHERE i.organization_id = organization_members.organization_id
+                  AND i.accepted_by = swarm_current_user()
+                  AND i.role = organization_members.role
+            )
+        )
+    );
+
+DROP POLICY organization_invites_insert ON organization_invites;
+
+CREATE POLICY organization_invites_insert ON organization_invites FOR INSERT
+    WITH CHECK (
+        organization_id = swarm_current_org()
+        AND (
+            (role = 'member' AND swarm_current_org_role() IN ('owner', 'admin'))
+            OR (role = 'admin' AND swarm_current_org_role() = 'owner')
+        )
+    );
@@ -0,0 +1,53 @@
+-- SQLite counterpart of the tables the auth endpoints use. UUIDs are stored as
+-- 16-byte blobs and timestamps as RFC 3339 text, which is how sqlx encodes them.
+
+CREATE TABLE IF NOT EXISTS users (
+    id BLOB NOT NULL PRIMARY KEY,
+    nickname TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    token_version INTEGER NOT NULL DEFAULT 0,
+    nickname_canonical TEXT NOT NULL UNIQUE,
+    nickname_skeleton TEXT NOT NULL UNIQUE,
+    suspended_at TEXT NULL,
+    version INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (lower(email));
+
+CREATE TABLE IF NOT EXISTS invites (
+    id BLOB NOT NULL PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    email TEXT NULL,
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    max_uses INTEGER NOT NULL CHECK (max_uses > 0),
+    used_count INTEGER NOT NULL DEFAULT 0 CHECK (used_count >= 0),
+    expires_at TEXT NULL,
+    created_by BLOB NULL REFERENCES users(id) ON DELETE SET NULL,
+    created_at TEXT NOT NULL,
+    revoked_at TEXT NULL
+);
+
+CREATE TABLE IF NOT EXISTS password_tokens (
+    id BLOB NOT NULL PRIMARY KEY,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TEXT NOT NULL,
+    used_at TEXT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS audit_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    occurred_at TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    actor_id BLOB NULL,
+    target_id BLOB NULL,
+    ip TEXT NULL,
+    request_id TEXT NULL,
+    payload TEXT NOT NULL DEFAULT '{}',
+    prev_hash TEXT NOT NULL,
+    hash TEXT NOT NULL UNIQUE
+);
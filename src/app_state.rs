@@ -1,8 +1,53 @@
-use crate::auth::jwt::JwtService;
+use crate::{
+    auth::jwt::JwtService,
+    db::{health::DatabaseHealth, routing::DatabasePools, store::Store},
+    error::AppError,
+    features::FeatureFlags,
+    http::admin::stats::StatsCache,
+    settings::Settings,
+    validation::email::EmailDomainPolicy,
+};
 use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub db: PgPool,
+    /// `None` when running on SQLite; endpoints that don't go through `store` need PostgreSQL.
+    pub db: Option<DatabasePools>,
+    /// Users, invites, password tokens and audit events as used by `http::auth` and
+    /// the admin user, invite and audit endpoints.
+    pub store: Arc<dyn Store>,
+    pub db_health: DatabaseHealth,
     pub jwt: JwtService,
+    pub settings: Settings,
+    pub email_policy: Arc<EmailDomainPolicy>,
+    pub trust_proxy_headers: bool,
+    pub stats_cache: Arc<StatsCache>,
+    pub features: FeatureFlags,
+}
+
+impl AppState {
+    /// The primary: for writes and for reads that must see them.
+    pub fn pg(&self) -> Result<&PgPool, AppError> {
+        self.pools().map(DatabasePools::primary)
+    }
+
+    /// A replica for reads made on behalf of `user_id`, unless they wrote recently.
+    pub fn pg_read(&self, user_id: Uuid) -> Result<&PgPool, AppError> {
+        self.pools().map(|pools| pools.reader_for(user_id))
+    }
+
+    /// Keeps `user_id` reading from the primary until replicas have caught up.
+    pub fn record_write(&self, user_id: Uuid) {
+        if let Some(pools) = &self.db {
+            pools.record_write(user_id);
+        }
+    }
+
+    fn pools(&self) -> Result<&DatabasePools, AppError> {
+        self.db.as_ref().ok_or_else(|| {
+            AppError::ServiceUnavailable("this endpoint requires PostgreSQL".to_string())
+        })
+    }
 }
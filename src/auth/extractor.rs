@@ -4,19 +4,70 @@ use axum::{
 };
 use uuid::Uuid;
 
-use crate::{app_state::AppState, error::AppError};
+use crate::{
+    app_state::AppState,
+    db::{
+        organizations::{self, OrganizationRole},
+        tenant::{self, TenantContext},
+        users::UserRecord,
+    },
+    error::AppError,
+};
 
 #[derive(Debug, Clone)]
 pub struct AuthUser {
     pub id: Uuid,
-    pub nickname: String,
-    pub email: String,
-    pub is_admin: bool,
+    /// The row the token was checked against, read from the primary; handlers use
+    /// it instead of reading the user again.
+    pub user: UserRecord,
+    /// Organization selected in the token, not yet checked against membership.
+    pub organization_id: Option<Uuid>,
+}
+
+impl AuthUser {
+    /// Row-level security context for this user outside any organization.
+    pub fn tenant(&self) -> TenantContext {
+        TenantContext {
+            user_id: self.id,
+            organization_id: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct AdminUser(pub AuthUser);
 
+/// A member of the organization selected in the token. Membership and role are
+/// re-read from the primary on every request, so removed members lose access
+/// immediately, even while replicas lag. Handlers
+/// run their queries in a [`tenant::begin`] transaction for [`OrgMember::tenant`].
+#[derive(Debug, Clone)]
+pub struct OrgMember {
+    pub user: AuthUser,
+    pub organization_id: Uuid,
+    pub role: OrganizationRole,
+}
+
+impl OrgMember {
+    pub fn tenant(&self) -> TenantContext {
+        TenantContext {
+            user_id: self.user.id,
+            organization_id: Some(self.organization_id),
+        }
+    }
+
+    pub fn require_role(&self, role: OrganizationRole) -> Result<(), AppError> {
+        if self.role < role {
+            return Err(AppError::Forbidden(format!(
+                "organization role '{}' is required for this endpoint",
+                role.as_str()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 impl FromRequestParts<AppState> for AuthUser {
     type Rejection = AppError;
 
@@ -39,11 +90,33 @@ impl FromRequestParts<AppState> for AuthUser {
             .parse::<Uuid>()
             .map_err(|_| AppError::Unauthorized("token subject is not valid UUID".to_string()))?;
 
+        // Tokens are revoked by bumping `users.token_version`, so the claims are
+        // only trusted while they still match the stored user. Read from the primary:
+        // a lagging replica would still accept revoked tokens and suspended accounts.
+        let user = state
+            .store
+            .session()
+            .await?
+            .find_user_by_id(user_id)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("user from token no longer exists".to_string()))?;
+
+        if user.token_version != claims.ver {
+            return Err(AppError::Unauthorized("token has been revoked".to_string()));
+        }
+
+        if user.suspended_at.is_some() {
+            return Err(AppError::Forbidden("account is suspended".to_string()));
+        }
+
+        if !parts.method.is_safe() {
+            state.record_write(user.id);
+        }
+
         Ok(Self {
-            id: user_id,
-            nickname: claims.nickname,
-            email: claims.email,
-            is_admin: claims.is_admin,
+            id: user.id,
+            user,
+            organization_id: claims.org,
         })
     }
 }
@@ -54,7 +127,7 @@ impl FromRequestParts<AppState> for AdminUser {
     async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
         let auth_user = AuthUser::from_request_parts(parts, state).await?;
 
-        if !auth_user.is_admin {
+        if !auth_user.user.is_admin {
             return Err(AppError::Forbidden(
                 "admin role is required for this endpoint".to_string(),
             ));
@@ -63,3 +136,31 @@ impl FromRequestParts<AppState> for AdminUser {
         Ok(Self(auth_user))
     }
 }
+
+impl FromRequestParts<AppState> for OrgMember {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let user = AuthUser::from_request_parts(parts, state).await?;
+
+        let organization_id = user.organization_id.ok_or_else(|| {
+            AppError::BadRequest(
+                "no organization selected; switch to one with POST /orgs/switch".to_string(),
+            )
+        })?;
+
+        let mut tx = tenant::begin(state.pg()?, user.tenant()).await?;
+        let membership = organizations::find_membership(&mut *tx, organization_id, user.id)
+            .await?
+            .ok_or_else(|| {
+                AppError::Forbidden("not a member of the selected organization".to_string())
+            })?;
+        tx.commit().await?;
+
+        Ok(Self {
+            role: OrganizationRole::parse(&membership.role)?,
+            user,
+            organization_id,
+        })
+    }
+}
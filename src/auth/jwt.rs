@@ -2,11 +2,11 @@ use crate::{db::users::UserRecord, error::AppError};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Clone)]
 pub struct JwtService {
     secret: String,
-    ttl_seconds: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -15,27 +15,37 @@ pub struct Claims {
     pub nickname: String,
     pub email: String,
     pub is_admin: bool,
+    #[serde(default)]
+    pub ver: i32,
+    /// Organization selected via `POST /orgs/switch`; requests act within it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<Uuid>,
     pub iat: usize,
     pub exp: usize,
 }
 
 impl JwtService {
-    pub fn new(secret: String, ttl_seconds: i64) -> Self {
-        Self {
-            secret,
-            ttl_seconds,
-        }
+    pub fn new(secret: String) -> Self {
+        Self { secret }
     }
 
-    pub fn issue_token(&self, user: &UserRecord) -> Result<String, AppError> {
+    /// `ttl_seconds` comes from the runtime settings, so it can change without a restart.
+    pub fn issue_token(
+        &self,
+        user: &UserRecord,
+        organization_id: Option<Uuid>,
+        ttl_seconds: i64,
+    ) -> Result<String, AppError> {
         let now = Utc::now();
-        let exp = now + Duration::seconds(self.ttl_seconds);
+        let exp = now + Duration::seconds(ttl_seconds);
 
         let claims = Claims {
             sub: user.id.to_string(),
             nickname: user.nickname.clone(),
             email: user.email.clone(),
             is_admin: user.is_admin,
+            ver: user.token_version,
+            org: organization_id,
             iat: now.timestamp() as usize,
             exp: exp.timestamp() as usize,
         };
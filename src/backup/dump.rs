@@ -0,0 +1,106 @@
+use chrono::Utc;
+use flate2::{write::GzEncoder, Compression};
+use futures_util::TryStreamExt;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::io::Write;
+
+use super::{
+    backup_tables, ensure_bypasses_row_security, primary_key, schema_version, table_path,
+    Manifest, TableEntry, FORMAT, FORMAT_VERSION, MANIFEST_PATH,
+};
+use crate::{db::schema::spec::TableSpec, error::AppError};
+
+/// Writes every backed-up table from one consistent snapshot to `output`. Each table is
+/// held in memory while it is hashed and added to the archive; the manifest is
+/// the last entry.
+pub async fn write_backup(pool: &PgPool, output: impl Write) -> Result<Manifest, AppError> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    ensure_bypasses_row_security(&mut tx).await?;
+
+    let created_at = Utc::now();
+    let schema_version = schema_version(&mut tx).await?;
+    let mut archive = tar::Builder::new(GzEncoder::new(output, Compression::default()));
+    let mut tables = Vec::new();
+
+    for table in backup_tables() {
+        let (data, rows) = dump_table(&mut tx, table).await?;
+        let path = table_path(table.name);
+
+        append_file(&mut archive, &path, &data, created_at.timestamp()).map_err(write_error)?;
+
+        tables.push(TableEntry {
+            name: table.name.to_string(),
+            path,
+            rows,
+            sha256: format!("{:x}", Sha256::digest(&data)),
+        });
+    }
+
+    tx.commit().await?;
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        created_at,
+        tables,
+    };
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|error| AppError::Internal(format!("failed to encode backup manifest: {error}")))?;
+    append_file(&mut archive, MANIFEST_PATH, &manifest_json, created_at.timestamp()).map_err(write_error)?;
+
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|mut output| output.flush())
+        .map_err(write_error)?;
+
+    Ok(manifest)
+}
+
+/// One `to_jsonb` object per line, in primary key order so identical data
+/// produces identical files.
+async fn dump_table(conn: &mut PgConnection, table: &TableSpec) -> Result<(Vec<u8>, u64), AppError> {
+    let order_by = primary_key(table)
+        .map(|columns| format!(" ORDER BY {}", columns.join(", ")))
+        .unwrap_or_default();
+    let sql = format!("SELECT to_jsonb(t)::text FROM {} t{order_by}", table.name);
+
+    let mut data = Vec::new();
+    let mut rows = 0;
+    let mut stream = sqlx::query_scalar::<_, String>(&sql).fetch(conn);
+
+    while let Some(line) = stream.try_next().await? {
+        data.extend_from_slice(line.as_bytes());
+        data.push(b'\n');
+        rows += 1;
+    }
+
+    Ok((data, rows))
+}
+
+pub(super) fn append_file<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    mtime: i64,
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(mtime.max(0) as u64);
+    header.set_cksum();
+
+    archive.append_data(&mut header, path, data)
+}
+
+fn write_error(error: std::io::Error) -> AppError {
+    AppError::Internal(format!("failed to write backup: {error}"))
+}
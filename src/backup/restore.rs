@@ -0,0 +1,445 @@
+use flate2::read::GzDecoder;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use super::{
+    ensure_bypasses_row_security, schema_version, Manifest, TableEntry, FORMAT,
+    FORMAT_VERSION, MANIFEST_PATH, TRANSIENT_TABLES,
+};
+use crate::{
+    db::{
+        audit::{self, AuditEventType, NewAuditEvent},
+        schema::spec,
+    },
+    error::AppError,
+};
+
+const INSERT_BATCH_SIZE: usize = 500;
+
+pub struct RestoreOptions {
+    /// Tables to load; every table in the archive when empty.
+    pub tables: Vec<String>,
+    /// Empty the chosen tables first instead of refusing to load into tables with rows.
+    pub replace: bool,
+}
+
+pub struct RestoreReport {
+    pub manifest: Manifest,
+    /// Table names and row counts, in the order they were loaded.
+    pub restored: Vec<(String, u64)>,
+}
+
+/// Reads the whole archive and checks every table file against the manifest
+/// without touching the database.
+pub fn verify_archive(path: &Path) -> Result<Manifest, AppError> {
+    let mut archive = open_archive(path)?;
+    let mut manifest = None;
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries().map_err(read_error)? {
+        let mut entry = entry.map_err(read_error)?;
+        let entry_path = entry.path().map_err(read_error)?.to_string_lossy().into_owned();
+
+        if entry_path == MANIFEST_PATH {
+            let mut json = Vec::new();
+            entry.read_to_end(&mut json).map_err(read_error)?;
+            manifest = Some(serde_json::from_slice::<Manifest>(&json).map_err(|error| {
+                AppError::BadRequest(format!("backup manifest is invalid: {error}"))
+            })?);
+        } else {
+            let (sha256, rows) = digest(&mut entry)?;
+            files.insert(entry_path, (sha256, rows));
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| AppError::BadRequest("archive has no manifest.json".to_string()))?;
+
+    if manifest.format != FORMAT || manifest.format_version > FORMAT_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "unsupported archive format '{}' version {}; this build reads '{FORMAT}' up to version {FORMAT_VERSION}",
+            manifest.format, manifest.format_version
+        )));
+    }
+
+    for table in &manifest.tables {
+        match files.remove(&table.path) {
+            Some((sha256, rows)) if sha256 == table.sha256 && rows == table.rows => {}
+            Some(_) => {
+                return Err(AppError::BadRequest(format!(
+                    "{} does not match its checksum in the manifest",
+                    table.path
+                )));
+            }
+            None => return Err(AppError::BadRequest(format!("archive is missing {}", table.path))),
+        }
+    }
+
+    if let Some(extra) = files.keys().next() {
+        return Err(AppError::BadRequest(format!("{extra} is not listed in the manifest")));
+    }
+
+    Ok(manifest)
+}
+
+/// Verifies the archive, then loads the chosen tables in one transaction that
+/// also records the restore in the audit log. Any failure leaves the database as it was.
+pub async fn restore_backup(
+    pool: &PgPool,
+    path: &Path,
+    options: RestoreOptions,
+    event: impl Fn(AuditEventType) -> NewAuditEvent,
+) -> Result<RestoreReport, AppError> {
+    let manifest = verify_archive(path)?;
+
+    let known: Vec<&str> = spec::data_tables().map(|table| table.name).collect();
+    if let Some(unknown) = manifest.tables.iter().find(|table| !known.contains(&table.name.as_str())) {
+        return Err(AppError::SchemaMismatch(format!(
+            "archive contains table '{}', which this build does not know",
+            unknown.name
+        )));
+    }
+
+    if let Some(transient) = options
+        .tables
+        .iter()
+        .find(|name| TRANSIENT_TABLES.contains(&name.as_str()))
+    {
+        return Err(AppError::BadRequest(format!(
+            "'{transient}' holds work in flight and is never restored"
+        )));
+    }
+
+    if let Some(missing) = options
+        .tables
+        .iter()
+        .find(|name| !manifest.tables.iter().any(|table| &table.name == *name))
+    {
+        return Err(AppError::BadRequest(format!("archive has no table '{missing}'")));
+    }
+
+    // Archives from before transient tables were left out may still contain them.
+    let selected: Vec<&TableEntry> = manifest
+        .tables
+        .iter()
+        .filter(|table| !TRANSIENT_TABLES.contains(&table.name.as_str()))
+        .filter(|table| options.tables.is_empty() || options.tables.contains(&table.name))
+        .collect();
+
+    let mut tx = pool.begin().await?;
+
+    let current_version = schema_version(&mut tx).await?;
+    if manifest.schema_version != current_version {
+        return Err(AppError::SchemaMismatch(format!(
+            "archive was taken at schema version {}, but the database is at version {current_version}; restore it with a matching build",
+            manifest.schema_version
+        )));
+    }
+
+    let row_security = spec::data_tables().any(|table| {
+        table.row_security && selected.iter().any(|entry| entry.name == table.name)
+    });
+    if row_security {
+        ensure_bypasses_row_security(&mut tx).await?;
+    }
+
+    let names: Vec<&str> = selected.iter().map(|table| table.name.as_str()).collect();
+    if options.replace {
+        truncate(&mut tx, &names).await?;
+    } else {
+        ensure_empty(&mut tx, &names).await?;
+    }
+
+    // Second pass; the tar entries come in manifest order, parents first.
+    let mut archive = open_archive(path)?;
+    let mut restored = Vec::new();
+
+    for entry in archive.entries().map_err(read_error)? {
+        let entry = entry.map_err(read_error)?;
+        let entry_path = entry.path().map_err(read_error)?.to_string_lossy().into_owned();
+        let Some(table) = selected.iter().find(|table| table.path == entry_path) else {
+            continue;
+        };
+
+        let rows = load_table(&mut tx, table, entry).await?;
+        restored.push((table.name.clone(), rows));
+    }
+
+    let tables: serde_json::Map<_, _> = restored
+        .iter()
+        .map(|(name, rows)| (name.clone(), json!(rows)))
+        .collect();
+    audit::record_event(
+        &mut tx,
+        event(AuditEventType::DatabaseRestored).payload(json!({
+            "archive_created_at": manifest.created_at,
+            "schema_version": manifest.schema_version,
+            "replace": options.replace,
+            "tables": tables,
+        })),
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(RestoreReport { manifest, restored })
+}
+
+/// Inserts the file in batches and moves the table's sequences past the restored
+/// ids. The file is hashed again while loading, in case it changed since it was verified.
+async fn load_table(conn: &mut PgConnection, table: &TableEntry, entry: impl Read) -> Result<u64, AppError> {
+    let sql = format!(
+        "INSERT INTO {name} SELECT * FROM jsonb_populate_recordset(NULL::{name}, $1::jsonb)",
+        name = table.name
+    );
+
+    let mut hasher = Sha256::new();
+    let mut rows = 0;
+    let mut batch = Vec::with_capacity(INSERT_BATCH_SIZE);
+    let mut reader = BufReader::new(entry);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let read = reader.read_line(&mut line).map_err(read_error)?;
+        hasher.update(line.as_bytes());
+
+        let row = line.trim_end();
+        if !row.is_empty() {
+            batch.push(row.to_string());
+            rows += 1;
+        }
+
+        if batch.len() == INSERT_BATCH_SIZE || (read == 0 && !batch.is_empty()) {
+            sqlx::query(&sql)
+                .bind(format!("[{}]", batch.join(",")))
+                .execute(&mut *conn)
+                .await?;
+            batch.clear();
+        }
+
+        if read == 0 {
+            break;
+        }
+    }
+
+    if format!("{:x}", hasher.finalize()) != table.sha256 {
+        return Err(AppError::BadRequest(format!(
+            "{} changed while it was being restored",
+            table.path
+        )));
+    }
+
+    reset_sequences(conn, &table.name).await?;
+
+    Ok(rows)
+}
+
+async fn reset_sequences(conn: &mut PgConnection, table: &str) -> Result<(), AppError> {
+    let columns: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT column_name::text
+        FROM information_schema.columns
+        WHERE table_schema = 'public' AND table_name = $1 AND column_default LIKE 'nextval(%'
+        "#,
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for column in columns {
+        sqlx::query(&format!(
+            "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({column}), 0) + 1, false) FROM {table}"
+        ))
+        .bind(table)
+        .bind(&column)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Tables referenced by ones left out fail the truncate; restoring them needs
+/// the referencing tables too.
+async fn truncate(conn: &mut PgConnection, tables: &[&str]) -> Result<(), AppError> {
+    sqlx::query(&format!("TRUNCATE {}", tables.join(", ")))
+        .execute(conn)
+        .await
+        .map_err(|error| match error {
+            sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("0A000") => {
+                AppError::BadRequest(format!(
+                    "{}; add the referencing tables with --table",
+                    db_error.message()
+                ))
+            }
+            other => AppError::from(other),
+        })?;
+
+    Ok(())
+}
+
+async fn ensure_empty(conn: &mut PgConnection, tables: &[&str]) -> Result<(), AppError> {
+    let mut non_empty = Vec::new();
+
+    for table in tables {
+        let has_rows: bool = sqlx::query_scalar(&format!("SELECT EXISTS (SELECT 1 FROM {table})"))
+            .fetch_one(&mut *conn)
+            .await?;
+        if has_rows {
+            non_empty.push(*table);
+        }
+    }
+
+    if non_empty.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Conflict(format!(
+            "{} already contain rows; pass --replace to overwrite them",
+            non_empty.join(", ")
+        )))
+    }
+}
+
+fn open_archive(path: &Path) -> Result<tar::Archive<GzDecoder<File>>, AppError> {
+    let file = File::open(path).map_err(|error| {
+        AppError::BadRequest(format!("failed to open {}: {error}", path.display()))
+    })?;
+
+    Ok(tar::Archive::new(GzDecoder::new(file)))
+}
+
+/// SHA-256 and line count of a table file.
+fn digest(reader: impl Read) -> Result<(String, u64), AppError> {
+    let mut hasher = Sha256::new();
+    let mut rows = 0;
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let buffer = reader.fill_buf().map_err(read_error)?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        hasher.update(buffer);
+        rows += buffer.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        let length = buffer.len();
+        reader.consume(length);
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), rows))
+}
+
+fn read_error(error: std::io::Error) -> AppError {
+    AppError::BadRequest(format!("failed to read backup archive: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use flate2::{write::GzEncoder, Compression};
+    use sha2::{Digest, Sha256};
+    use std::path::PathBuf;
+
+    use super::verify_archive;
+    use crate::{
+        backup::{dump::append_file, Manifest, TableEntry, FORMAT, FORMAT_VERSION, MANIFEST_PATH},
+        error::AppError,
+    };
+
+    const USERS: &[u8] = b"{\"id\": 1}\n{\"id\": 2}\n";
+
+    fn entry(path: &str, data: &[u8]) -> TableEntry {
+        TableEntry {
+            name: "users".to_string(),
+            path: path.to_string(),
+            rows: data.iter().filter(|&&byte| byte == b'\n').count() as u64,
+            sha256: format!("{:x}", Sha256::digest(data)),
+        }
+    }
+
+    /// Writes `files` followed by a manifest listing `tables` to a fresh temporary file.
+    fn write_archive(name: &str, files: &[(&str, &[u8])], tables: Vec<TableEntry>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("swarm-{name}-{}.tar.gz", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::fast()));
+
+        for (file_path, data) in files {
+            append_file(&mut archive, file_path, data, 0).unwrap();
+        }
+
+        let manifest = Manifest {
+            format: FORMAT.to_string(),
+            format_version: FORMAT_VERSION,
+            app_version: "test".to_string(),
+            schema_version: 1,
+            created_at: Utc::now(),
+            tables,
+        };
+        append_file(&mut archive, MANIFEST_PATH, &serde_json::to_vec(&manifest).unwrap(), 0).unwrap();
+        archive.into_inner().unwrap().finish().unwrap();
+
+        path
+    }
+
+    fn verify_error(path: PathBuf) -> String {
+        let result = verify_archive(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        match result {
+            Err(AppError::BadRequest(message)) => message,
+            Err(other) => panic!("expected a bad request, got {other:?}"),
+            Ok(_) => panic!("expected the archive to be rejected"),
+        }
+    }
+
+    #[test]
+    fn accepts_an_intact_archive() {
+        let path = write_archive(
+            "intact",
+            &[("tables/users.jsonl", USERS)],
+            vec![entry("tables/users.jsonl", USERS)],
+        );
+
+        let manifest = verify_archive(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest.tables.len(), 1);
+        assert_eq!(manifest.tables[0].rows, 2);
+    }
+
+    #[test]
+    fn rejects_a_tampered_file() {
+        let path = write_archive(
+            "tampered",
+            &[("tables/users.jsonl", b"{\"id\": 1}\n{\"id\": 3}\n")],
+            vec![entry("tables/users.jsonl", USERS)],
+        );
+
+        assert!(verify_error(path).contains("does not match its checksum"));
+    }
+
+    #[test]
+    fn rejects_a_missing_file() {
+        let path = write_archive("missing", &[], vec![entry("tables/users.jsonl", USERS)]);
+
+        assert!(verify_error(path).contains("archive is missing tables/users.jsonl"));
+    }
+
+    #[test]
+    fn rejects_an_unlisted_file() {
+        let path = write_archive(
+            "extra",
+            &[("tables/users.jsonl", USERS), ("tables/extra.jsonl", b"{}\n")],
+            vec![entry("tables/users.jsonl", USERS)],
+        );
+
+        assert!(verify_error(path).contains("tables/extra.jsonl is not listed in the manifest"));
+    }
+}
@@ -0,0 +1,92 @@
+use futures_util::{stream, Stream};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    bulk::BulkFormat,
+    db::{
+        store::Store,
+        users::{SortDirection, UserListFilter, UserListQuery, UserSortField},
+    },
+    error::AppError,
+    models::PublicUser,
+};
+
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// Every user as `PublicUser`, oldest first, serialized batch by batch so the
+/// whole table is never held in memory. CSV output starts with a header row
+/// (derived from the first record, so an empty export is empty). Each batch is
+/// read through [`Store::read_session`] for `reader`.
+pub fn export_users(
+    store: Arc<dyn Store>,
+    reader: Uuid,
+    format: BulkFormat,
+) -> impl Stream<Item = Result<Vec<u8>, AppError>> {
+    stream::unfold(Some((None, true)), move |state| {
+        let store = store.clone();
+        async move {
+            let (after, first) = state?;
+
+            let query = UserListQuery {
+                filter: UserListFilter::default(),
+                sort: UserSortField::CreatedAt,
+                direction: SortDirection::Asc,
+                after,
+                limit: EXPORT_BATCH_SIZE,
+            };
+            let page = async { store.read_session(reader).await?.list_users(query).await };
+            let page = match page.await {
+                Ok(page) => page,
+                Err(error) => return Some((Err(error), None)),
+            };
+
+            if page.users.is_empty() && !first {
+                return None;
+            }
+
+            let records = page.users.into_iter().map(PublicUser::from);
+            let chunk = match serialize_batch(format, records, first) {
+                Ok(chunk) => chunk,
+                Err(error) => return Some((Err(error), None)),
+            };
+
+            let next = page.next_cursor.map(|cursor| (Some(cursor), false));
+            Some((Ok(chunk), next))
+        }
+    })
+}
+
+fn serialize_batch(
+    format: BulkFormat,
+    records: impl Iterator<Item = PublicUser>,
+    with_header: bool,
+) -> Result<Vec<u8>, AppError> {
+    let serialize_error =
+        |error: &dyn std::fmt::Display| AppError::Internal(format!("failed to serialize user: {error}"));
+
+    match format {
+        BulkFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(with_header)
+                .from_writer(Vec::new());
+
+            for record in records {
+                writer.serialize(record).map_err(|error| serialize_error(&error))?;
+            }
+
+            writer
+                .into_inner()
+                .map_err(|error| serialize_error(&error))
+        }
+        BulkFormat::Jsonl => {
+            let mut chunk = Vec::new();
+            for record in records {
+                serde_json::to_writer(&mut chunk, &record).map_err(|error| serialize_error(&error))?;
+                chunk.push(b'\n');
+            }
+
+            Ok(chunk)
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{Duration, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{Connection, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    bulk::BulkFormat,
+    db::{
+        audit::{self, AuditEventType, NewAuditEvent},
+        invites::{self, NewInvite},
+        users::{self, NewUser},
+    },
+    error::AppError,
+    jobs::Job,
+    validation::{
+        nickname::{canonical_nickname, nickname_skeleton},
+        normalize_and_validate_email, validate_nickname, validate_password,
+    },
+};
+
+pub const MAX_IMPORT_ROWS: usize = 5_000;
+
+const INVITE_TTL: Duration = Duration::days(7);
+
+/// Placeholder for imports that are rolled back anyway (dry runs, or files with
+/// invalid rows), which skip bcrypt.
+const ROLLBACK_PASSWORD_HASH: &str = "rolled-back";
+
+/// One input row. CSV files need a header line with these column names.
+#[derive(Debug, Deserialize)]
+pub struct ImportRow {
+    pub nickname: String,
+    pub email: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub is_admin: Option<bool>,
+}
+
+/// What the imported people receive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportNotification {
+    /// Accounts are created with the password from the file; nothing is sent.
+    #[default]
+    None,
+    /// Accounts are created without a usable password and a set-password link is emailed.
+    SetPassword,
+    /// No accounts are created; an email-bound invite is issued and emailed instead.
+    Invite,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    pub format: BulkFormat,
+    pub dry_run: bool,
+    pub notify: ImportNotification,
+    pub actor_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedRow {
+    pub line: u64,
+    pub nickname: String,
+    pub email: String,
+    pub is_admin: bool,
+    /// Set when an account was created (not for invites or dry runs).
+    pub user_id: Option<Uuid>,
+    pub invite_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    pub line: u64,
+    pub email: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub notify: ImportNotification,
+    /// `false` for dry runs and whenever any row failed: imports are all-or-nothing.
+    pub committed: bool,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub rows: Vec<ImportedRow>,
+    pub errors: Vec<RowError>,
+    /// Emails handed to the job queue; they go out once the import has committed.
+    pub emails_queued: usize,
+}
+
+/// Validates and imports `data`. Every row goes through the same validators and
+/// `users::create_user` uniqueness checks as registration, each in its own
+/// savepoint, so one bad row is reported without hiding problems in the others.
+/// `event` builds the audit event for each imported row.
+///
+/// Passwords are hashed before the transaction opens: its first audit event takes
+/// the audit chain lock, which must not be held through thousands of bcrypt runs.
+pub async fn import_users(
+    pool: &PgPool,
+    data: &[u8],
+    options: ImportOptions,
+    event: impl Fn(AuditEventType) -> NewAuditEvent,
+) -> Result<ImportReport, AppError> {
+    let parsed = parse_rows(options.format, data)?;
+    if parsed.len() > MAX_IMPORT_ROWS {
+        return Err(AppError::BadRequest(format!(
+            "imports are limited to {MAX_IMPORT_ROWS} rows"
+        )));
+    }
+
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        notify: options.notify,
+        committed: false,
+        total_rows: parsed.len(),
+        valid_rows: 0,
+        rows: Vec::new(),
+        errors: Vec::new(),
+        emails_queued: 0,
+    };
+
+    let mut seen = SeenRows::default();
+    let mut prepared = Vec::with_capacity(parsed.len());
+
+    for (line, row) in parsed {
+        let row = match row {
+            Ok(row) => row,
+            Err(error) => {
+                report.errors.push(RowError { line, email: None, error });
+                continue;
+            }
+        };
+
+        let email = Some(row.email.trim().to_string());
+        match prepare_row(line, row, options, &mut seen) {
+            Ok(row) => prepared.push(row),
+            Err(error) => report.errors.push(RowError {
+                line,
+                email,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    let rolls_back = options.dry_run || !report.errors.is_empty();
+    let unusable_password_hash = if rolls_back || options.notify == ImportNotification::None {
+        None
+    } else {
+        Some(unusable_password_hash().await?)
+    };
+
+    let mut hashed = Vec::with_capacity(prepared.len());
+    for mut row in prepared {
+        let password_hash = match (row.password.take(), &unusable_password_hash) {
+            _ if rolls_back => ROLLBACK_PASSWORD_HASH.to_string(),
+            (Some(password), _) => hash_password(password).await?,
+            (None, Some(hash)) => hash.clone(),
+            (None, None) => {
+                return Err(AppError::Internal("imported row has no password".to_string()));
+            }
+        };
+        hashed.push((row, password_hash));
+    }
+
+    let mut emails_queued = 0;
+
+    let mut tx = pool.begin().await?;
+
+    for (row, password_hash) in hashed {
+        let line = row.line;
+        let email = Some(row.email.clone());
+        match import_row(&mut tx, row, password_hash, options, &event).await {
+            Ok((imported, email_queued)) => {
+                report.rows.push(imported);
+                emails_queued += usize::from(email_queued);
+            }
+            Err(error) if is_row_error(&error) => report.errors.push(RowError {
+                line,
+                email,
+                error: error.to_string(),
+            }),
+            Err(error) => return Err(error),
+        }
+    }
+
+    report.valid_rows = report.rows.len();
+    report.errors.sort_by_key(|error| error.line);
+
+    if options.dry_run || !report.errors.is_empty() {
+        tx.rollback().await?;
+        if !options.dry_run {
+            for row in &mut report.rows {
+                row.user_id = None;
+                row.invite_id = None;
+            }
+        }
+        return Ok(report);
+    }
+
+    tx.commit().await?;
+    report.committed = true;
+    report.emails_queued = emails_queued;
+
+    Ok(report)
+}
+
+/// A row that passed validation and the in-file duplicate checks.
+struct PreparedRow {
+    line: u64,
+    nickname: String,
+    email: String,
+    is_admin: bool,
+    /// Plaintext from the file; `None` when the password is set by email.
+    password: Option<String>,
+}
+
+fn prepare_row(
+    line: u64,
+    row: ImportRow,
+    options: ImportOptions,
+    seen: &mut SeenRows,
+) -> Result<PreparedRow, AppError> {
+    let email = normalize_and_validate_email(&row.email)?;
+    let nickname = validate_nickname(&row.nickname)?;
+    let is_admin = row.is_admin.unwrap_or(false);
+    let password = row.password.filter(|password| !password.is_empty());
+
+    if let Some(previous) = seen.emails.insert(email.to_lowercase(), line) {
+        return Err(AppError::Conflict(format!("email duplicates line {previous}")));
+    }
+    let skeleton = nickname_skeleton(&canonical_nickname(&nickname));
+    if let Some(previous) = seen.skeletons.insert(skeleton, line) {
+        return Err(AppError::Conflict(format!(
+            "nickname is too similar to the one on line {previous}"
+        )));
+    }
+
+    match (options.notify, &password) {
+        (ImportNotification::None, None) => {
+            return Err(AppError::BadRequest(
+                "password is required when no email is sent".to_string(),
+            ));
+        }
+        (ImportNotification::None, Some(password)) => validate_password(password)?,
+        (_, Some(_)) => {
+            return Err(AppError::BadRequest(
+                "password must be empty when emails are sent".to_string(),
+            ));
+        }
+        (_, None) => {}
+    }
+
+    Ok(PreparedRow {
+        line,
+        nickname,
+        email,
+        is_admin,
+        password,
+    })
+}
+
+async fn import_row(
+    tx: &mut Transaction<'_, Postgres>,
+    row: PreparedRow,
+    password_hash: String,
+    options: ImportOptions,
+    event: &impl Fn(AuditEventType) -> NewAuditEvent,
+) -> Result<(ImportedRow, bool), AppError> {
+    let PreparedRow {
+        line,
+        nickname,
+        email,
+        is_admin,
+        ..
+    } = row;
+
+    let mut savepoint = tx.begin().await?;
+
+    let user = users::create_user(
+        &mut savepoint,
+        NewUser {
+            nickname: nickname.clone(),
+            email: email.clone(),
+            password_hash,
+            is_admin,
+        },
+    )
+    .await?;
+
+    let mut imported = ImportedRow {
+        line,
+        nickname: nickname.clone(),
+        email: email.clone(),
+        is_admin,
+        user_id: None,
+        invite_id: None,
+    };
+
+    // Invites only needed the uniqueness checks; the account is created at registration.
+    if options.notify == ImportNotification::Invite {
+        savepoint.rollback().await?;
+
+        let invite = invites::create_invite(
+            &mut **tx,
+            NewInvite {
+                code: invites::generate_invite_code(),
+                email: Some(email.clone()),
+                is_admin,
+                max_uses: 1,
+                expires_at: Some(Utc::now() + INVITE_TTL),
+                created_by: options.actor_id,
+            },
+        )
+        .await?;
+
+        audit::record_event(
+            tx,
+            event(AuditEventType::InviteCreated).payload(json!({
+                "source": "import",
+                "invite_id": invite.id,
+                "email": invite.email,
+                "is_admin": invite.is_admin,
+                "max_uses": invite.max_uses,
+                "expires_at": invite.expires_at,
+            })),
+        )
+        .await?;
+
+        Job::SendInviteEmail {
+            invite_id: invite.id,
+            nickname: Some(nickname),
+        }
+        .enqueue(&mut **tx)
+        .await?;
+
+        imported.invite_id = Some(invite.id);
+
+        return Ok((imported, true));
+    }
+
+    let email_queued = options.notify == ImportNotification::SetPassword;
+    if email_queued {
+        Job::SendSetPasswordEmail { user_id: user.id }
+            .enqueue(&mut *savepoint)
+            .await?;
+    }
+
+    audit::record_event(
+        &mut savepoint,
+        event(AuditEventType::UserImported).target(user.id).payload(json!({
+            "nickname": user.nickname,
+            "email": user.email,
+            "is_admin": user.is_admin,
+            "notify": options.notify,
+        })),
+    )
+    .await?;
+
+    savepoint.commit().await?;
+
+    imported.user_id = Some(user.id);
+
+    Ok((imported, email_queued))
+}
+
+/// Per-import state for spotting duplicates inside the file itself.
+#[derive(Default)]
+struct SeenRows {
+    emails: HashMap<String, u64>,
+    skeletons: HashMap<String, u64>,
+}
+
+/// Hash of a random secret that is never stored or shown, shared by every account
+/// of one import that must set its password by email.
+async fn unusable_password_hash() -> Result<String, AppError> {
+    let secret = format!("{:x}", rand::random::<u128>());
+    hash_password(secret).await
+}
+
+/// Rejected input is reported per row; anything else (database or hashing
+/// failures) aborts the whole import.
+fn is_row_error(error: &AppError) -> bool {
+    matches!(
+        error,
+        AppError::BadRequest(_) | AppError::Conflict(_) | AppError::Forbidden(_)
+    )
+}
+
+/// Input line number and the row, or why it could not be parsed.
+type ParsedRow = (u64, Result<ImportRow, String>);
+
+/// Parses every row, keeping per-row errors instead of failing the whole file.
+/// Line numbers refer to the input, counting a CSV header as line 1.
+fn parse_rows(format: BulkFormat, data: &[u8]) -> Result<Vec<ParsedRow>, AppError> {
+    match format {
+        BulkFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(data);
+
+            let headers = reader
+                .headers()
+                .map_err(|error| AppError::BadRequest(format!("CSV header is invalid: {error}")))?
+                .clone();
+
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                let row = match record {
+                    Ok(record) => {
+                        let line = record.position().map_or(0, |position| position.line());
+                        let row = record
+                            .deserialize::<ImportRow>(Some(&headers))
+                            .map_err(|error| format!("row is malformed: {error}"));
+                        (line, row)
+                    }
+                    Err(error) => {
+                        let line = error.position().map_or(0, |position| position.line());
+                        (line, Err(format!("row is malformed: {error}")))
+                    }
+                };
+                rows.push(row);
+            }
+
+            Ok(rows)
+        }
+        BulkFormat::Jsonl => {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| AppError::BadRequest("JSONL input must be UTF-8".to_string()))?;
+
+            Ok(text
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    let row = serde_json::from_str::<ImportRow>(line)
+                        .map_err(|error| format!("row is malformed: {error}"));
+                    (index as u64 + 1, row)
+                })
+                .collect())
+        }
+    }
+}
+
+async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || hash(password, DEFAULT_COST))
+        .await
+        .map_err(|error| AppError::Internal(format!("password hashing task failed: {error}")))?
+        .map_err(AppError::from)
+}
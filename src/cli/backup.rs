@@ -0,0 +1,134 @@
+use chrono::Utc;
+use clap::Args;
+use sqlx::PgPool;
+use std::{
+    fs::{File, OpenOptions},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use super::users::cli_event;
+use crate::{
+    backup::{
+        dump::write_backup,
+        restore::{restore_backup, verify_archive, RestoreOptions},
+        Manifest,
+    },
+    error::AppError,
+};
+
+#[derive(Debug, Args)]
+pub struct BackupArgs {
+    /// Archive to write; swarm-backup-<timestamp>.tar.gz in the current directory when omitted
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Archive written by `swarm backup`
+    pub file: PathBuf,
+    /// Restore only this table; repeat for several. Every table when omitted
+    #[arg(long = "table", value_name = "TABLE")]
+    pub tables: Vec<String>,
+    /// Empty the restored tables first; without it, tables that have rows are refused
+    #[arg(long)]
+    pub replace: bool,
+    /// Verify the archive's checksums and print its manifest without restoring
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub async fn backup(pool: &PgPool, args: BackupArgs) -> Result<(), AppError> {
+    let output = args.output.unwrap_or_else(|| {
+        PathBuf::from(format!("swarm-backup-{}.tar.gz", Utc::now().format("%Y%m%dT%H%M%SZ")))
+    });
+
+    // Written next to the target and renamed, so a failed backup never leaves a
+    // truncated archive under the final name.
+    let mut partial = output.clone().into_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let write_error = |error: std::io::Error| AppError::Internal(format!("failed to write backup: {error}"));
+
+    let file = create_private(&partial).map_err(write_error)?;
+    let manifest = match write_backup(pool, BufWriter::new(file)).await {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(error);
+        }
+    };
+    std::fs::rename(&partial, &output).map_err(write_error)?;
+
+    print_tables(&manifest);
+    println!(
+        "backed up {} tables at schema version {} to {}",
+        manifest.tables.len(),
+        manifest.schema_version,
+        output.display()
+    );
+
+    Ok(())
+}
+
+pub async fn restore(pool: &PgPool, args: RestoreArgs) -> Result<(), AppError> {
+    if args.dry_run {
+        let manifest = verify_archive(&args.file)?;
+        print_tables(&manifest);
+        println!(
+            "archive from {} at schema version {} is intact; nothing was restored",
+            manifest.created_at.to_rfc3339(),
+            manifest.schema_version
+        );
+        return Ok(());
+    }
+
+    let report = restore_backup(
+        pool,
+        &args.file,
+        RestoreOptions {
+            tables: args.tables,
+            replace: args.replace,
+        },
+        cli_event,
+    )
+    .await?;
+
+    for (name, rows) in &report.restored {
+        println!("{name:<24} {rows:>10} rows");
+    }
+    println!(
+        "restored {} tables from the backup taken {}",
+        report.restored.len(),
+        report.manifest.created_at.to_rfc3339()
+    );
+
+    Ok(())
+}
+
+/// The archive holds password and token hashes and plaintext invite codes, so only
+/// its owner may read it.
+fn create_private(path: &Path) -> std::io::Result<File> {
+    #[cfg(unix)]
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let file = options.open(path)?;
+    // `mode` only applies when the file is created; a leftover partial keeps its own.
+    #[cfg(unix)]
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+
+    Ok(file)
+}
+
+fn print_tables(manifest: &Manifest) {
+    for table in &manifest.tables {
+        println!("{:<24} {:>10} rows  sha256 {}", table.name, table.rows, table.sha256);
+    }
+}
@@ -0,0 +1,110 @@
+use clap::{Args, Subcommand};
+use sqlx::PgPool;
+
+use crate::{
+    config::{AppConfig, SchemaCheckMode},
+    db::{migrations, schema},
+    error::AppError,
+};
+
+#[derive(Debug, Args)]
+pub struct MigrateArgs {
+    #[command(subcommand)]
+    pub command: Option<MigrateCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply pending migrations (default)
+    Apply,
+    /// Show every migration and whether it has been applied
+    List,
+    /// Fail unless every migration is applied with a matching checksum
+    Verify,
+}
+
+#[derive(Debug, Args)]
+pub struct CheckSchemaArgs {
+    /// Fail on extra tables, columns, constraints and indexes too (overrides SCHEMA_CHECK_MODE)
+    #[arg(long)]
+    pub strict: bool,
+}
+
+pub async fn migrate(pool: &PgPool, config: &AppConfig, args: MigrateArgs) -> Result<(), AppError> {
+    match args.command.unwrap_or(MigrateCommand::Apply) {
+        MigrateCommand::Apply => {
+            let applied = migrations::run_migrations(pool).await?;
+            schema::check_schema(pool, config.schema_check_mode).await?;
+            println!("applied {applied} migration(s); database schema is up to date");
+        }
+        MigrateCommand::List => {
+            for status in migrations::migration_status(pool).await? {
+                let applied_at = status
+                    .applied_at
+                    .map(|applied_at| applied_at.to_rfc3339())
+                    .unwrap_or_default();
+
+                println!(
+                    "{:04}_{:<32} {:<18} {applied_at}",
+                    status.version,
+                    status.name,
+                    status.state.as_str()
+                );
+            }
+        }
+        MigrateCommand::Verify => {
+            let problems: Vec<String> = migrations::migration_status(pool)
+                .await?
+                .into_iter()
+                .filter(|status| status.state.is_problem())
+                .map(|status| {
+                    format!(
+                        "{:04}_{}: {}",
+                        status.version,
+                        status.name,
+                        status.state.as_str()
+                    )
+                })
+                .collect();
+
+            if !problems.is_empty() {
+                return Err(AppError::SchemaMismatch(problems.join("; ")));
+            }
+
+            println!("all {} migration(s) applied and verified", migrations::MIGRATIONS.len());
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn check_schema(
+    pool: &PgPool,
+    config: &AppConfig,
+    args: CheckSchemaArgs,
+) -> Result<(), AppError> {
+    let mode = if args.strict {
+        SchemaCheckMode::Strict
+    } else {
+        config.schema_check_mode
+    };
+
+    let report = schema::schema_report(pool).await?;
+
+    for drift in &report.drifts {
+        let marker = if drift.is_blocking(mode) { "error" } else { "allowed" };
+        println!("{marker:<8} {drift}");
+    }
+
+    let problems = report.blocking(mode).count();
+
+    if problems > 0 {
+        return Err(AppError::SchemaMismatch(format!(
+            "{problems} difference(s) from the expected schema"
+        )));
+    }
+
+    println!("database schema is valid");
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+pub mod backup;
+pub mod migrate;
+pub mod seed;
+pub mod users;
+
+use clap::{Parser, Subcommand};
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    config::AppConfig,
+    db::{self, routing::DatabasePools, store::postgres::PgStore},
+    error::AppError,
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "swarm", version, about = "Swarm API server and operator commands")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the HTTP API server (default when no subcommand is given)
+    Serve,
+    /// Create a new account with the admin role
+    CreateAdmin(users::CreateAdminArgs),
+    /// Grant or revoke the admin role of an existing account
+    SetAdmin(users::SetAdminArgs),
+    /// Set a new password for an account and revoke its tokens
+    ResetPassword(users::ResetPasswordArgs),
+    /// Invalidate previously issued tokens for one account or for everyone
+    RevokeTokens(users::RevokeTokensArgs),
+    /// Create accounts or invites in bulk from a CSV or JSONL file
+    ImportUsers(users::ImportUsersArgs),
+    /// Write every account as CSV or JSONL
+    ExportUsers(users::ExportUsersArgs),
+    /// Apply, list or verify versioned schema migrations
+    Migrate(migrate::MigrateArgs),
+    /// Report every difference between the database and the expected schema
+    CheckSchema(migrate::CheckSchemaArgs),
+    /// Write every table to a compressed, checksummed archive
+    Backup(backup::BackupArgs),
+    /// Load tables from a `backup` archive in a single transaction
+    Restore(backup::RestoreArgs),
+    /// Fill a development database with deterministic fake accounts and organizations
+    Seed(seed::SeedArgs),
+}
+
+/// Runs an operator command. `serve` is handled by `main` and never reaches this function.
+pub async fn run(command: Command, config: &AppConfig) -> Result<(), AppError> {
+    match db::DatabaseKind::from_url(&config.database_url)? {
+        db::DatabaseKind::Postgres => {}
+        #[cfg(feature = "sqlite")]
+        db::DatabaseKind::Sqlite => return run_on_sqlite(command, config).await,
+    }
+
+    let pool = db::connect(&config.database_url, &config.database_pool).await?;
+
+    match command {
+        Command::CheckSchema(args) => return migrate::check_schema(&pool, config, args).await,
+        Command::Migrate(args) => return migrate::migrate(&pool, config, args).await,
+        _ => {}
+    }
+
+    db::schema::ensure_schema(&pool, config.schema_check_mode).await?;
+
+    let store = Arc::new(PgStore::new(DatabasePools::new(pool.clone(), Vec::new(), Duration::ZERO)));
+
+    match command {
+        Command::CreateAdmin(args) => users::create_admin(store.as_ref(), args).await,
+        Command::SetAdmin(args) => users::set_admin(store.as_ref(), args).await,
+        Command::ResetPassword(args) => users::reset_password(store.as_ref(), args).await,
+        Command::RevokeTokens(args) => users::revoke_tokens(&pool, args).await,
+        Command::ImportUsers(args) => users::import_users(&pool, args).await,
+        Command::ExportUsers(args) => users::export_users_to(store, args).await,
+        Command::Backup(args) => backup::backup(&pool, args).await,
+        Command::Restore(args) => backup::restore(&pool, args).await,
+        Command::Seed(args) => seed::seed(&pool, args).await,
+        Command::Serve | Command::CheckSchema(_) | Command::Migrate(_) => unreachable!("handled above"),
+    }
+}
+
+/// The account commands and the export run through the store; everything else
+/// needs PostgreSQL.
+#[cfg(feature = "sqlite")]
+async fn run_on_sqlite(command: Command, config: &AppConfig) -> Result<(), AppError> {
+    use crate::db::store::sqlite::SqliteStore;
+
+    let pool = db::connect_sqlite(&config.database_url).await?;
+    db::schema::sqlite::ensure_schema(&pool).await?;
+
+    let store = Arc::new(SqliteStore::new(pool));
+
+    match command {
+        Command::CreateAdmin(args) => users::create_admin(store.as_ref(), args).await,
+        Command::SetAdmin(args) => users::set_admin(store.as_ref(), args).await,
+        Command::ResetPassword(args) => users::reset_password(store.as_ref(), args).await,
+        Command::ExportUsers(args) => users::export_users_to(store, args).await,
+        _ => Err(AppError::ServiceUnavailable(
+            "this operator command requires a PostgreSQL DATABASE_URL".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,360 @@
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{Duration, Utc};
+use clap::{Args, ValueEnum};
+use rand::{rngs::StdRng, seq::IndexedRandom, RngExt, SeedableRng};
+use sqlx::{PgConnection, PgPool};
+use std::env;
+
+use crate::{
+    db::{
+        invites::{self, NewInvite},
+        organizations::{self, NewOrganization, NewOrganizationInvite, OrganizationRole},
+        schema::spec,
+        tenant::{self, TenantContext},
+        users::{self, NewUser, UserRecord, UserUpdate},
+    },
+    error::AppError,
+    validation::{
+        normalize_and_validate_email,
+        organization::{validate_name, validate_slug},
+        validate_nickname, validate_password,
+    },
+};
+
+/// Every seeded address uses this domain; the "already seeded" check and
+/// `--reset` recognize seed accounts by it.
+const SEED_EMAIL_DOMAIN: &str = "seed.swarm.test";
+const DEFAULT_PASSWORD: &str = "swarm-dev-password";
+
+/// `--reset` only runs where `SWARM_ENV` is set to this value.
+const RESET_ENVIRONMENT: &str = "development";
+
+const FIRST_NAMES: &[&str] = &[
+    "ada", "alan", "barbara", "charles", "dennis", "edsger", "frances", "grace", "hedy", "ivan",
+    "john", "katherine", "ken", "linus", "margaret", "niklaus", "radia", "sophie", "tim", "tony",
+];
+// No two of these differ only by confusable letters, so seeded nicknames never
+// trip the similarity check.
+const LAST_NAMES: &[&str] = &[
+    "allen", "backus", "cerf", "dijkstra", "engelbart", "floyd", "goldberg", "hamilton", "hopper",
+    "johnson", "kay", "lamport", "liskov", "lovelace", "perlman", "ritchie", "shannon", "thompson",
+    "torvalds", "turing", "wirth",
+];
+const TEAM_ADJECTIVES: &[&str] = &[
+    "Blue", "Bright", "Quiet", "Rapid", "Silver", "Solid", "Swift", "Wild",
+];
+const TEAM_NOUNS: &[&str] = &[
+    "Badgers", "Beacons", "Builders", "Falcons", "Foxes", "Herons", "Otters", "Pilots",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SeedProfile {
+    /// One admin, a handful of users and one organization
+    Minimal,
+    /// Enough of everything to click through the app
+    Demo,
+    /// Thousands of users for pagination and performance work
+    Load,
+}
+
+struct ProfileSize {
+    admins: usize,
+    users: usize,
+    organizations: usize,
+    members_per_organization: usize,
+    invites: usize,
+}
+
+impl SeedProfile {
+    fn size(self) -> ProfileSize {
+        match self {
+            Self::Minimal => ProfileSize {
+                admins: 1,
+                users: 5,
+                organizations: 1,
+                members_per_organization: 3,
+                invites: 1,
+            },
+            Self::Demo => ProfileSize {
+                admins: 2,
+                users: 40,
+                organizations: 4,
+                members_per_organization: 8,
+                invites: 5,
+            },
+            Self::Load => ProfileSize {
+                admins: 3,
+                users: 2000,
+                organizations: 50,
+                members_per_organization: 30,
+                invites: 50,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    #[arg(long, value_enum, default_value_t = SeedProfile::Demo)]
+    pub profile: SeedProfile,
+    /// The same seed, profile and build produce the same nicknames, emails, organization
+    /// names and invite codes; ids, timestamps and expiry dates differ between runs
+    #[arg(long, default_value_t = 1)]
+    pub seed: u64,
+    /// Password of every seeded account
+    #[arg(long, env = "SWARM_PASSWORD", hide_env_values = true, default_value = DEFAULT_PASSWORD)]
+    pub password: String,
+    /// Delete all data first; refused unless SWARM_ENV=development and every account is a seed account
+    #[arg(long)]
+    pub reset: bool,
+}
+
+/// Creates the profile's accounts, invites and organizations through the same
+/// validators and queries as the API, in one transaction.
+pub async fn seed(pool: &PgPool, args: SeedArgs) -> Result<(), AppError> {
+    validate_password(&args.password)?;
+    let password_hash = hash(&args.password, DEFAULT_COST)?;
+    let size = args.profile.size();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+
+    let mut tx = pool.begin().await?;
+
+    if args.reset {
+        ensure_resettable(&mut tx).await?;
+        let tables: Vec<&str> = spec::data_tables().map(|table| table.name).collect();
+        sqlx::query(&format!("TRUNCATE {}", tables.join(", ")))
+            .execute(&mut *tx)
+            .await?;
+    } else if seed_account_count(&mut tx).await? > 0 {
+        return Err(AppError::Conflict(
+            "database already holds seed data; pass --reset to recreate it".to_string(),
+        ));
+    }
+
+    let mut admins = Vec::with_capacity(size.admins);
+    for index in 0..size.admins {
+        admins.push(create_account(&mut tx, &mut rng, "ops", index, &password_hash, true).await?);
+    }
+
+    let mut people = Vec::with_capacity(size.users);
+    for index in 0..size.users {
+        let first = *FIRST_NAMES.choose(&mut rng).unwrap_or(&"user");
+        people.push(create_account(&mut tx, &mut rng, first, index, &password_hash, false).await?);
+    }
+
+    // Every tenth account is suspended, so admin filters have something to show.
+    let mut suspended = 0;
+    for person in people.iter().skip(9).step_by(10) {
+        users::update_user(
+            &mut tx,
+            person.id,
+            UserUpdate {
+                nickname: None,
+                email: None,
+                suspended: Some(true),
+            },
+            None,
+        )
+        .await?;
+        suspended += 1;
+    }
+
+    let inviter = admins.first().map(|admin| admin.id);
+    for index in 0..size.invites {
+        invites::create_invite(
+            &mut *tx,
+            NewInvite {
+                code: seeded_code(&mut rng),
+                email: Some(seed_email(&format!("invitee.{}", index + 1))?),
+                is_admin: false,
+                max_uses: 1,
+                expires_at: Some(Utc::now() + Duration::days(30)),
+                created_by: inviter,
+            },
+        )
+        .await?;
+    }
+
+    let mut memberships = 0;
+    for index in 0..size.organizations.min(people.len()) {
+        memberships +=
+            create_organization(&mut tx, &mut rng, index, &people, size.members_per_organization).await?;
+    }
+
+    tx.commit().await?;
+
+    println!(
+        "seeded {} admins, {} users ({suspended} suspended), {} invites, {} organizations with {memberships} memberships",
+        admins.len(),
+        people.len(),
+        size.invites,
+        size.organizations.min(people.len()),
+    );
+    if let Some(admin) = admins.first() {
+        println!("sign in as '{}' <{}>", admin.nickname, admin.email);
+    }
+    println!("every seeded account uses the password given by --password (default '{DEFAULT_PASSWORD}')");
+
+    Ok(())
+}
+
+async fn create_account(
+    conn: &mut PgConnection,
+    rng: &mut StdRng,
+    first: &str,
+    index: usize,
+    password_hash: &str,
+    is_admin: bool,
+) -> Result<UserRecord, AppError> {
+    let last = LAST_NAMES.choose(rng).copied().unwrap_or("seed");
+    let local_part = format!("{first}.{last}.{}", index + 1);
+
+    users::create_user(
+        conn,
+        NewUser {
+            nickname: validate_nickname(&local_part)?,
+            email: seed_email(&local_part)?,
+            password_hash: password_hash.to_string(),
+            is_admin,
+        },
+    )
+    .await
+}
+
+/// Creates an organization owned by a random account and fills it through
+/// invites redeemed by the members, as the API would. Returns the member count.
+async fn create_organization(
+    conn: &mut PgConnection,
+    rng: &mut StdRng,
+    index: usize,
+    people: &[UserRecord],
+    members: usize,
+) -> Result<usize, AppError> {
+    let adjective = TEAM_ADJECTIVES.choose(rng).copied().unwrap_or("Seed");
+    let noun = TEAM_NOUNS.choose(rng).copied().unwrap_or("Team");
+    let name = validate_name(&format!("{adjective} {noun}"))?;
+    let slug = validate_slug(&format!("{}-{}", name.to_lowercase().replace(' ', "-"), index + 1))?;
+
+    let mut chosen: Vec<&UserRecord> = people.sample(rng, members.min(people.len())).collect();
+    chosen.sort_by_key(|person| person.created_at);
+    let Some((owner, others)) = chosen.split_first() else {
+        return Ok(0);
+    };
+
+    tenant::enter(conn, TenantContext { user_id: owner.id, organization_id: None }).await?;
+    let organization = organizations::create_organization(
+        conn,
+        NewOrganization {
+            slug,
+            name,
+            owner_id: owner.id,
+        },
+    )
+    .await?
+    .organization;
+
+    for (position, member) in others.iter().enumerate() {
+        let role = if position == 0 {
+            OrganizationRole::Admin
+        } else {
+            OrganizationRole::Member
+        };
+        let code = seeded_code(rng);
+
+        tenant::enter(
+            conn,
+            TenantContext {
+                user_id: owner.id,
+                organization_id: Some(organization.id),
+            },
+        )
+        .await?;
+        organizations::create_invite(
+            &mut *conn,
+            NewOrganizationInvite {
+                organization_id: organization.id,
+                code: code.clone(),
+                email: Some(member.email.clone()),
+                role,
+                expires_at: Utc::now() + Duration::days(7),
+                created_by: owner.id,
+            },
+        )
+        .await?;
+
+        tenant::enter(conn, TenantContext { user_id: member.id, organization_id: None }).await?;
+        organizations::accept_invite(conn, &code, member.id, &member.email)
+            .await?
+            .ok_or_else(|| {
+                AppError::Internal("seeded organization invite could not be redeemed".to_string())
+            })?;
+    }
+
+    Ok(chosen.len())
+}
+
+fn seed_email(local_part: &str) -> Result<String, AppError> {
+    normalize_and_validate_email(&format!("{local_part}@{SEED_EMAIL_DOMAIN}"))
+}
+
+/// Same shape as `invites::generate_invite_code`, but drawn from the seeded generator.
+fn seeded_code(rng: &mut StdRng) -> String {
+    format!("{:032x}", rng.random::<u128>())
+}
+
+async fn seed_account_count(conn: &mut PgConnection) -> Result<i64, AppError> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE lower(email) LIKE '%@' || $1")
+        .bind(SEED_EMAIL_DOMAIN)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(count)
+}
+
+/// Allows wiping the database only where `SWARM_ENV` marks a development
+/// environment and the database itself holds nothing but seed accounts, so a
+/// production `DATABASE_URL` in a development shell is still refused.
+async fn ensure_resettable(conn: &mut PgConnection) -> Result<(), AppError> {
+    if !env::var("SWARM_ENV").is_ok_and(|value| value.trim() == RESET_ENVIRONMENT) {
+        return Err(refuse_outside_development(conn).await?);
+    }
+
+    let other_accounts: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE lower(email) NOT LIKE '%@' || $1")
+            .bind(SEED_EMAIL_DOMAIN)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    if other_accounts > 0 {
+        return Err(AppError::Forbidden(format!(
+            "refusing to reset: {other_accounts} accounts are not seed accounts (@{SEED_EMAIL_DOMAIN}); \
+             only databases created by `seed` can be reset"
+        )));
+    }
+
+    Ok(())
+}
+
+/// The refusal lists every data table that holds rows, except the organization
+/// tables whose rows row-level security hides from this role.
+async fn refuse_outside_development(conn: &mut PgConnection) -> Result<AppError, AppError> {
+    let mut held = Vec::new();
+    for table in spec::data_tables() {
+        let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table.name))
+            .fetch_one(&mut *conn)
+            .await?;
+        if rows > 0 {
+            held.push(format!("{}: {rows}", table.name));
+        }
+    }
+
+    let contents = if held.is_empty() {
+        "no rows".to_string()
+    } else {
+        held.join(", ")
+    };
+
+    Ok(AppError::Forbidden(format!(
+        "refusing to reset: set SWARM_ENV={RESET_ENVIRONMENT} to delete this database's data ({contents})"
+    )))
+}
@@ -0,0 +1,333 @@
+use bcrypt::{hash, DEFAULT_COST};
+use clap::Args;
+use futures_util::TryStreamExt;
+use serde_json::json;
+use sqlx::PgPool;
+use std::{io::Write, path::PathBuf, sync::Arc};
+use uuid::Uuid;
+
+use crate::{
+    bulk::{
+        export::export_users,
+        import::{self, ImportNotification, ImportOptions},
+        BulkFormat,
+    },
+    db::{
+        audit::{self, AuditEventType, NewAuditEvent},
+        store::Store,
+        users::{self, NewUser, UserRecord},
+    },
+    error::AppError,
+    validation::{normalize_and_validate_email, validate_nickname, validate_password},
+};
+
+#[derive(Debug, Args)]
+pub struct CreateAdminArgs {
+    #[arg(long)]
+    pub nickname: String,
+    #[arg(long)]
+    pub email: String,
+    /// Password for the new account; a random one is generated and printed when omitted
+    #[arg(long, env = "SWARM_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SetAdminArgs {
+    #[arg(long)]
+    pub email: String,
+    /// Revoke the admin role instead of granting it
+    #[arg(long)]
+    pub revoke: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ResetPasswordArgs {
+    #[arg(long)]
+    pub email: String,
+    /// New password; a random one is generated and printed when omitted
+    #[arg(long, env = "SWARM_PASSWORD", hide_env_values = true)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+pub struct RevokeTokensArgs {
+    /// Revoke tokens of a single account
+    #[arg(long)]
+    pub email: Option<String>,
+    /// Revoke tokens of every account
+    #[arg(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ImportUsersArgs {
+    /// CSV (with a nickname,email[,password,is_admin] header) or JSONL file
+    pub file: PathBuf,
+    /// Input format; inferred from the file extension when omitted
+    #[arg(long, value_enum)]
+    pub format: Option<BulkFormat>,
+    /// Validate every row and report problems without creating anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Email sent to each imported person
+    #[arg(long, value_enum, default_value_t = ImportNotification::None)]
+    pub notify: ImportNotification,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportUsersArgs {
+    /// Output format; inferred from --output when omitted, otherwise CSV
+    #[arg(long, value_enum)]
+    pub format: Option<BulkFormat>,
+    /// File to write; standard output when omitted
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+/// `create_admin`, `set_admin` and `reset_password` go through the store, so
+/// they also work on SQLite.
+pub async fn create_admin(store: &dyn Store, args: CreateAdminArgs) -> Result<(), AppError> {
+    let nickname = validate_nickname(&args.nickname)?;
+    let email = normalize_and_validate_email(&args.email)?;
+    let (password, generated) = resolve_password(args.password)?;
+
+    let password_hash = hash(&password, DEFAULT_COST)?;
+
+    let mut tx = store.begin().await?;
+
+    let user = tx
+        .create_user(NewUser {
+            nickname,
+            email,
+            password_hash,
+            is_admin: true,
+        })
+        .await?;
+
+    tx.record_event(
+        cli_event(AuditEventType::AdminCreated)
+            .target(user.id)
+            .payload(json!({ "source": "cli", "nickname": user.nickname, "email": user.email })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    println!("created admin '{}' <{}> with id {}", user.nickname, user.email, user.id);
+    if generated {
+        println!("generated password: {password}");
+    }
+
+    Ok(())
+}
+
+pub async fn set_admin(store: &dyn Store, args: SetAdminArgs) -> Result<(), AppError> {
+    let user = find_user(store, &args.email).await?;
+
+    let mut tx = store.begin().await?;
+
+    let updated = tx
+        .set_admin(user.id, !args.revoke, None)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("user <{}> no longer exists", user.email)))?;
+
+    let event_type = if updated.is_admin {
+        AuditEventType::RoleGranted
+    } else {
+        AuditEventType::RoleRevoked
+    };
+    tx.record_event(cli_event(event_type).target(updated.id)).await?;
+
+    tx.commit().await?;
+
+    let role = if updated.is_admin { "an admin" } else { "a regular user" };
+    println!("'{}' <{}> is now {role}", updated.nickname, updated.email);
+
+    Ok(())
+}
+
+pub async fn reset_password(store: &dyn Store, args: ResetPasswordArgs) -> Result<(), AppError> {
+    let user = find_user(store, &args.email).await?;
+    let (password, generated) = resolve_password(args.password)?;
+
+    let password_hash = hash(&password, DEFAULT_COST)?;
+
+    let mut tx = store.begin().await?;
+
+    tx.update_password_hash(user.id, &password_hash)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("user <{}> no longer exists", user.email)))?;
+
+    tx.record_event(cli_event(AuditEventType::PasswordReset).target(user.id)).await?;
+
+    tx.commit().await?;
+
+    println!("password for '{}' <{}> has been reset; existing tokens are revoked", user.nickname, user.email);
+    if generated {
+        println!("generated password: {password}");
+    }
+
+    Ok(())
+}
+
+pub async fn revoke_tokens(pool: &PgPool, args: RevokeTokensArgs) -> Result<(), AppError> {
+    if args.all {
+        let mut tx = pool.begin().await?;
+        let affected = users::revoke_all_tokens(&mut tx).await?;
+        audit::record_event(
+            &mut tx,
+            cli_event(AuditEventType::TokensRevoked)
+                .payload(json!({ "source": "cli", "all": true, "affected": affected })),
+        )
+        .await?;
+        tx.commit().await?;
+
+        println!("revoked tokens of {affected} users");
+        return Ok(());
+    }
+
+    let email = normalize_and_validate_email(&args.email.unwrap_or_default())?;
+    let user = users::find_user_by_email(pool, &email)
+        .await?
+        .ok_or_else(|| no_such_user(&email))?;
+
+    let mut tx = pool.begin().await?;
+
+    users::revoke_tokens(&mut tx, user.id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("user <{}> no longer exists", user.email)))?;
+
+    audit::record_event(&mut tx, cli_event(AuditEventType::TokensRevoked).target(user.id)).await?;
+
+    tx.commit().await?;
+
+    println!("revoked tokens of '{}' <{}>", user.nickname, user.email);
+
+    Ok(())
+}
+
+pub async fn import_users(pool: &PgPool, args: ImportUsersArgs) -> Result<(), AppError> {
+    let format = args
+        .format
+        .or_else(|| BulkFormat::from_path(&args.file))
+        .ok_or_else(|| {
+            AppError::BadRequest("cannot infer the format from the file name; pass --format".to_string())
+        })?;
+
+    let data = std::fs::read(&args.file).map_err(|error| {
+        AppError::BadRequest(format!("failed to read {}: {error}", args.file.display()))
+    })?;
+
+    let report = import::import_users(
+        pool,
+        &data,
+        ImportOptions {
+            format,
+            dry_run: args.dry_run,
+            notify: args.notify,
+            actor_id: None,
+        },
+        cli_event,
+    )
+    .await?;
+
+    for error in &report.errors {
+        let email = error.email.as_deref().unwrap_or("-");
+        println!("line {}: <{email}>: {}", error.line, error.error);
+    }
+
+    println!(
+        "{} of {} rows valid, {} errors",
+        report.valid_rows,
+        report.total_rows,
+        report.errors.len()
+    );
+
+    if report.committed {
+        println!(
+            "imported {} rows, queued {} emails for the server's job workers",
+            report.rows.len(),
+            report.emails_queued
+        );
+        Ok(())
+    } else if report.dry_run && report.errors.is_empty() {
+        println!("dry run: nothing was written");
+        Ok(())
+    } else if report.dry_run {
+        Err(AppError::BadRequest("dry run found invalid rows".to_string()))
+    } else {
+        Err(AppError::BadRequest("nothing was imported; fix the rows above and retry".to_string()))
+    }
+}
+
+pub async fn export_users_to(store: Arc<dyn Store>, args: ExportUsersArgs) -> Result<(), AppError> {
+    let format = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(BulkFormat::from_path))
+        .unwrap_or(BulkFormat::Csv);
+
+    let write_error = |error: std::io::Error| AppError::Internal(format!("failed to write export: {error}"));
+
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(std::io::BufWriter::new(
+            std::fs::File::create(path).map_err(write_error)?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    // No operator is signed in, so the nil id only picks the read pool.
+    let mut stream = std::pin::pin!(export_users(store, Uuid::nil(), format));
+    while let Some(chunk) = stream.try_next().await? {
+        output.write_all(&chunk).map_err(write_error)?;
+    }
+    output.flush().map_err(write_error)?;
+
+    if let Some(path) = &args.output {
+        eprintln!("exported users to {}", path.display());
+    }
+
+    Ok(())
+}
+
+async fn find_user(store: &dyn Store, email: &str) -> Result<UserRecord, AppError> {
+    let email = normalize_and_validate_email(email)?;
+
+    store
+        .session()
+        .await?
+        .find_user_by_email(&email)
+        .await?
+        .ok_or_else(|| no_such_user(&email))
+}
+
+fn no_such_user(email: &str) -> AppError {
+    AppError::NotFound(format!("no user with email <{email}>"))
+}
+
+/// Operator commands have no authenticated actor; the payload marks them as CLI-originated.
+pub(super) fn cli_event(event_type: AuditEventType) -> NewAuditEvent {
+    NewAuditEvent::new(event_type).payload(json!({ "source": "cli" }))
+}
+
+fn resolve_password(password: Option<String>) -> Result<(String, bool), AppError> {
+    match password {
+        Some(password) => {
+            validate_password(&password)?;
+            Ok((password, false))
+        }
+        None => Ok((generate_password(), true)),
+    }
+}
+
+fn generate_password() -> String {
+    let bytes: [u8; 12] = rand::random();
+
+    let mut output = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        output.push_str(&format!("{byte:02x}"));
+    }
+
+    output
+}
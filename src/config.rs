@@ -1,11 +1,77 @@
-use std::{env, net::{IpAddr, SocketAddr}};
+use serde::{Deserialize, Serialize};
+use std::{env, net::{IpAddr, SocketAddr}, path::PathBuf, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+impl RegistrationMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "open" => Some(Self::Open),
+            "invite_only" | "invite-only" => Some(Self::InviteOnly),
+            "closed" => Some(Self::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// How `check_schema` treats database objects the schema spec doesn't know about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaCheckMode {
+    /// Extra tables, columns, constraints and indexes are reported but allowed.
+    Tolerant,
+    /// Any difference from the spec fails the check.
+    Strict,
+}
+
+impl SchemaCheckMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "tolerant" => Some(Self::Tolerant),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+}
+
+pub struct DatabasePoolConfig {
+    pub max_connections: u32,
+    /// Connections kept open even when idle.
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    /// `None` keeps idle connections forever.
+    pub idle_timeout: Option<Duration>,
+    /// Sent as PostgreSQL's `statement_timeout`; `None` leaves the server default.
+    pub statement_timeout: Option<Duration>,
+    /// How long `serve` waits for the database before starting in degraded mode.
+    pub startup_deadline: Duration,
+}
 
 pub struct AppConfig {
     pub addr: SocketAddr,
     pub database_url: String,
+    pub database_pool: DatabasePoolConfig,
+    /// Read replicas; empty sends every query to `database_url`.
+    pub database_replica_urls: Vec<String>,
+    /// How long a user's reads stay on the primary after they write.
+    pub replica_sticky_window: Duration,
     pub jwt_secret: String,
     pub jwt_secret_is_ephemeral: bool,
     pub jwt_ttl_seconds: i64,
+    pub registration_mode: RegistrationMode,
+    pub schema_check_mode: SchemaCheckMode,
+    pub disposable_email_domains_file: Option<PathBuf>,
+    pub trust_proxy_headers: bool,
+    pub smtp_url: Option<String>,
+    pub mail_from: String,
+    pub public_base_url: String,
+    /// Background job workers in the server process; `0` leaves jobs to other instances.
+    pub job_workers: usize,
 }
 
 impl AppConfig {
@@ -25,6 +91,35 @@ impl AppConfig {
         let database_url = env::var("DATABASE_URL")
             .expect("DATABASE_URL is required; application will not start without PostgreSQL DSN");
 
+        let database_pool = DatabasePoolConfig {
+            max_connections: env_number("DB_MAX_CONNECTIONS").unwrap_or(12).max(1),
+            min_connections: env_number("DB_MIN_CONNECTIONS").unwrap_or(0),
+            acquire_timeout: Duration::from_secs(env_number("DB_ACQUIRE_TIMEOUT_SECONDS").unwrap_or(5)),
+            idle_timeout: Some(env_number("DB_IDLE_TIMEOUT_SECONDS").unwrap_or(600))
+                .filter(|seconds| *seconds > 0)
+                .map(Duration::from_secs),
+            statement_timeout: env_number("DB_STATEMENT_TIMEOUT_MS")
+                .filter(|millis| *millis > 0)
+                .map(Duration::from_millis),
+            startup_deadline: Duration::from_secs(
+                env_number("DB_STARTUP_DEADLINE_SECONDS").unwrap_or(60),
+            ),
+        };
+
+        let database_replica_urls = env::var("DATABASE_REPLICA_URLS")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let replica_sticky_window =
+            Duration::from_secs(env_number("DB_REPLICA_STICKY_SECONDS").unwrap_or(10));
+
         let jwt_secret_from_env = env::var("JWT_SECRET")
             .ok()
             .map(|value| value.trim().to_string())
@@ -38,16 +133,77 @@ impl AppConfig {
             .and_then(|value| value.parse::<i64>().ok())
             .unwrap_or(3600);
 
+        let registration_mode = env::var("REGISTRATION_MODE")
+            .ok()
+            .map(|value| {
+                RegistrationMode::parse(&value)
+                    .expect("REGISTRATION_MODE must be one of: open, invite_only, closed")
+            })
+            .unwrap_or(RegistrationMode::Open);
+
+        let schema_check_mode = env::var("SCHEMA_CHECK_MODE")
+            .ok()
+            .map(|value| {
+                SchemaCheckMode::parse(&value)
+                    .expect("SCHEMA_CHECK_MODE must be one of: tolerant, strict")
+            })
+            .unwrap_or(SchemaCheckMode::Tolerant);
+
+        let disposable_email_domains_file = env::var("DISPOSABLE_EMAIL_DOMAINS_FILE")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from);
+
+        let trust_proxy_headers = env::var("TRUST_PROXY_HEADERS")
+            .ok()
+            .map(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let smtp_url = env::var("SMTP_URL")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        let mail_from = env::var("MAIL_FROM")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| "Swarm <no-reply@localhost>".to_string());
+
+        let public_base_url = env::var("PUBLIC_BASE_URL")
+            .ok()
+            .map(|value| value.trim().trim_end_matches('/').to_string())
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| "http://localhost".to_string());
+
+        let job_workers = env_number("JOB_WORKERS").unwrap_or(2);
+
         Self {
             addr: SocketAddr::from((host_ip, port)),
             database_url,
+            database_pool,
+            database_replica_urls,
+            replica_sticky_window,
             jwt_secret,
             jwt_secret_is_ephemeral,
             jwt_ttl_seconds,
+            registration_mode,
+            schema_check_mode,
+            disposable_email_domains_file,
+            trust_proxy_headers,
+            smtp_url,
+            mail_from,
+            public_base_url,
+            job_workers,
         }
     }
 }
 
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|value| value.trim().parse().ok())
+}
+
 fn generate_secure_jwt_secret() -> String {
     let bytes: [u8; 64] = rand::random();
 
@@ -0,0 +1,385 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+/// Arbitrary key for `pg_advisory_xact_lock`; serializes appends to the hash chain.
+const AUDIT_CHAIN_LOCK_KEY: i64 = 0x5357_4152_4d41_5544;
+
+/// `prev_hash` of the very first event.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    UserRegistered,
+    LoginSucceeded,
+    LoginFailed,
+    TokenIssued,
+    InviteCreated,
+    InviteRevoked,
+    UserUpdated,
+    UserDeleted,
+    RoleGranted,
+    RoleRevoked,
+    AdminCreated,
+    PasswordReset,
+    TokensRevoked,
+    UserImported,
+    PasswordSet,
+    FeatureFlagCreated,
+    FeatureFlagUpdated,
+    FeatureFlagDeleted,
+    SettingsUpdated,
+    OrganizationCreated,
+    OrganizationInviteCreated,
+    OrganizationJoined,
+    JobRetried,
+    DatabaseRestored,
+}
+
+impl AuditEventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UserRegistered => "user.registered",
+            Self::LoginSucceeded => "auth.login_succeeded",
+            Self::LoginFailed => "auth.login_failed",
+            Self::TokenIssued => "auth.token_issued",
+            Self::InviteCreated => "admin.invite_created",
+            Self::InviteRevoked => "admin.invite_revoked",
+            Self::UserUpdated => "admin.user_updated",
+            Self::UserDeleted => "admin.user_deleted",
+            Self::RoleGranted => "admin.role_granted",
+            Self::RoleRevoked => "admin.role_revoked",
+            Self::AdminCreated => "admin.admin_created",
+            Self::PasswordReset => "admin.password_reset",
+            Self::TokensRevoked => "admin.tokens_revoked",
+            Self::UserImported => "admin.user_imported",
+            Self::PasswordSet => "auth.password_set",
+            Self::FeatureFlagCreated => "admin.feature_flag_created",
+            Self::FeatureFlagUpdated => "admin.feature_flag_updated",
+            Self::FeatureFlagDeleted => "admin.feature_flag_deleted",
+            Self::SettingsUpdated => "admin.settings_updated",
+            Self::OrganizationCreated => "organization.created",
+            Self::OrganizationInviteCreated => "organization.invite_created",
+            Self::OrganizationJoined => "organization.member_joined",
+            Self::JobRetried => "admin.job_retried",
+            Self::DatabaseRestored => "admin.database_restored",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub event_type: AuditEventType,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+impl NewAuditEvent {
+    pub fn new(event_type: AuditEventType) -> Self {
+        Self {
+            event_type,
+            actor_id: None,
+            target_id: None,
+            ip: None,
+            request_id: None,
+            payload: serde_json::Value::Object(Default::default()),
+        }
+    }
+
+    pub fn actor(mut self, actor_id: Uuid) -> Self {
+        self.actor_id = Some(actor_id);
+        self
+    }
+
+    pub fn target(mut self, target_id: Uuid) -> Self {
+        self.target_id = Some(target_id);
+        self
+    }
+
+    pub fn payload(mut self, payload: serde_json::Value) -> Self {
+        self.payload = payload;
+        self
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AuditEventRecord {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub event_type: String,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AuditEventFilter {
+    pub event_type: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainVerification {
+    pub valid: bool,
+    pub events_checked: u64,
+    pub first_invalid_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// Appends an event to the hash chain on the caller's connection, so it commits
+/// or rolls back together with the change it describes.
+pub async fn record_event(conn: &mut PgConnection, event: NewAuditEvent) -> Result<AuditEventRecord, AppError> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(AUDIT_CHAIN_LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+
+    let prev_hash: Option<String> = sqlx::query_scalar("SELECT hash FROM audit_events ORDER BY id DESC LIMIT 1")
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let SealedAuditEvent {
+        occurred_at,
+        event,
+        prev_hash,
+        hash,
+    } = seal_event(prev_hash, event);
+
+    let record = sqlx::query_as::<_, AuditEventRecord>(
+        r#"
+        INSERT INTO audit_events (occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash
+        "#,
+    )
+    .bind(occurred_at)
+    .bind(event.event_type.as_str())
+    .bind(event.actor_id)
+    .bind(event.target_id)
+    .bind(event.ip)
+    .bind(event.request_id)
+    .bind(event.payload)
+    .bind(prev_hash)
+    .bind(hash)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(record)
+}
+
+/// An event linked to its predecessor, ready to be stored.
+pub struct SealedAuditEvent {
+    pub occurred_at: DateTime<Utc>,
+    pub event: NewAuditEvent,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// Timestamps the event and chains it to `prev_hash`, the hash of the newest
+/// stored event (`None` for the first one).
+pub fn seal_event(prev_hash: Option<String>, event: NewAuditEvent) -> SealedAuditEvent {
+    let prev_hash = prev_hash.unwrap_or_else(|| GENESIS_HASH.to_string());
+
+    // Postgres keeps microseconds; truncate first so the stored value hashes the same.
+    let occurred_at = DateTime::from_timestamp_micros(Utc::now().timestamp_micros())
+        .unwrap_or_else(Utc::now);
+
+    let hash = compute_hash(
+        &prev_hash,
+        occurred_at,
+        event.event_type.as_str(),
+        event.actor_id,
+        event.target_id,
+        event.ip.as_deref(),
+        event.request_id.as_deref(),
+        &event.payload,
+    );
+
+    SealedAuditEvent {
+        occurred_at,
+        event,
+        prev_hash,
+        hash,
+    }
+}
+
+/// Newest first; `before_id` is the keyset cursor from the previous page.
+pub async fn list_events(
+    conn: &mut PgConnection,
+    filter: &AuditEventFilter,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<AuditEventRecord>, AppError> {
+    let mut builder = QueryBuilder::<Postgres>::new(
+        "SELECT id, occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash \
+         FROM audit_events WHERE TRUE",
+    );
+
+    if let Some(event_type) = &filter.event_type {
+        builder.push(" AND event_type = ").push_bind(event_type.clone());
+    }
+
+    if let Some(actor_id) = filter.actor_id {
+        builder.push(" AND actor_id = ").push_bind(actor_id);
+    }
+
+    if let Some(target_id) = filter.target_id {
+        builder.push(" AND target_id = ").push_bind(target_id);
+    }
+
+    if let Some(from) = filter.from {
+        builder.push(" AND occurred_at >= ").push_bind(from);
+    }
+
+    if let Some(to) = filter.to {
+        builder.push(" AND occurred_at < ").push_bind(to);
+    }
+
+    if let Some(before_id) = before_id {
+        builder.push(" AND id < ").push_bind(before_id);
+    }
+
+    builder.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+    let records = builder.build_query_as().fetch_all(conn).await?;
+
+    Ok(records)
+}
+
+/// Batch size for walking the chain in [`verify_chain`] and its store counterparts.
+pub const VERIFY_BATCH_SIZE: i64 = 1000;
+
+/// Walks the whole chain in id order and recomputes every hash.
+pub async fn verify_chain(conn: &mut PgConnection) -> Result<ChainVerification, AppError> {
+    let mut verifier = ChainVerifier::default();
+    let mut after_id = 0_i64;
+
+    loop {
+        let batch = sqlx::query_as::<_, AuditEventRecord>(
+            r#"
+            SELECT id, occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash
+            FROM audit_events
+            WHERE id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(after_id)
+        .bind(VERIFY_BATCH_SIZE)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        if let Some(invalid) = verifier.check(&batch) {
+            return Ok(invalid);
+        }
+    }
+
+    Ok(verifier.finish())
+}
+
+/// Checks the chain one id-ordered batch at a time, independent of where the
+/// events are stored.
+pub struct ChainVerifier {
+    expected_prev_hash: String,
+    events_checked: u64,
+}
+
+impl Default for ChainVerifier {
+    fn default() -> Self {
+        Self {
+            expected_prev_hash: GENESIS_HASH.to_string(),
+            events_checked: 0,
+        }
+    }
+}
+
+impl ChainVerifier {
+    /// The verdict for the first broken link in `batch`, if any.
+    pub fn check(&mut self, batch: &[AuditEventRecord]) -> Option<ChainVerification> {
+        for event in batch {
+            let invalid = |reason: &str| ChainVerification {
+                valid: false,
+                events_checked: self.events_checked,
+                first_invalid_id: Some(event.id),
+                reason: Some(reason.to_string()),
+            };
+
+            if event.prev_hash != self.expected_prev_hash {
+                return Some(invalid("prev_hash does not match the preceding event"));
+            }
+
+            let recomputed = compute_hash(
+                &event.prev_hash,
+                event.occurred_at,
+                &event.event_type,
+                event.actor_id,
+                event.target_id,
+                event.ip.as_deref(),
+                event.request_id.as_deref(),
+                &event.payload,
+            );
+            if recomputed != event.hash {
+                return Some(invalid("hash does not match the event contents"));
+            }
+
+            self.expected_prev_hash = event.hash.clone();
+            self.events_checked += 1;
+        }
+
+        None
+    }
+
+    pub fn finish(self) -> ChainVerification {
+        ChainVerification {
+            valid: true,
+            events_checked: self.events_checked,
+            first_invalid_id: None,
+            reason: None,
+        }
+    }
+}
+
+/// SHA-256 over the previous hash and every stored field. `serde_json::Value`
+/// serializes object keys in sorted order, so the payload survives the JSONB round trip.
+#[allow(clippy::too_many_arguments)]
+fn compute_hash(
+    prev_hash: &str,
+    occurred_at: DateTime<Utc>,
+    event_type: &str,
+    actor_id: Option<Uuid>,
+    target_id: Option<Uuid>,
+    ip: Option<&str>,
+    request_id: Option<&str>,
+    payload: &serde_json::Value,
+) -> String {
+    let fields = serde_json::json!([
+        prev_hash,
+        occurred_at.timestamp_micros(),
+        event_type,
+        actor_id,
+        target_id,
+        ip,
+        request_id,
+        payload,
+    ]);
+
+    format!("{:x}", Sha256::digest(fields.to_string().as_bytes()))
+}
@@ -0,0 +1,175 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Channel the triggers from `migrations/0001_initial_schema.sql` notify whenever flags or their targets change.
+pub const FEATURE_FLAGS_CHANNEL: &str = "feature_flags_changed";
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FeatureFlagRecord {
+    pub key: String,
+    pub description: String,
+    pub enabled: bool,
+    pub rollout_percentage: i16,
+    pub user_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct NewFeatureFlag {
+    pub key: String,
+    pub description: String,
+    pub enabled: bool,
+    pub rollout_percentage: i16,
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Default)]
+pub struct FeatureFlagUpdate {
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    pub rollout_percentage: Option<i16>,
+    /// Replaces the whole per-user target list when set.
+    pub user_ids: Option<Vec<Uuid>>,
+}
+
+pub async fn list_flags(pool: &PgPool) -> Result<Vec<FeatureFlagRecord>, AppError> {
+    select_flags(pool, None).await
+}
+
+pub async fn find_flag<'e>(
+    executor: impl PgExecutor<'e>,
+    key: &str,
+) -> Result<Option<FeatureFlagRecord>, AppError> {
+    let mut records = select_flags(executor, Some(key)).await?;
+
+    Ok(records.pop())
+}
+
+pub async fn create_flag(
+    conn: &mut PgConnection,
+    new_flag: NewFeatureFlag,
+) -> Result<FeatureFlagRecord, AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO feature_flags (key, description, enabled, rollout_percentage)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(&new_flag.key)
+    .bind(&new_flag.description)
+    .bind(new_flag.enabled)
+    .bind(new_flag.rollout_percentage)
+    .execute(&mut *conn)
+    .await
+    .map_err(map_write_error)?;
+
+    replace_flag_users(conn, &new_flag.key, &new_flag.user_ids).await?;
+
+    find_flag(&mut *conn, &new_flag.key)
+        .await?
+        .ok_or_else(|| AppError::Internal("created feature flag disappeared".to_string()))
+}
+
+pub async fn update_flag(
+    conn: &mut PgConnection,
+    key: &str,
+    update: FeatureFlagUpdate,
+) -> Result<Option<FeatureFlagRecord>, AppError> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE feature_flags
+        SET description = COALESCE($2, description),
+            enabled = COALESCE($3, enabled),
+            rollout_percentage = COALESCE($4, rollout_percentage),
+            updated_at = NOW()
+        WHERE key = $1
+        "#,
+    )
+    .bind(key)
+    .bind(update.description)
+    .bind(update.enabled)
+    .bind(update.rollout_percentage)
+    .execute(&mut *conn)
+    .await?;
+
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    if let Some(user_ids) = update.user_ids {
+        replace_flag_users(conn, key, &user_ids).await?;
+    }
+
+    find_flag(&mut *conn, key).await
+}
+
+pub async fn delete_flag<'e>(executor: impl PgExecutor<'e>, key: &str) -> Result<bool, AppError> {
+    let deleted = sqlx::query("DELETE FROM feature_flags WHERE key = $1")
+        .bind(key)
+        .execute(executor)
+        .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+async fn replace_flag_users(conn: &mut PgConnection, key: &str, user_ids: &[Uuid]) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM feature_flag_users WHERE flag_key = $1")
+        .bind(key)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO feature_flag_users (flag_key, user_id)
+        SELECT $1, user_id FROM UNNEST($2::uuid[]) AS targets(user_id)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(key)
+    .bind(user_ids)
+    .execute(&mut *conn)
+    .await
+    .map_err(map_write_error)?;
+
+    Ok(())
+}
+
+async fn select_flags<'e>(
+    executor: impl PgExecutor<'e>,
+    key: Option<&str>,
+) -> Result<Vec<FeatureFlagRecord>, AppError> {
+    let records = sqlx::query_as::<_, FeatureFlagRecord>(
+        r#"
+        SELECT flags.key, flags.description, flags.enabled, flags.rollout_percentage,
+               COALESCE(
+                   array_agg(targets.user_id ORDER BY targets.user_id) FILTER (WHERE targets.user_id IS NOT NULL),
+                   '{}'
+               ) AS user_ids,
+               flags.created_at, flags.updated_at
+        FROM feature_flags AS flags
+        LEFT JOIN feature_flag_users AS targets ON targets.flag_key = flags.key
+        WHERE $1::text IS NULL OR flags.key = $1
+        GROUP BY flags.key
+        ORDER BY flags.key
+        "#,
+    )
+    .bind(key)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records)
+}
+
+fn map_write_error(error: sqlx::Error) -> AppError {
+    match error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            AppError::Conflict("feature flag already exists".to_string())
+        }
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23503") => {
+            AppError::BadRequest("user_ids contains an unknown user".to_string())
+        }
+        other => AppError::from(other),
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// Where the database startup sequence (connect, migrate, load caches) stands.
+#[derive(Debug, Clone)]
+pub enum DatabaseState {
+    Connecting {
+        attempts: u32,
+        last_error: Option<String>,
+    },
+    Ready,
+    /// Connected, but migrating or loading failed; needs an operator.
+    Failed { error: String },
+}
+
+/// Shared between the startup task and the health endpoint.
+#[derive(Clone)]
+pub struct DatabaseHealth {
+    state: Arc<RwLock<DatabaseState>>,
+}
+
+impl Default for DatabaseHealth {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(DatabaseState::Connecting {
+                attempts: 0,
+                last_error: None,
+            })),
+        }
+    }
+}
+
+impl DatabaseHealth {
+    pub fn current(&self) -> DatabaseState {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set(&self, state: DatabaseState) {
+        *self.state.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = state;
+    }
+
+    /// Resolves once the startup sequence has finished, for background tasks
+    /// that must not touch the schema before it is migrated.
+    pub async fn wait_until_ready(&self) {
+        while !matches!(self.current(), DatabaseState::Ready) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
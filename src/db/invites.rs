@@ -0,0 +1,146 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct InviteRecord {
+    pub id: Uuid,
+    pub code: String,
+    pub email: Option<String>,
+    pub is_admin: bool,
+    pub max_uses: i32,
+    pub used_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+pub struct NewInvite {
+    pub code: String,
+    pub email: Option<String>,
+    pub is_admin: bool,
+    pub max_uses: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+}
+
+pub fn generate_invite_code() -> String {
+    let bytes: [u8; 16] = rand::random();
+
+    let mut output = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        output.push_str(&format!("{byte:02x}"));
+    }
+
+    output
+}
+
+pub async fn create_invite<'e>(
+    executor: impl PgExecutor<'e>,
+    new_invite: NewInvite,
+) -> Result<InviteRecord, AppError> {
+    let invite_id = Uuid::new_v4();
+
+    let query_result = sqlx::query_as::<_, InviteRecord>(
+        r#"
+        INSERT INTO invites (id, code, email, is_admin, max_uses, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+        "#,
+    )
+    .bind(invite_id)
+    .bind(new_invite.code)
+    .bind(new_invite.email)
+    .bind(new_invite.is_admin)
+    .bind(new_invite.max_uses)
+    .bind(new_invite.expires_at)
+    .bind(new_invite.created_by)
+    .fetch_one(executor)
+    .await;
+
+    match query_result {
+        Ok(record) => Ok(record),
+        Err(sqlx::Error::Database(db_error)) if db_error.code().as_deref() == Some("23505") => {
+            Err(AppError::Conflict("invite code already exists".to_string()))
+        }
+        Err(other) => Err(AppError::from(other)),
+    }
+}
+
+pub async fn list_invites<'e>(executor: impl PgExecutor<'e>) -> Result<Vec<InviteRecord>, AppError> {
+    let records = sqlx::query_as::<_, InviteRecord>(
+        r#"
+        SELECT id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+        FROM invites
+        ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records)
+}
+
+pub async fn find_invite<'e>(
+    executor: impl PgExecutor<'e>,
+    invite_id: Uuid,
+) -> Result<Option<InviteRecord>, AppError> {
+    let record = sqlx::query_as::<_, InviteRecord>(
+        r#"
+        SELECT id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+        FROM invites
+        WHERE id = $1
+        "#,
+    )
+    .bind(invite_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+pub async fn revoke_invite<'e>(
+    executor: impl PgExecutor<'e>,
+    invite_id: Uuid,
+) -> Result<Option<InviteRecord>, AppError> {
+    let record = sqlx::query_as::<_, InviteRecord>(
+        r#"
+        UPDATE invites
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE id = $1
+        RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+        "#,
+    )
+    .bind(invite_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+/// Atomically takes one use of a redeemable invite. Returns `None` when the code
+/// is unknown, revoked, expired or exhausted. Callers run this inside the same
+/// transaction as `create_user` so a failed registration gives the use back.
+pub async fn consume_invite<'e>(
+    executor: impl PgExecutor<'e>,
+    code: &str,
+) -> Result<Option<InviteRecord>, AppError> {
+    let record = sqlx::query_as::<_, InviteRecord>(
+        r#"
+        UPDATE invites
+        SET used_count = used_count + 1
+        WHERE code = $1
+          AND revoked_at IS NULL
+          AND (expires_at IS NULL OR expires_at > NOW())
+          AND used_count < max_uses
+        RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+        "#,
+    )
+    .bind(code)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
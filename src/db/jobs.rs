@@ -0,0 +1,226 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder};
+use std::time::Duration;
+
+/// Channel notified by the `jobs_notify` trigger whenever jobs are inserted.
+pub const JOBS_CHANNEL: &str = "jobs_enqueued";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Dead,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Dead => "dead",
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct JobRecord {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub unique_key: Option<String>,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct NewJob {
+    pub kind: &'static str,
+    pub payload: serde_json::Value,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub unique_key: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct JobFilter {
+    pub status: Option<JobStatus>,
+    pub kind: Option<String>,
+}
+
+const JOB_COLUMNS: &str = "id, kind, payload, status, attempts, max_attempts, run_at, unique_key, \
+     locked_by, locked_until, last_error, created_at, updated_at";
+
+/// Inserts the job on the caller's executor, so it only runs if that transaction
+/// commits. Returns `None` when a pending job with the same `unique_key` exists.
+pub async fn enqueue<'e>(
+    executor: impl PgExecutor<'e>,
+    new_job: NewJob,
+) -> Result<Option<i64>, AppError> {
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts, run_at, unique_key)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (unique_key) WHERE status = 'pending' DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(new_job.kind)
+    .bind(new_job.payload)
+    .bind(new_job.max_attempts)
+    .bind(new_job.run_at)
+    .bind(new_job.unique_key)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(id)
+}
+
+/// Takes the next due job, or one whose lease has run out, for `lease` and
+/// counts the attempt. Concurrent workers skip each other's rows instead of waiting.
+pub async fn claim(pool: &PgPool, worker: &str, lease: Duration) -> Result<Option<JobRecord>, AppError> {
+    let record = sqlx::query_as::<_, JobRecord>(&format!(
+        r#"
+        UPDATE jobs
+        SET status = 'running',
+            attempts = attempts + 1,
+            locked_by = $1,
+            locked_until = NOW() + make_interval(secs => $2),
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id
+            FROM jobs
+            WHERE (status = 'pending' AND run_at <= NOW())
+               OR (status = 'running' AND locked_until < NOW())
+            ORDER BY run_at, id
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {JOB_COLUMNS}
+        "#
+    ))
+    .bind(worker)
+    .bind(lease.as_secs_f64())
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record)
+}
+
+/// Deletes a job that succeeded. `false` when the lease was lost to another worker.
+pub async fn complete(pool: &PgPool, job: &JobRecord) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        "DELETE FROM jobs WHERE id = $1 AND status = 'running' AND locked_by = $2 AND attempts = $3",
+    )
+    .bind(job.id)
+    .bind(&job.locked_by)
+    .bind(job.attempts)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Records a failed attempt: back to pending at `retry_at`, or dead when `None`.
+pub async fn fail(
+    pool: &PgPool,
+    job: &JobRecord,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN $4::timestamptz IS NULL THEN 'dead' ELSE 'pending' END,
+            run_at = COALESCE($4, run_at),
+            last_error = $5,
+            locked_by = NULL,
+            locked_until = NULL,
+            updated_at = NOW()
+        WHERE id = $1 AND status = 'running' AND locked_by = $2 AND attempts = $3
+        "#,
+    )
+    .bind(job.id)
+    .bind(&job.locked_by)
+    .bind(job.attempts)
+    .bind(retry_at)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Newest first. `before_id` is the cursor from the previous page.
+pub async fn list_jobs(
+    pool: &PgPool,
+    filter: &JobFilter,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<JobRecord>, AppError> {
+    let mut query = QueryBuilder::<Postgres>::new(format!("SELECT {JOB_COLUMNS} FROM jobs WHERE TRUE"));
+
+    if let Some(status) = filter.status {
+        query.push(" AND status = ").push_bind(status.as_str());
+    }
+    if let Some(kind) = &filter.kind {
+        query.push(" AND kind = ").push_bind(kind.clone());
+    }
+    if let Some(before_id) = before_id {
+        query.push(" AND id < ").push_bind(before_id);
+    }
+
+    query.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+    let records = query.build_query_as::<JobRecord>().fetch_all(pool).await?;
+
+    Ok(records)
+}
+
+pub async fn find_job<'e>(executor: impl PgExecutor<'e>, job_id: i64) -> Result<Option<JobRecord>, AppError> {
+    let record = sqlx::query_as::<_, JobRecord>(&format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = $1"))
+        .bind(job_id)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(record)
+}
+
+/// Gives a dead job a fresh set of attempts, due now, and wakes the workers.
+/// `None` when the job does not exist or is not dead.
+pub async fn retry_dead_job(conn: &mut PgConnection, job_id: i64) -> Result<Option<JobRecord>, AppError> {
+    let record = sqlx::query_as::<_, JobRecord>(&format!(
+        r#"
+        UPDATE jobs
+        SET status = 'pending', attempts = 0, run_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND status = 'dead'
+        RETURNING {JOB_COLUMNS}
+        "#
+    ))
+    .bind(job_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|error| match error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            AppError::Conflict("a pending job with the same unique key already exists".to_string())
+        }
+        other => AppError::from(other),
+    })?;
+
+    // Only inserts fire the `jobs_notify` trigger.
+    if record.is_some() {
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(JOBS_CHANNEL)
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(record)
+}
@@ -0,0 +1,328 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::{collections::BTreeMap, time::Instant};
+use tracing::{info, warn};
+
+use crate::{db::schema, error::AppError};
+
+/// Session-level advisory lock held while migrating, so instances starting
+/// together never apply the same migration twice.
+const MIGRATION_LOCK_ID: i64 = 0x7377_6172_6d5f_6d67;
+
+/// Deployments created before migrations existed already match this version.
+const BASELINE_VERSION: i64 = 1;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    sql: &'static str,
+}
+
+impl Migration {
+    /// Line endings are normalized so a checkout with CRLF files hashes the same.
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.sql.replace("\r\n", "\n").as_bytes()))
+    }
+
+    pub fn label(&self) -> String {
+        format!("{:04}_{}", self.version, self.name)
+    }
+}
+
+/// Every migration in the order it is applied. Append new files here; never edit
+/// one that has shipped, its checksum is recorded by every deployment that ran it.
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("../../migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "user_version",
+        sql: include_str!("../../migrations/0002_user_version.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "organizations",
+        sql: include_str!("../../migrations/0003_organizations.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "row_level_security",
+        sql: include_str!("../../migrations/0004_row_level_security.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "jobs",
+        sql: include_str!("../../migrations/0005_jobs.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "outbox",
+        sql: include_str!("../../migrations/0006_outbox.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "membership_policies",
+        sql: include_str!("../../migrations/0007_membership_policies.sql"),
+    },
+];
+
+#[derive(Debug, sqlx::FromRow)]
+struct AppliedMigration {
+    version: i64,
+    name: String,
+    checksum: String,
+    applied_at: DateTime<Utc>,
+    baselined: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    /// Recorded without running because the schema already existed.
+    Baselined,
+    Pending,
+    /// The file changed after it was applied.
+    ChecksumMismatch,
+    /// Applied by a newer build that this binary doesn't know about.
+    Unknown,
+}
+
+impl MigrationState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Applied => "applied",
+            Self::Baselined => "baselined",
+            Self::Pending => "pending",
+            Self::ChecksumMismatch => "checksum mismatch",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    pub fn is_problem(self) -> bool {
+        !matches!(self, Self::Applied | Self::Baselined)
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+/// Applies every pending migration, each in its own transaction, and returns how many ran.
+pub async fn run_migrations(pool: &PgPool) -> Result<usize, AppError> {
+    let mut conn = pool.acquire().await?;
+
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_ID)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = apply_pending(&mut conn).await;
+
+    let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_ID)
+        .execute(&mut *conn)
+        .await;
+
+    if unlocked.is_err() {
+        // Closing the session is the only other way to release the lock.
+        drop(conn.detach());
+    }
+
+    let applied = result?;
+    unlocked?;
+
+    Ok(applied)
+}
+
+/// Compares the recorded migrations with the ones built into this binary without changing anything.
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, AppError> {
+    let mut conn = pool.acquire().await?;
+    let applied = if schema::table_exists(&mut conn, "schema_migrations").await? {
+        load_applied(&mut conn).await?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS
+        .iter()
+        .map(|migration| match applied.get(&migration.version) {
+            Some(record) => MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state: if record.checksum != migration.checksum() {
+                    MigrationState::ChecksumMismatch
+                } else if record.baselined {
+                    MigrationState::Baselined
+                } else {
+                    MigrationState::Applied
+                },
+                applied_at: Some(record.applied_at),
+            },
+            None => MigrationStatus {
+                version: migration.version,
+                name: migration.name.to_string(),
+                state: MigrationState::Pending,
+                applied_at: None,
+            },
+        })
+        .collect();
+
+    for record in applied.values() {
+        if !MIGRATIONS.iter().any(|migration| migration.version == record.version) {
+            statuses.push(MigrationStatus {
+                version: record.version,
+                name: record.name.clone(),
+                state: MigrationState::Unknown,
+                applied_at: Some(record.applied_at),
+            });
+        }
+    }
+
+    statuses.sort_by_key(|status| status.version);
+
+    Ok(statuses)
+}
+
+async fn apply_pending(conn: &mut PgConnection) -> Result<usize, AppError> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            execution_ms BIGINT NOT NULL,
+            baselined BOOLEAN NOT NULL DEFAULT FALSE
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let mut applied = load_applied(conn).await?;
+
+    if applied.is_empty() && schema::table_exists(&mut *conn, "users").await? {
+        warn!("existing schema without migration history detected; baselining");
+        baseline(conn).await?;
+        applied = load_applied(conn).await?;
+    }
+
+    for migration in MIGRATIONS {
+        if let Some(record) = applied.get(&migration.version)
+            && record.checksum != migration.checksum()
+        {
+            return Err(AppError::SchemaMismatch(format!(
+                "migration {} was modified after it was applied",
+                migration.label()
+            )));
+        }
+    }
+
+    for record in applied.values() {
+        if !MIGRATIONS.iter().any(|migration| migration.version == record.version) {
+            warn!(
+                version = record.version,
+                name = %record.name,
+                "database has a migration this build does not know about"
+            );
+        }
+    }
+
+    let mut count = 0;
+
+    for migration in MIGRATIONS {
+        if applied.contains_key(&migration.version) {
+            continue;
+        }
+
+        let started = Instant::now();
+        let mut tx = conn.begin().await?;
+
+        // `Executor::execute` keeps the future `Send` for startup running in a spawned task.
+        tx.execute(sqlx::raw_sql(migration.sql))
+            .await
+            .map_err(|error| {
+                AppError::SchemaMismatch(format!("migration {} failed: {error}", migration.label()))
+            })?;
+
+        record_migration(&mut tx, migration, started, false).await?;
+        tx.commit().await?;
+
+        info!(migration = %migration.label(), "migration applied");
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Upgrades a schema created by the old `ensure_schema` to the baseline shape, then
+/// records the baseline migrations as applied without running them again.
+async fn baseline(conn: &mut PgConnection) -> Result<(), AppError> {
+    schema::upgrade_legacy_users_table(conn).await?;
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version <= BASELINE_VERSION)
+    {
+        let started = Instant::now();
+        let mut tx = conn.begin().await?;
+
+        // Baseline files are idempotent; re-running them creates whatever tables the
+        // legacy deployment never got to.
+        tx.execute(sqlx::raw_sql(migration.sql)).await?;
+        record_migration(&mut tx, migration, started, true).await?;
+        tx.commit().await?;
+
+        info!(migration = %migration.label(), "migration baselined");
+    }
+
+    Ok(())
+}
+
+async fn record_migration(
+    conn: &mut PgConnection,
+    migration: &Migration,
+    started: Instant,
+    baselined: bool,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO schema_migrations (version, name, checksum, execution_ms, baselined)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(migration.version)
+    .bind(migration.name)
+    .bind(migration.checksum())
+    .bind(started.elapsed().as_millis() as i64)
+    .bind(baselined)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn load_applied(conn: &mut PgConnection) -> Result<BTreeMap<i64, AppliedMigration>, AppError> {
+    let records: Vec<AppliedMigration> = sqlx::query_as(
+        r#"
+        SELECT version, name, checksum, applied_at, baselined
+        FROM schema_migrations
+        ORDER BY version
+        "#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| (record.version, record))
+        .collect())
+}
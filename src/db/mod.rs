@@ -1,2 +1,143 @@
+pub mod audit;
+pub mod feature_flags;
+pub mod health;
+pub mod invites;
+pub mod jobs;
+pub mod migrations;
+pub mod notify;
+pub mod organizations;
+pub mod outbox;
+pub mod password_tokens;
+pub mod routing;
 pub mod schema;
+pub mod settings;
+pub mod stats;
+pub mod store;
+pub mod tenant;
 pub mod users;
+
+use crate::{config::DatabasePoolConfig, error::AppError};
+use health::{DatabaseHealth, DatabaseState};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    PgPool,
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseKind {
+    Postgres,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl DatabaseKind {
+    pub fn from_url(database_url: &str) -> Result<Self, AppError> {
+        let scheme = database_url.split(':').next().unwrap_or_default();
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::Sqlite),
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => Err(AppError::ServiceUnavailable(
+                "DATABASE_URL points to SQLite but this build lacks the `sqlite` feature".to_string(),
+            )),
+            _ => Err(AppError::ServiceUnavailable(format!(
+                "unsupported DATABASE_URL scheme '{scheme}'; expected postgres:// or sqlite:"
+            ))),
+        }
+    }
+}
+
+/// Builds the pool without opening a connection; the first query connects.
+pub fn connect_lazy(database_url: &str, config: &DatabasePoolConfig) -> Result<PgPool, AppError> {
+    let mut options = PgConnectOptions::from_str(database_url).map_err(|error| {
+        AppError::ServiceUnavailable(format!("invalid PostgreSQL DATABASE_URL: {error}"))
+    })?;
+
+    if let Some(statement_timeout) = config.statement_timeout {
+        let millis = statement_timeout.as_millis().to_string();
+        options = options.options([("statement_timeout", millis.as_str())]);
+    }
+
+    Ok(PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .connect_lazy_with(options))
+}
+
+/// Connects, retrying until `config.startup_deadline` passes.
+pub async fn connect(database_url: &str, config: &DatabasePoolConfig) -> Result<PgPool, AppError> {
+    let pool = connect_lazy(database_url, config)?;
+    wait_for_database(&pool, Some(config.startup_deadline), &DatabaseHealth::default()).await?;
+
+    Ok(pool)
+}
+
+/// Pings PostgreSQL until it answers, doubling the delay between attempts up to
+/// `MAX_BACKOFF`. Gives up with the last error once `deadline` has passed;
+/// `None` retries forever. Every failed attempt is recorded in `health`.
+pub async fn wait_for_database(
+    pool: &PgPool,
+    deadline: Option<Duration>,
+    health: &DatabaseHealth,
+) -> Result<(), AppError> {
+    let started = Instant::now();
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        let error = match sqlx::query("SELECT 1").execute(pool).await {
+            Ok(_) => {
+                info!(attempts, "connected to PostgreSQL");
+                return Ok(());
+            }
+            Err(error) => error.to_string(),
+        };
+
+        health.set(DatabaseState::Connecting {
+            attempts,
+            last_error: Some(error.clone()),
+        });
+
+        if deadline.is_some_and(|deadline| started.elapsed() + backoff > deadline) {
+            return Err(AppError::ServiceUnavailable(format!(
+                "PostgreSQL unreachable after {attempts} attempt(s): {error}"
+            )));
+        }
+
+        warn!(attempts, retry_in_ms = backoff.as_millis() as u64, "PostgreSQL unreachable: {error}");
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// SQLite takes one writer at a time, so a single connection serializes units of
+/// work instead of failing them with `SQLITE_BUSY`.
+#[cfg(feature = "sqlite")]
+pub async fn connect_sqlite(database_url: &str) -> Result<sqlx::SqlitePool, AppError> {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    let options = SqliteConnectOptions::from_str(database_url)
+        .map_err(|error| AppError::ServiceUnavailable(format!("invalid SQLite DATABASE_URL: {error}")))?
+        .create_if_missing(true)
+        .foreign_keys(true);
+
+    SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(|error| AppError::ServiceUnavailable(format!("failed to open SQLite database: {error}")))
+}
@@ -0,0 +1,60 @@
+use sqlx::{postgres::PgListener, PgPool};
+use std::{future::Future, time::Duration};
+use tracing::{info, warn};
+
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Calls `on_change` whenever `channel` is notified, for the lifetime of the
+/// process. A lost connection may have swallowed notifications, so `on_change`
+/// also runs after every (re)subscription.
+pub async fn watch<F, Fut>(pool: PgPool, channel: &'static str, mut on_change: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    listen(pool, channel, |_| on_change()).await;
+}
+
+/// Like [`watch`], but hands over each notification's payload. `on_message` gets
+/// `None` after every (re)subscription, when notifications may have been missed.
+pub async fn listen<F, Fut>(pool: PgPool, channel: &'static str, mut on_message: F)
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    loop {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(listener) => listener,
+            Err(error) => {
+                warn!("listener for '{channel}' failed to connect: {error}");
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        if let Err(error) = listener.listen(channel).await {
+            warn!("listener for '{channel}' failed to subscribe: {error}");
+            tokio::time::sleep(RETRY_DELAY).await;
+            continue;
+        }
+
+        on_message(None).await;
+        info!("listening for notifications on '{channel}'");
+
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(notification)) => on_message(Some(notification.payload().to_string())).await,
+                Ok(None) => {
+                    warn!("listener for '{channel}' lost its connection; reconnecting");
+                    break;
+                }
+                Err(error) => {
+                    warn!("listener for '{channel}' failed: {error}");
+                    break;
+                }
+            }
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
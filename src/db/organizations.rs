@@ -0,0 +1,290 @@
+use crate::{db::tenant, error::AppError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, PgConnection, PgExecutor};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl OrganizationRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Member => "member",
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        match value {
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            "owner" => Ok(Self::Owner),
+            other => Err(AppError::Internal(format!("unknown organization role '{other}'"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OrganizationRecord {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An organization as seen by one of its members.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MembershipRecord {
+    #[sqlx(flatten)]
+    pub organization: OrganizationRecord,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MemberRecord {
+    pub user_id: Uuid,
+    pub nickname: String,
+    pub email: String,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OrganizationInviteRecord {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub code: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct NewOrganization {
+    pub slug: String,
+    pub name: String,
+    pub owner_id: Uuid,
+}
+
+pub struct NewOrganizationInvite {
+    pub organization_id: Uuid,
+    pub code: String,
+    pub email: Option<String>,
+    pub role: OrganizationRole,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Uuid,
+}
+
+/// Creates the organization with `owner_id` as its first owner. `conn` must be
+/// scoped to `owner_id` with [`tenant::begin`].
+pub async fn create_organization(
+    conn: &mut PgConnection,
+    new_organization: NewOrganization,
+) -> Result<MembershipRecord, AppError> {
+    let mut tx = conn.begin().await?;
+
+    let organization_id = Uuid::new_v4();
+    tenant::enter_organization(&mut tx, organization_id).await?;
+
+    let organization = sqlx::query_as::<_, OrganizationRecord>(
+        r#"
+        INSERT INTO organizations (id, slug, name, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, slug, name, created_by, created_at
+        "#,
+    )
+    .bind(organization_id)
+    .bind(new_organization.slug)
+    .bind(new_organization.name)
+    .bind(new_organization.owner_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|error| match error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            AppError::Conflict("organization slug is already taken".to_string())
+        }
+        other => AppError::from(other),
+    })?;
+
+    let joined_at: DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        INSERT INTO organization_members (organization_id, user_id, role)
+        VALUES ($1, $2, $3)
+        RETURNING created_at
+        "#,
+    )
+    .bind(organization.id)
+    .bind(new_organization.owner_id)
+    .bind(OrganizationRole::Owner.as_str())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(MembershipRecord {
+        organization,
+        role: OrganizationRole::Owner.as_str().to_string(),
+        joined_at,
+    })
+}
+
+pub async fn list_memberships<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<Vec<MembershipRecord>, AppError> {
+    let records = sqlx::query_as::<_, MembershipRecord>(
+        r#"
+        SELECT o.id, o.slug, o.name, o.created_by, o.created_at, m.role, m.created_at AS joined_at
+        FROM organization_members m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.user_id = $1
+        ORDER BY o.name, o.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records)
+}
+
+pub async fn find_membership<'e>(
+    executor: impl PgExecutor<'e>,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<MembershipRecord>, AppError> {
+    let record = sqlx::query_as::<_, MembershipRecord>(
+        r#"
+        SELECT o.id, o.slug, o.name, o.created_by, o.created_at, m.role, m.created_at AS joined_at
+        FROM organization_members m
+        JOIN organizations o ON o.id = m.organization_id
+        WHERE m.organization_id = $1 AND m.user_id = $2
+        "#,
+    )
+    .bind(organization_id)
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+pub async fn list_members<'e>(
+    executor: impl PgExecutor<'e>,
+    organization_id: Uuid,
+) -> Result<Vec<MemberRecord>, AppError> {
+    let records = sqlx::query_as::<_, MemberRecord>(
+        r#"
+        SELECT u.id AS user_id, u.nickname, u.email, m.role, m.created_at AS joined_at
+        FROM organization_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.organization_id = $1
+        ORDER BY m.created_at, u.id
+        "#,
+    )
+    .bind(organization_id)
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records)
+}
+
+pub async fn create_invite<'e>(
+    executor: impl PgExecutor<'e>,
+    new_invite: NewOrganizationInvite,
+) -> Result<OrganizationInviteRecord, AppError> {
+    let query_result = sqlx::query_as::<_, OrganizationInviteRecord>(
+        r#"
+        INSERT INTO organization_invites (id, organization_id, code, email, role, expires_at, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, organization_id, code, email, role, expires_at, created_by, created_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(new_invite.organization_id)
+    .bind(new_invite.code)
+    .bind(new_invite.email)
+    .bind(new_invite.role.as_str())
+    .bind(new_invite.expires_at)
+    .bind(new_invite.created_by)
+    .fetch_one(executor)
+    .await;
+
+    match query_result {
+        Ok(record) => Ok(record),
+        Err(sqlx::Error::Database(db_error)) if db_error.code().as_deref() == Some("23505") => {
+            Err(AppError::Conflict("invite code already exists".to_string()))
+        }
+        Err(other) => Err(AppError::from(other)),
+    }
+}
+
+/// Redeems a pending invite for `user_id` and adds them with the invited role.
+/// `None` when the code is unknown, expired, already used or issued for another
+/// email; existing members get a conflict and the invite stays unused. `conn`
+/// must be scoped to `user_id` with [`tenant::begin`].
+pub async fn accept_invite(
+    conn: &mut PgConnection,
+    code: &str,
+    user_id: Uuid,
+    email: &str,
+) -> Result<Option<MembershipRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+
+    tenant::present_invite_code(&mut tx, code).await?;
+
+    let invite = sqlx::query_as::<_, OrganizationInviteRecord>(
+        r#"
+        UPDATE organization_invites
+        SET accepted_by = $2, accepted_at = NOW()
+        WHERE code = $1
+          AND accepted_at IS NULL
+          AND expires_at > NOW()
+          AND (email IS NULL OR lower(email) = lower($3))
+        RETURNING id, organization_id, code, email, role, expires_at, created_by, created_at
+        "#,
+    )
+    .bind(code)
+    .bind(user_id)
+    .bind(email)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(invite) = invite else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO organization_members (organization_id, user_id, role)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(invite.organization_id)
+    .bind(user_id)
+    .bind(&invite.role)
+    .execute(&mut *tx)
+    .await
+    .map_err(|error| match error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            AppError::Conflict("already a member of this organization".to_string())
+        }
+        other => AppError::from(other),
+    })?;
+
+    let membership = find_membership(&mut *tx, invite.organization_id, user_id).await?;
+
+    tx.commit().await?;
+
+    Ok(membership)
+}
@@ -0,0 +1,141 @@
+use crate::{error::AppError, events::DomainEvent};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgExecutor};
+
+/// Channel notified by the `outbox_notify` trigger whenever events are appended.
+pub const OUTBOX_CHANNEL: &str = "outbox_appended";
+
+/// Channel carrying each delivered event to every instance, as a JSON `Envelope`.
+pub const PUBLISHED_CHANNEL: &str = "outbox_published";
+
+/// PostgreSQL rejects notification payloads of 8000 bytes or more.
+pub const MAX_BROADCAST_BYTES: usize = 7999;
+
+/// Arbitrary key for `pg_try_advisory_xact_lock`; only one dispatcher publishes at a time.
+const DISPATCH_LOCK_ID: i64 = 0x7377_6172_6d5f_6f62;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OutboxRecord {
+    pub id: i64,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub occurred_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+/// Appends the events on the caller's executor, so they are only published if
+/// that transaction commits. Call it after writing the aggregate's row: the row
+/// lock then orders concurrent appends for one aggregate by their commit.
+pub async fn append<'e>(executor: impl PgExecutor<'e>, events: &[DomainEvent]) -> Result<(), AppError> {
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    let mut aggregate_types = Vec::with_capacity(events.len());
+    let mut aggregate_ids = Vec::with_capacity(events.len());
+    let mut event_types = Vec::with_capacity(events.len());
+    let mut payloads = Vec::with_capacity(events.len());
+
+    for event in events {
+        let (aggregate_type, aggregate_id) = event.aggregate();
+        aggregate_types.push(aggregate_type);
+        aggregate_ids.push(aggregate_id);
+        event_types.push(event.event_type());
+        payloads.push(event.payload()?);
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO outbox (aggregate_type, aggregate_id, event_type, payload)
+        SELECT * FROM UNNEST($1::text[], $2::uuid[], $3::text[], $4::jsonb[])
+        "#,
+    )
+    .bind(aggregate_types)
+    .bind(aggregate_ids)
+    .bind(event_types)
+    .bind(payloads)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Takes the dispatch lock for the rest of the transaction. `false` when another
+/// dispatcher holds it.
+pub async fn try_lock_dispatch(conn: &mut PgConnection) -> Result<bool, AppError> {
+    let locked = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_xact_lock($1)")
+        .bind(DISPATCH_LOCK_ID)
+        .fetch_one(conn)
+        .await?;
+
+    Ok(locked)
+}
+
+/// The oldest event, skipping every aggregate whose earliest pending event is
+/// waiting for a retry, so a failed event holds back the ones after it.
+pub async fn fetch_next(conn: &mut PgConnection) -> Result<Option<OutboxRecord>, AppError> {
+    let record = sqlx::query_as::<_, OutboxRecord>(
+        r#"
+        SELECT id, event_type, payload, occurred_at, attempts
+        FROM outbox o
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM outbox earlier
+            WHERE earlier.aggregate_type = o.aggregate_type
+              AND earlier.aggregate_id = o.aggregate_id
+              AND earlier.id <= o.id
+              AND earlier.next_attempt_at > NOW()
+        )
+        ORDER BY id
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(record)
+}
+
+/// Deletes an event every subscriber has handled.
+pub async fn remove(conn: &mut PgConnection, id: i64) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM outbox WHERE id = $1")
+        .bind(id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Sends `payload` on [`PUBLISHED_CHANNEL`] when the transaction commits.
+pub async fn broadcast(conn: &mut PgConnection, payload: &str) -> Result<(), AppError> {
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(PUBLISHED_CHANNEL)
+        .bind(payload)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Records a failed delivery and holds the event back until `retry_at`.
+pub async fn defer(
+    conn: &mut PgConnection,
+    id: i64,
+    error: &str,
+    retry_at: DateTime<Utc>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        UPDATE outbox
+        SET attempts = attempts + 1, next_attempt_at = $2, last_error = $3
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(retry_at)
+    .bind(error)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::error::AppError;
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+pub const PASSWORD_TOKEN_TTL: Duration = Duration::hours(72);
+
+/// Issues a single-use token that lets the user choose a password. Only its
+/// SHA-256 is stored; the returned raw token goes out by email. The user's earlier
+/// unused tokens are deleted, so a retried email never leaves several live links.
+pub async fn issue_password_token<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<String, AppError> {
+    let bytes: [u8; 32] = rand::random();
+
+    let mut token = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        token.push_str(&format!("{byte:02x}"));
+    }
+
+    sqlx::query(
+        r#"
+        WITH superseded AS (
+            DELETE FROM password_tokens WHERE user_id = $2 AND used_at IS NULL
+        )
+        INSERT INTO password_tokens (id, user_id, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(user_id)
+    .bind(hash_token(&token))
+    .bind(Utc::now() + PASSWORD_TOKEN_TTL)
+    .execute(executor)
+    .await?;
+
+    Ok(token)
+}
+
+/// Marks the token used and returns its user, or `None` when it is unknown,
+/// expired or already used.
+pub async fn consume_password_token<'e>(
+    executor: impl PgExecutor<'e>,
+    token: &str,
+) -> Result<Option<Uuid>, AppError> {
+    let user_id = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        UPDATE password_tokens
+        SET used_at = NOW()
+        WHERE token_hash = $1
+          AND used_at IS NULL
+          AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(hash_token(token.trim()))
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(user_id)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Deletes tokens that can no longer be used. Returns how many were removed.
+pub async fn purge_password_tokens<'e>(executor: impl PgExecutor<'e>) -> Result<u64, AppError> {
+    let result = sqlx::query("DELETE FROM password_tokens WHERE used_at IS NOT NULL OR expires_at <= NOW()")
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The primary pool plus optional read replicas.
+///
+/// Writes, and reads that must see them, use [`primary`](Self::primary). Reads that
+/// tolerate replication lag use [`replica`](Self::replica), or
+/// [`reader_for`](Self::reader_for) when made on behalf of a user: a user who wrote
+/// within the sticky window keeps reading from the primary, so they see their own
+/// changes. The window is tracked per process only.
+#[derive(Clone)]
+pub struct DatabasePools {
+    primary: PgPool,
+    replicas: Arc<[PgPool]>,
+    next_replica: Arc<AtomicUsize>,
+    recent_writes: Arc<Mutex<HashMap<Uuid, Instant>>>,
+    sticky_window: Duration,
+}
+
+impl DatabasePools {
+    pub fn new(primary: PgPool, replicas: Vec<PgPool>, sticky_window: Duration) -> Self {
+        Self {
+            primary,
+            replicas: replicas.into(),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            recent_writes: Arc::new(Mutex::new(HashMap::new())),
+            sticky_window,
+        }
+    }
+
+    pub fn primary(&self) -> &PgPool {
+        &self.primary
+    }
+
+    pub fn replica_count(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Round-robins over the replicas; the primary when none are configured.
+    pub fn replica(&self) -> &PgPool {
+        if self.replicas.is_empty() {
+            return &self.primary;
+        }
+
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replicas.len();
+        &self.replicas[index]
+    }
+
+    pub fn reader_for(&self, user_id: Uuid) -> &PgPool {
+        if self.wrote_recently(user_id) {
+            &self.primary
+        } else {
+            self.replica()
+        }
+    }
+
+    /// Pins reads for `user_id` to the primary for the sticky window.
+    pub fn record_write(&self, user_id: Uuid) {
+        if self.replicas.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut recent_writes = self.lock_recent_writes();
+        recent_writes.retain(|_, written_at| now.duration_since(*written_at) < self.sticky_window);
+        recent_writes.insert(user_id, now);
+    }
+
+    fn wrote_recently(&self, user_id: Uuid) -> bool {
+        self.lock_recent_writes()
+            .get(&user_id)
+            .is_some_and(|written_at| written_at.elapsed() < self.sticky_window)
+    }
+
+    fn lock_recent_writes(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Instant>> {
+        self.recent_writes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
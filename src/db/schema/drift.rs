@@ -0,0 +1,443 @@
+//! Compares the live database against the declarative spec. Every object is
+//! reduced to a name and a rendered definition, so the comparison itself is
+//! just a keyed diff and column order never matters.
+
+use std::{collections::BTreeMap, fmt};
+
+use super::spec::{ConstraintKind, TableSpec};
+use crate::config::SchemaCheckMode;
+
+/// Rendered definitions of one live table's objects, keyed by object name.
+#[derive(Debug, Default)]
+pub struct LiveTable {
+    pub columns: BTreeMap<String, String>,
+    pub constraints: BTreeMap<String, String>,
+    pub indexes: BTreeMap<String, String>,
+    pub policies: BTreeMap<String, String>,
+    /// Rendered with [`row_security_definition`].
+    pub row_security: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftKind {
+    Missing,
+    Extra,
+    Mismatched,
+}
+
+impl DriftKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Extra => "extra",
+            Self::Mismatched => "mismatched",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Drift {
+    pub kind: DriftKind,
+    /// Object type and qualified name, e.g. `column users.email`.
+    pub object: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl Drift {
+    /// Extras are additive, so tolerant mode lets them through.
+    pub fn is_blocking(&self, mode: SchemaCheckMode) -> bool {
+        self.kind != DriftKind::Extra || mode == SchemaCheckMode::Strict
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind.as_str(), self.object)?;
+
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => write!(f, ": expected {expected}, got {actual}"),
+            (Some(expected), None) => write!(f, " ({expected})"),
+            (None, Some(actual)) => write!(f, " ({actual})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SchemaReport {
+    pub drifts: Vec<Drift>,
+}
+
+impl SchemaReport {
+    pub fn blocking(&self, mode: SchemaCheckMode) -> impl Iterator<Item = &Drift> {
+        self.drifts.iter().filter(move |drift| drift.is_blocking(mode))
+    }
+
+    pub fn tolerated(&self, mode: SchemaCheckMode) -> impl Iterator<Item = &Drift> {
+        self.drifts.iter().filter(move |drift| !drift.is_blocking(mode))
+    }
+}
+
+pub fn column_definition(data_type: &str, nullable: bool) -> String {
+    if nullable {
+        format!("{data_type} null")
+    } else {
+        format!("{data_type} not null")
+    }
+}
+
+/// `references` is the referenced table and the `ON DELETE` action of a foreign key.
+pub fn constraint_definition(
+    kind: &str,
+    columns: &[impl AsRef<str>],
+    references: Option<(&str, &str)>,
+) -> String {
+    let columns: Vec<&str> = columns.iter().map(AsRef::as_ref).collect();
+    let mut definition = format!("{kind} ({})", columns.join(", "));
+
+    if let Some((table, on_delete)) = references {
+        definition.push_str(&format!(" references {table} on delete {on_delete}"));
+    }
+
+    definition
+}
+
+/// Includes the expression, so a check loosened under its old name is caught.
+pub fn check_definition(columns: &[impl AsRef<str>], expression: &str) -> String {
+    format!(
+        "{} {}",
+        constraint_definition("check", columns, None),
+        normalize_expression(expression)
+    )
+}
+
+pub fn index_definition(unique: bool, keys: &str) -> String {
+    if unique {
+        format!("unique index ({keys})")
+    } else {
+        format!("index ({keys})")
+    }
+}
+
+pub fn row_security_definition(enabled: bool, forced: bool) -> String {
+    match (enabled, forced) {
+        (true, true) => "enabled, forced".to_string(),
+        (true, false) => "enabled".to_string(),
+        (false, _) => "disabled".to_string(),
+    }
+}
+
+/// Includes the expressions, so a policy weakened under its old name is caught.
+pub fn policy_definition(command: &str, using: Option<&str>, check: Option<&str>) -> String {
+    let mut definition = format!("policy for {}", command.to_ascii_lowercase());
+
+    if let Some(using) = using {
+        definition.push_str(&format!(" using {}", normalize_expression(using)));
+    }
+    if let Some(check) = check {
+        definition.push_str(&format!(" with check {}", normalize_expression(check)));
+    }
+
+    definition
+}
+
+/// Deparsed expressions break long subqueries over indented lines.
+fn normalize_expression(expression: &str) -> String {
+    expression.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn diff_schema(spec: &[TableSpec], live: &BTreeMap<String, LiveTable>) -> SchemaReport {
+    let mut report = SchemaReport::default();
+
+    for table in spec {
+        let Some(live_table) = live.get(table.name) else {
+            report.drifts.push(Drift {
+                kind: DriftKind::Missing,
+                object: format!("table {}", table.name),
+                expected: None,
+                actual: None,
+            });
+            continue;
+        };
+
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| (column.name, column_definition(column.data_type, column.nullable)));
+        diff_objects(&mut report, "column", table.name, columns, &live_table.columns);
+
+        let constraints = table.constraints.iter().map(|constraint| {
+            let definition = match constraint.kind {
+                ConstraintKind::PrimaryKey => {
+                    constraint_definition("primary key", constraint.columns, None)
+                }
+                ConstraintKind::Unique => constraint_definition("unique", constraint.columns, None),
+                ConstraintKind::Check { expression } => check_definition(constraint.columns, expression),
+                ConstraintKind::ForeignKey { table, on_delete } => {
+                    constraint_definition("foreign key", constraint.columns, Some((table, on_delete)))
+                }
+            };
+            (constraint.name, definition)
+        });
+        diff_objects(&mut report, "constraint", table.name, constraints, &live_table.constraints);
+
+        let indexes = table
+            .indexes
+            .iter()
+            .map(|index| (index.name, index_definition(index.unique, index.keys)));
+        diff_objects(&mut report, "index", table.name, indexes, &live_table.indexes);
+
+        let policies = table
+            .policies
+            .iter()
+            .map(|policy| (policy.name, policy_definition(policy.command, policy.using, policy.check)));
+        diff_objects(&mut report, "policy", table.name, policies, &live_table.policies);
+
+        let row_security = row_security_definition(table.row_security, table.row_security);
+        if live_table.row_security != row_security {
+            report.drifts.push(Drift {
+                kind: DriftKind::Mismatched,
+                object: format!("row security {}", table.name),
+                expected: Some(row_security),
+                actual: Some(live_table.row_security.clone()),
+            });
+        }
+    }
+
+    for table_name in live.keys() {
+        if !spec.iter().any(|table| table.name == table_name) {
+            report.drifts.push(Drift {
+                kind: DriftKind::Extra,
+                object: format!("table {table_name}"),
+                expected: None,
+                actual: None,
+            });
+        }
+    }
+
+    report
+}
+
+fn diff_objects<'a>(
+    report: &mut SchemaReport,
+    object_type: &str,
+    table_name: &str,
+    expected: impl Iterator<Item = (&'a str, String)>,
+    actual: &BTreeMap<String, String>,
+) {
+    let mut seen = Vec::new();
+
+    for (name, expected_definition) in expected {
+        seen.push(name);
+        let object = format!("{object_type} {table_name}.{name}");
+
+        match actual.get(name) {
+            None => report.drifts.push(Drift {
+                kind: DriftKind::Missing,
+                object,
+                expected: Some(expected_definition),
+                actual: None,
+            }),
+            Some(actual_definition) if *actual_definition != expected_definition => {
+                report.drifts.push(Drift {
+                    kind: DriftKind::Mismatched,
+                    object,
+                    expected: Some(expected_definition),
+                    actual: Some(actual_definition.clone()),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (name, actual_definition) in actual {
+        if !seen.contains(&name.as_str()) {
+            report.drifts.push(Drift {
+                kind: DriftKind::Extra,
+                object: format!("{object_type} {table_name}.{name}"),
+                expected: None,
+                actual: Some(actual_definition.clone()),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        check_definition, column_definition, constraint_definition, diff_schema, policy_definition,
+        row_security_definition, DriftKind, LiveTable, SchemaReport,
+    };
+    use crate::{
+        config::SchemaCheckMode,
+        db::schema::spec::{ColumnSpec, ConstraintKind, ConstraintSpec, PolicySpec, TableSpec},
+    };
+
+    const OWNER_ONLY: &str = "(owner = (current_setting('swarm.current_user'::text, true))::uuid)";
+
+    static SPEC: &[TableSpec] = &[TableSpec {
+        name: "widgets",
+        columns: &[
+            ColumnSpec { name: "id", data_type: "uuid", nullable: false },
+            ColumnSpec { name: "owner", data_type: "uuid", nullable: false },
+            ColumnSpec { name: "size", data_type: "integer", nullable: true },
+        ],
+        constraints: &[
+            ConstraintSpec { name: "widgets_pkey", kind: ConstraintKind::PrimaryKey, columns: &["id"] },
+            ConstraintSpec {
+                name: "widgets_size_check",
+                kind: ConstraintKind::Check { expression: "((size > 0))" },
+                columns: &["size"],
+            },
+        ],
+        indexes: &[],
+        row_security: true,
+        policies: &[PolicySpec {
+            name: "widgets_select",
+            command: "select",
+            using: Some(OWNER_ONLY),
+            check: None,
+        }],
+    }];
+
+    /// The live side of [`SPEC`], rendered the way `load_live_schema` renders it.
+    fn live_widgets() -> BTreeMap<String, LiveTable> {
+        let mut table = LiveTable {
+            row_security: row_security_definition(true, true),
+            ..LiveTable::default()
+        };
+        table.columns.insert("id".to_string(), column_definition("uuid", false));
+        table.columns.insert("owner".to_string(), column_definition("uuid", false));
+        table.columns.insert("size".to_string(), column_definition("integer", true));
+        table
+            .constraints
+            .insert("widgets_pkey".to_string(), constraint_definition("primary key", &["id"], None));
+        table
+            .constraints
+            .insert("widgets_size_check".to_string(), check_definition(&["size"], "((size > 0))"));
+        table.policies.insert(
+            "widgets_select".to_string(),
+            policy_definition("SELECT", Some(OWNER_ONLY), None),
+        );
+
+        BTreeMap::from([("widgets".to_string(), table)])
+    }
+
+    fn widgets(live: &mut BTreeMap<String, LiveTable>) -> &mut LiveTable {
+        live.get_mut("widgets").unwrap()
+    }
+
+    fn kinds(report: &SchemaReport) -> Vec<(DriftKind, &str)> {
+        report
+            .drifts
+            .iter()
+            .map(|drift| (drift.kind, drift.object.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn matching_schema_has_no_drift() {
+        assert!(diff_schema(SPEC, &live_widgets()).drifts.is_empty());
+    }
+
+    #[test]
+    fn reports_missing_objects() {
+        let mut live = live_widgets();
+        widgets(&mut live).columns.remove("size");
+        widgets(&mut live).policies.remove("widgets_select");
+
+        let report = diff_schema(SPEC, &live);
+        assert_eq!(
+            kinds(&report),
+            [
+                (DriftKind::Missing, "column widgets.size"),
+                (DriftKind::Missing, "policy widgets.widgets_select"),
+            ]
+        );
+        assert!(report.drifts.iter().all(|drift| drift.is_blocking(SchemaCheckMode::Tolerant)));
+
+        let report = diff_schema(SPEC, &BTreeMap::new());
+        assert_eq!(kinds(&report), [(DriftKind::Missing, "table widgets")]);
+    }
+
+    #[test]
+    fn extras_only_block_in_strict_mode() {
+        let mut live = live_widgets();
+        widgets(&mut live)
+            .indexes
+            .insert("widgets_size_idx".to_string(), "index (size)".to_string());
+        live.insert("gadgets".to_string(), LiveTable::default());
+
+        let report = diff_schema(SPEC, &live);
+        assert_eq!(
+            kinds(&report),
+            [
+                (DriftKind::Extra, "index widgets.widgets_size_idx"),
+                (DriftKind::Extra, "table gadgets"),
+            ]
+        );
+        assert_eq!(report.blocking(SchemaCheckMode::Tolerant).count(), 0);
+        assert_eq!(report.tolerated(SchemaCheckMode::Tolerant).count(), 2);
+        assert_eq!(report.blocking(SchemaCheckMode::Strict).count(), 2);
+        assert_eq!(report.tolerated(SchemaCheckMode::Strict).count(), 0);
+    }
+
+    #[test]
+    fn reports_mismatched_definitions() {
+        let mut live = live_widgets();
+        widgets(&mut live)
+            .columns
+            .insert("owner".to_string(), column_definition("uuid", true));
+        widgets(&mut live).row_security = row_security_definition(true, false);
+
+        let report = diff_schema(SPEC, &live);
+        assert_eq!(
+            kinds(&report),
+            [
+                (DriftKind::Mismatched, "column widgets.owner"),
+                (DriftKind::Mismatched, "row security widgets"),
+            ]
+        );
+        assert_eq!(report.blocking(SchemaCheckMode::Tolerant).count(), 2);
+        assert_eq!(
+            report.drifts[0].to_string(),
+            "mismatched column widgets.owner: expected uuid not null, got uuid null"
+        );
+    }
+
+    #[test]
+    fn reports_a_check_loosened_under_its_name() {
+        let mut live = live_widgets();
+        widgets(&mut live)
+            .constraints
+            .insert("widgets_size_check".to_string(), check_definition(&["size"], "((size >= 0))"));
+
+        let report = diff_schema(SPEC, &live);
+        assert_eq!(kinds(&report), [(DriftKind::Mismatched, "constraint widgets.widgets_size_check")]);
+    }
+
+    #[test]
+    fn policy_expressions_compare_modulo_whitespace() {
+        let reformatted = OWNER_ONLY.replace(" = ", "\n    =  ");
+        assert_eq!(
+            policy_definition("SELECT", Some(&reformatted), None),
+            policy_definition("select", Some(OWNER_ONLY), None)
+        );
+
+        let mut live = live_widgets();
+        widgets(&mut live).policies.insert(
+            "widgets_select".to_string(),
+            policy_definition("SELECT", Some(&reformatted), None),
+        );
+        assert!(diff_schema(SPEC, &live).drifts.is_empty());
+
+        widgets(&mut live)
+            .policies
+            .insert("widgets_select".to_string(), policy_definition("SELECT", Some("true"), None));
+        let report = diff_schema(SPEC, &live);
+        assert_eq!(kinds(&report), [(DriftKind::Mismatched, "policy widgets.widgets_select")]);
+    }
+}
@@ -0,0 +1,406 @@
+pub mod drift;
+pub mod spec;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use crate::{
+    config::SchemaCheckMode,
+    db::migrations,
+    error::AppError,
+    validation::nickname::{canonical_nickname, nickname_skeleton},
+};
+use drift::{
+    LiveTable, SchemaReport, check_definition, column_definition, constraint_definition,
+    index_definition, policy_definition, row_security_definition,
+};
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use tracing::{info, warn};
+
+#[derive(Debug, sqlx::FromRow)]
+struct ColumnRow {
+    table_name: String,
+    column_name: String,
+    data_type: String,
+    nullable: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ConstraintRow {
+    table_name: String,
+    constraint_name: String,
+    constraint_type: String,
+    columns: Vec<String>,
+    referenced_table: Option<String>,
+    on_delete: String,
+    definition: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TableRow {
+    table_name: String,
+    row_security: bool,
+    force_row_security: bool,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PolicyRow {
+    table_name: String,
+    policy_name: String,
+    command: String,
+    using: Option<String>,
+    check: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct IndexRow {
+    table_name: String,
+    index_name: String,
+    is_unique: bool,
+    keys: String,
+}
+
+/// Applies pending migrations, then validates the result.
+pub async fn ensure_schema(pool: &PgPool, mode: SchemaCheckMode) -> Result<(), AppError> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .map_err(|error| {
+            AppError::ServiceUnavailable(format!(
+                "unable to reach PostgreSQL during startup: {error}"
+            ))
+        })?;
+
+    migrations::run_migrations(pool).await?;
+
+    check_schema(pool, mode).await?;
+
+    warn_if_row_security_bypassed(pool).await
+}
+
+/// Superusers and `BYPASSRLS` roles skip every policy, leaving tenant isolation
+/// to the application's own filters.
+async fn warn_if_row_security_bypassed(pool: &PgPool) -> Result<(), AppError> {
+    let (role, bypasses): (String, bool) = sqlx::query_as(
+        "SELECT rolname::text, rolsuper OR rolbypassrls FROM pg_roles WHERE rolname = current_user",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if bypasses {
+        warn!(
+            role,
+            "database role bypasses row-level security; connect as a role without SUPERUSER or BYPASSRLS to enforce tenant isolation"
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates the schema without creating or altering anything. Every difference
+/// from the spec is reported at once; in tolerant mode extras are only logged.
+pub async fn check_schema(pool: &PgPool, mode: SchemaCheckMode) -> Result<(), AppError> {
+    let report = schema_report(pool).await?;
+
+    for drift in report.tolerated(mode) {
+        warn!("schema drift tolerated: {drift}");
+    }
+
+    let problems: Vec<String> = report.blocking(mode).map(ToString::to_string).collect();
+
+    if !problems.is_empty() {
+        return Err(AppError::SchemaMismatch(format!(
+            "{} difference(s) from the expected schema: {}",
+            problems.len(),
+            problems.join("; ")
+        )));
+    }
+
+    info!("database schema validated successfully");
+
+    Ok(())
+}
+
+/// Diffs the `public` schema against [`spec::SCHEMA`].
+pub async fn schema_report(pool: &PgPool) -> Result<SchemaReport, AppError> {
+    let live = load_live_schema(pool).await?;
+
+    Ok(drift::diff_schema(spec::SCHEMA, &live))
+}
+
+async fn load_live_schema(pool: &PgPool) -> Result<BTreeMap<String, LiveTable>, AppError> {
+    let columns: Vec<ColumnRow> = sqlx::query_as(
+        r#"
+        SELECT c.relname::text AS table_name,
+               a.attname::text AS column_name,
+               format_type(a.atttypid, a.atttypmod) AS data_type,
+               NOT a.attnotnull AS nullable
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = 'public'
+          AND c.relkind IN ('r', 'p')
+          AND a.attnum > 0
+          AND NOT a.attisdropped
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // NOT NULL constraints (contype 'n' since PostgreSQL 18) are covered by the column check.
+    let constraints: Vec<ConstraintRow> = sqlx::query_as(
+        r#"
+        SELECT c.relname::text AS table_name,
+               con.conname::text AS constraint_name,
+               con.contype::text AS constraint_type,
+               ARRAY(
+                   SELECT a.attname::text
+                   FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, position)
+                   JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                   ORDER BY k.position
+               ) AS columns,
+               ref.relname::text AS referenced_table,
+               con.confdeltype::text AS on_delete,
+               pg_get_constraintdef(con.oid) AS definition
+        FROM pg_constraint con
+        JOIN pg_class c ON c.oid = con.conrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        LEFT JOIN pg_class ref ON ref.oid = con.confrelid
+        WHERE n.nspname = 'public'
+          AND con.contype IN ('p', 'u', 'c', 'f')
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let indexes: Vec<IndexRow> = sqlx::query_as(
+        r#"
+        SELECT t.relname::text AS table_name,
+               i.relname::text AS index_name,
+               ix.indisunique AS is_unique,
+               array_to_string(
+                   ARRAY(
+                       SELECT pg_get_indexdef(ix.indexrelid, k, true)
+                       FROM generate_series(1, ix.indnkeyatts) AS k
+                   ),
+                   ', '
+               ) AS keys
+        FROM pg_index ix
+        JOIN pg_class i ON i.oid = ix.indexrelid
+        JOIN pg_class t ON t.oid = ix.indrelid
+        JOIN pg_namespace n ON n.oid = t.relnamespace
+        WHERE n.nspname = 'public'
+          AND NOT EXISTS (SELECT 1 FROM pg_constraint con WHERE con.conindid = ix.indexrelid)
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let tables: Vec<TableRow> = sqlx::query_as(
+        r#"
+        SELECT c.relname::text AS table_name,
+               c.relrowsecurity AS row_security,
+               c.relforcerowsecurity AS force_row_security
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = 'public'
+          AND c.relkind IN ('r', 'p')
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let policies: Vec<PolicyRow> = sqlx::query_as(
+        r#"
+        SELECT tablename::text AS table_name,
+               policyname::text AS policy_name,
+               cmd AS command,
+               qual AS using,
+               with_check AS check
+        FROM pg_policies
+        WHERE schemaname = 'public'
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut live: BTreeMap<String, LiveTable> = BTreeMap::new();
+
+    for table in tables {
+        live.entry(table.table_name).or_default().row_security =
+            row_security_definition(table.row_security, table.force_row_security);
+    }
+
+    for column in columns {
+        live.entry(column.table_name).or_default().columns.insert(
+            column.column_name,
+            column_definition(&column.data_type, column.nullable),
+        );
+    }
+
+    for constraint in constraints {
+        let definition = match constraint.constraint_type.as_str() {
+            "p" => constraint_definition("primary key", &constraint.columns, None),
+            "u" => constraint_definition("unique", &constraint.columns, None),
+            "c" => check_definition(
+                &constraint.columns,
+                constraint.definition.strip_prefix("CHECK ").unwrap_or(&constraint.definition),
+            ),
+            _ => constraint_definition(
+                "foreign key",
+                &constraint.columns,
+                Some((
+                    constraint.referenced_table.as_deref().unwrap_or_default(),
+                    on_delete_action(&constraint.on_delete),
+                )),
+            ),
+        };
+
+        live.entry(constraint.table_name)
+            .or_default()
+            .constraints
+            .insert(constraint.constraint_name, definition);
+    }
+
+    for index in indexes {
+        live.entry(index.table_name)
+            .or_default()
+            .indexes
+            .insert(index.index_name, index_definition(index.is_unique, &index.keys));
+    }
+
+    for policy in policies {
+        live.entry(policy.table_name)
+            .or_default()
+            .policies
+            .insert(
+                policy.policy_name,
+                policy_definition(&policy.command, policy.using.as_deref(), policy.check.as_deref()),
+            );
+    }
+
+    Ok(live)
+}
+
+fn on_delete_action(code: &str) -> &'static str {
+    match code {
+        "r" => "restrict",
+        "c" => "cascade",
+        "n" => "set null",
+        "d" => "set default",
+        _ => "no action",
+    }
+}
+
+pub async fn table_exists(conn: &mut PgConnection, table_name: &str) -> Result<bool, AppError> {
+    let exists: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+          SELECT 1
+          FROM information_schema.tables
+          WHERE table_schema = 'public' AND table_name = $1
+        )
+        "#,
+    )
+    .bind(table_name)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Brings a `users` table created by a release that predates migrations up to the
+/// shape of the baseline migration. Only used when baselining such a deployment.
+pub async fn upgrade_legacy_users_table(conn: &mut PgConnection) -> Result<(), AppError> {
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0")
+        .execute(&mut *conn)
+        .await?;
+
+    if !column_exists(&mut *conn, "users", "nickname_canonical").await? {
+        warn!("column 'users.nickname_canonical' is missing; backfilling nickname canonical forms");
+        backfill_nickname_canonical_forms(conn).await?;
+        info!("columns 'users.nickname_canonical' and 'users.nickname_skeleton' created");
+    }
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_at TIMESTAMPTZ NULL")
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+async fn column_exists(
+    conn: &mut PgConnection,
+    table_name: &str,
+    column_name: &str,
+) -> Result<bool, AppError> {
+    let exists: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+          SELECT 1
+          FROM information_schema.columns
+          WHERE table_schema = 'public' AND table_name = $1 AND column_name = $2
+        )
+        "#,
+    )
+    .bind(table_name)
+    .bind(column_name)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(exists)
+}
+
+/// Canonical forms are computed in Rust (NFKC + confusable skeleton), so existing
+/// rows are filled in one transaction before the columns become NOT NULL/UNIQUE.
+async fn backfill_nickname_canonical_forms(conn: &mut PgConnection) -> Result<(), AppError> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+            ADD COLUMN nickname_canonical TEXT NULL,
+            ADD COLUMN nickname_skeleton TEXT NULL
+        "#,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let rows: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, nickname FROM users")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for (user_id, nickname) in rows {
+        let canonical = canonical_nickname(&nickname);
+        let skeleton = nickname_skeleton(&canonical);
+
+        sqlx::query("UPDATE users SET nickname_canonical = $2, nickname_skeleton = $3 WHERE id = $1")
+            .bind(user_id)
+            .bind(canonical)
+            .bind(skeleton)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    sqlx::query(
+        r#"
+        ALTER TABLE users
+            ALTER COLUMN nickname_canonical SET NOT NULL,
+            ALTER COLUMN nickname_skeleton SET NOT NULL,
+            ADD CONSTRAINT users_nickname_canonical_key UNIQUE (nickname_canonical),
+            ADD CONSTRAINT users_nickname_skeleton_key UNIQUE (nickname_skeleton)
+        "#,
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|error| {
+        AppError::SchemaMismatch(format!(
+            "existing nicknames collide after case folding or confusable mapping; rename the duplicates and restart: {error}"
+        ))
+    })?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,560 @@
+//! Declarative description of the schema the migrations produce. Update it in the
+//! same change as every new migration; `check_schema` diffs the live database against it.
+
+pub struct TableSpec {
+    pub name: &'static str,
+    pub columns: &'static [ColumnSpec],
+    pub constraints: &'static [ConstraintSpec],
+    /// Indexes that don't back a primary key or unique constraint.
+    pub indexes: &'static [IndexSpec],
+    /// Row-level security enabled and forced on the owner; see `db::tenant`.
+    pub row_security: bool,
+    pub policies: &'static [PolicySpec],
+}
+
+pub struct ColumnSpec {
+    pub name: &'static str,
+    /// Type as rendered by PostgreSQL's `format_type`.
+    pub data_type: &'static str,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    PrimaryKey,
+    Unique,
+    /// Expression as rendered by `pg_get_constraintdef` after `CHECK `; runs of
+    /// whitespace are compared as one space.
+    Check {
+        expression: &'static str,
+    },
+    ForeignKey {
+        table: &'static str,
+        on_delete: &'static str,
+    },
+}
+
+pub struct ConstraintSpec {
+    pub name: &'static str,
+    pub kind: ConstraintKind,
+    pub columns: &'static [&'static str],
+}
+
+pub struct IndexSpec {
+    pub name: &'static str,
+    pub unique: bool,
+    /// Key list as rendered by `pg_get_indexdef`, without the surrounding parentheses.
+    pub keys: &'static str,
+}
+
+pub struct PolicySpec {
+    pub name: &'static str,
+    /// Command as listed in `pg_policies.cmd`, lowercased: `select`, `insert`, `all`, ...
+    pub command: &'static str,
+    /// `USING` and `WITH CHECK` expressions as deparsed in `pg_policies.qual` and
+    /// `with_check`; runs of whitespace are compared as one space.
+    pub using: Option<&'static str>,
+    pub check: Option<&'static str>,
+}
+
+const UUID: &str = "uuid";
+const TEXT: &str = "text";
+const BOOLEAN: &str = "boolean";
+const SMALLINT: &str = "smallint";
+const INTEGER: &str = "integer";
+const BIGINT: &str = "bigint";
+const JSONB: &str = "jsonb";
+const TIMESTAMPTZ: &str = "timestamp with time zone";
+
+const fn not_null(name: &'static str, data_type: &'static str) -> ColumnSpec {
+    ColumnSpec {
+        name,
+        data_type,
+        nullable: false,
+    }
+}
+
+const fn null(name: &'static str, data_type: &'static str) -> ColumnSpec {
+    ColumnSpec {
+        name,
+        data_type,
+        nullable: true,
+    }
+}
+
+const fn primary_key(name: &'static str, columns: &'static [&'static str]) -> ConstraintSpec {
+    ConstraintSpec {
+        name,
+        kind: ConstraintKind::PrimaryKey,
+        columns,
+    }
+}
+
+const fn unique(name: &'static str, columns: &'static [&'static str]) -> ConstraintSpec {
+    ConstraintSpec {
+        name,
+        kind: ConstraintKind::Unique,
+        columns,
+    }
+}
+
+const fn check(
+    name: &'static str,
+    columns: &'static [&'static str],
+    expression: &'static str,
+) -> ConstraintSpec {
+    ConstraintSpec {
+        name,
+        kind: ConstraintKind::Check { expression },
+        columns,
+    }
+}
+
+const fn foreign_key(
+    name: &'static str,
+    columns: &'static [&'static str],
+    table: &'static str,
+    on_delete: &'static str,
+) -> ConstraintSpec {
+    ConstraintSpec {
+        name,
+        kind: ConstraintKind::ForeignKey { table, on_delete },
+        columns,
+    }
+}
+
+const fn index(name: &'static str, keys: &'static str) -> IndexSpec {
+    IndexSpec {
+        name,
+        unique: false,
+        keys,
+    }
+}
+
+const fn unique_index(name: &'static str, keys: &'static str) -> IndexSpec {
+    IndexSpec {
+        name,
+        unique: true,
+        keys,
+    }
+}
+
+const fn policy(
+    name: &'static str,
+    command: &'static str,
+    using: Option<&'static str>,
+    check: Option<&'static str>,
+) -> PolicySpec {
+    PolicySpec {
+        name,
+        command,
+        using,
+        check,
+    }
+}
+
+/// Tables holding application data, in the order the migrations create them, so
+/// parents come before the rows that reference them. `schema_migrations`
+/// describes the schema itself.
+pub fn data_tables() -> impl Iterator<Item = &'static TableSpec> {
+    SCHEMA.iter().filter(|table| table.name != "schema_migrations")
+}
+
+pub static SCHEMA: &[TableSpec] = &[
+    TableSpec {
+        name: "schema_migrations",
+        columns: &[
+            not_null("version", BIGINT),
+            not_null("name", TEXT),
+            not_null("checksum", TEXT),
+            not_null("applied_at", TIMESTAMPTZ),
+            not_null("execution_ms", BIGINT),
+            not_null("baselined", BOOLEAN),
+        ],
+        constraints: &[primary_key("schema_migrations_pkey", &["version"])],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "users",
+        columns: &[
+            not_null("id", UUID),
+            not_null("nickname", TEXT),
+            not_null("email", TEXT),
+            not_null("password_hash", TEXT),
+            not_null("is_admin", BOOLEAN),
+            not_null("created_at", TIMESTAMPTZ),
+            not_null("token_version", INTEGER),
+            not_null("nickname_canonical", TEXT),
+            not_null("nickname_skeleton", TEXT),
+            null("suspended_at", TIMESTAMPTZ),
+            not_null("version", BIGINT),
+        ],
+        constraints: &[
+            primary_key("users_pkey", &["id"]),
+            unique("users_nickname_key", &["nickname"]),
+            unique("users_email_key", &["email"]),
+            unique("users_nickname_canonical_key", &["nickname_canonical"]),
+            unique("users_nickname_skeleton_key", &["nickname_skeleton"]),
+        ],
+        indexes: &[unique_index("users_email_lower_key", "lower(email)")],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "invites",
+        columns: &[
+            not_null("id", UUID),
+            not_null("code", TEXT),
+            null("email", TEXT),
+            not_null("is_admin", BOOLEAN),
+            not_null("max_uses", INTEGER),
+            not_null("used_count", INTEGER),
+            null("expires_at", TIMESTAMPTZ),
+            null("created_by", UUID),
+            not_null("created_at", TIMESTAMPTZ),
+            null("revoked_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("invites_pkey", &["id"]),
+            unique("invites_code_key", &["code"]),
+            check("invites_max_uses_check", &["max_uses"], "((max_uses > 0))"),
+            check("invites_used_count_check", &["used_count"], "((used_count >= 0))"),
+            foreign_key("invites_created_by_fkey", &["created_by"], "users", "set null"),
+        ],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "password_tokens",
+        columns: &[
+            not_null("id", UUID),
+            not_null("user_id", UUID),
+            not_null("token_hash", TEXT),
+            not_null("expires_at", TIMESTAMPTZ),
+            null("used_at", TIMESTAMPTZ),
+            not_null("created_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("password_tokens_pkey", &["id"]),
+            unique("password_tokens_token_hash_key", &["token_hash"]),
+            foreign_key("password_tokens_user_id_fkey", &["user_id"], "users", "cascade"),
+        ],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "feature_flags",
+        columns: &[
+            not_null("key", TEXT),
+            not_null("description", TEXT),
+            not_null("enabled", BOOLEAN),
+            not_null("rollout_percentage", SMALLINT),
+            not_null("created_at", TIMESTAMPTZ),
+            not_null("updated_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("feature_flags_pkey", &["key"]),
+            check(
+                "feature_flags_rollout_percentage_check",
+                &["rollout_percentage"],
+                "(((rollout_percentage >= 0) AND (rollout_percentage <= 100)))",
+            ),
+        ],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "feature_flag_users",
+        columns: &[not_null("flag_key", TEXT), not_null("user_id", UUID)],
+        constraints: &[
+            primary_key("feature_flag_users_pkey", &["flag_key", "user_id"]),
+            foreign_key(
+                "feature_flag_users_flag_key_fkey",
+                &["flag_key"],
+                "feature_flags",
+                "cascade",
+            ),
+            foreign_key("feature_flag_users_user_id_fkey", &["user_id"], "users", "cascade"),
+        ],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "settings",
+        columns: &[
+            not_null("key", TEXT),
+            not_null("value", JSONB),
+            null("updated_by", UUID),
+            not_null("updated_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("settings_pkey", &["key"]),
+            foreign_key("settings_updated_by_fkey", &["updated_by"], "users", "set null"),
+        ],
+        indexes: &[],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "settings_history",
+        columns: &[
+            not_null("id", BIGINT),
+            not_null("key", TEXT),
+            null("old_value", JSONB),
+            null("new_value", JSONB),
+            null("changed_by", UUID),
+            not_null("changed_at", TIMESTAMPTZ),
+        ],
+        constraints: &[primary_key("settings_history_pkey", &["id"])],
+        indexes: &[index("settings_history_key_idx", "key, id")],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "audit_events",
+        columns: &[
+            not_null("id", BIGINT),
+            not_null("occurred_at", TIMESTAMPTZ),
+            not_null("event_type", TEXT),
+            null("actor_id", UUID),
+            null("target_id", UUID),
+            null("ip", TEXT),
+            null("request_id", TEXT),
+            not_null("payload", JSONB),
+            not_null("prev_hash", TEXT),
+            not_null("hash", TEXT),
+        ],
+        constraints: &[
+            primary_key("audit_events_pkey", &["id"]),
+            unique("audit_events_hash_key", &["hash"]),
+        ],
+        indexes: &[
+            index("audit_events_occurred_at_idx", "occurred_at"),
+            index("audit_events_event_type_idx", "event_type, id"),
+            index("audit_events_actor_id_idx", "actor_id, id"),
+            index("audit_events_target_id_idx", "target_id, id"),
+        ],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "organizations",
+        columns: &[
+            not_null("id", UUID),
+            not_null("slug", TEXT),
+            not_null("name", TEXT),
+            null("created_by", UUID),
+            not_null("created_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("organizations_pkey", &["id"]),
+            unique("organizations_slug_key", &["slug"]),
+            foreign_key("organizations_created_by_fkey", &["created_by"], "users", "set null"),
+        ],
+        indexes: &[],
+        row_security: true,
+        policies: &[
+            policy(
+                "organizations_select",
+                "select",
+                Some(
+                    "((id = swarm_current_org()) OR (EXISTS ( SELECT 1 FROM organization_members m \
+                     WHERE ((m.organization_id = organizations.id) \
+                     AND (m.user_id = swarm_current_user())))))",
+                ),
+                None,
+            ),
+            policy(
+                "organizations_insert",
+                "insert",
+                None,
+                Some("(created_by = swarm_current_user())"),
+            ),
+        ],
+    },
+    TableSpec {
+        name: "organization_members",
+        columns: &[
+            not_null("organization_id", UUID),
+            not_null("user_id", UUID),
+            not_null("role", TEXT),
+            not_null("created_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("organization_members_pkey", &["organization_id", "user_id"]),
+            check(
+                "organization_members_role_check",
+                &["role"],
+                "((role = ANY (ARRAY['owner'::text, 'admin'::text, 'member'::text])))",
+            ),
+            foreign_key(
+                "organization_members_organization_id_fkey",
+                &["organization_id"],
+                "organizations",
+                "cascade",
+            ),
+            foreign_key("organization_members_user_id_fkey", &["user_id"], "users", "cascade"),
+        ],
+        indexes: &[index("organization_members_user_id_idx", "user_id")],
+        row_security: true,
+        policies: &[
+            policy(
+                "organization_members_select",
+                "select",
+                Some("((user_id = swarm_current_user()) OR (organization_id = swarm_current_org()))"),
+                None,
+            ),
+            policy(
+                "organization_members_insert",
+                "insert",
+                None,
+                Some(
+                    "(((organization_id = swarm_current_org()) AND (role = 'owner'::text) \
+                     AND (user_id = swarm_current_user()) AND (EXISTS ( SELECT 1 FROM organizations o \
+                     WHERE ((o.id = organization_members.organization_id) \
+                     AND (o.created_by = swarm_current_user())))) AND (NOT (EXISTS ( SELECT 1 \
+                     FROM organization_members m \
+                     WHERE (m.organization_id = organization_members.organization_id))))) \
+                     OR ((organization_id = swarm_current_org()) AND (((role = 'member'::text) \
+                     AND (swarm_current_org_role() = ANY (ARRAY['owner'::text, 'admin'::text]))) \
+                     OR ((role = 'admin'::text) AND (swarm_current_org_role() = 'owner'::text)))) \
+                     OR ((user_id = swarm_current_user()) AND (role <> 'owner'::text) \
+                     AND (EXISTS ( SELECT 1 FROM organization_invites i \
+                     WHERE ((i.organization_id = organization_members.organization_id) \
+                     AND (i.accepted_by = swarm_current_user()) \
+                     AND (i.role = organization_members.role))))))",
+                ),
+            ),
+        ],
+    },
+    TableSpec {
+        name: "organization_invites",
+        columns: &[
+            not_null("id", UUID),
+            not_null("organization_id", UUID),
+            not_null("code", TEXT),
+            null("email", TEXT),
+            not_null("role", TEXT),
+            not_null("expires_at", TIMESTAMPTZ),
+            null("created_by", UUID),
+            not_null("created_at", TIMESTAMPTZ),
+            null("accepted_by", UUID),
+            null("accepted_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("organization_invites_pkey", &["id"]),
+            unique("organization_invites_code_key", &["code"]),
+            check(
+                "organization_invites_role_check",
+                &["role"],
+                "((role = ANY (ARRAY['admin'::text, 'member'::text])))",
+            ),
+            foreign_key(
+                "organization_invites_organization_id_fkey",
+                &["organization_id"],
+                "organizations",
+                "cascade",
+            ),
+            foreign_key(
+                "organization_invites_created_by_fkey",
+                &["created_by"],
+                "users",
+                "set null",
+            ),
+            foreign_key(
+                "organization_invites_accepted_by_fkey",
+                &["accepted_by"],
+                "users",
+                "set null",
+            ),
+        ],
+        indexes: &[index("organization_invites_organization_id_idx", "organization_id")],
+        row_security: true,
+        policies: &[
+            policy(
+                "organization_invites_select",
+                "select",
+                Some(
+                    "((organization_id = swarm_current_org()) OR (accepted_by = swarm_current_user()) \
+                     OR (code = current_setting('swarm.invite_code'::text, true)))",
+                ),
+                None,
+            ),
+            policy(
+                "organization_invites_insert",
+                "insert",
+                None,
+                Some(
+                    "((organization_id = swarm_current_org()) AND (((role = 'member'::text) \
+                     AND (swarm_current_org_role() = ANY (ARRAY['owner'::text, 'admin'::text]))) \
+                     OR ((role = 'admin'::text) AND (swarm_current_org_role() = 'owner'::text))))",
+                ),
+            ),
+            policy(
+                "organization_invites_redeem",
+                "update",
+                Some("(code = current_setting('swarm.invite_code'::text, true))"),
+                Some("(accepted_by = swarm_current_user())"),
+            ),
+        ],
+    },
+    TableSpec {
+        name: "jobs",
+        columns: &[
+            not_null("id", BIGINT),
+            not_null("kind", TEXT),
+            not_null("payload", JSONB),
+            not_null("status", TEXT),
+            not_null("attempts", INTEGER),
+            not_null("max_attempts", INTEGER),
+            not_null("run_at", TIMESTAMPTZ),
+            null("unique_key", TEXT),
+            null("locked_by", TEXT),
+            null("locked_until", TIMESTAMPTZ),
+            null("last_error", TEXT),
+            not_null("created_at", TIMESTAMPTZ),
+            not_null("updated_at", TIMESTAMPTZ),
+        ],
+        constraints: &[
+            primary_key("jobs_pkey", &["id"]),
+            check(
+                "jobs_status_check",
+                &["status"],
+                "((status = ANY (ARRAY['pending'::text, 'running'::text, 'dead'::text])))",
+            ),
+            check("jobs_max_attempts_check", &["max_attempts"], "((max_attempts > 0))"),
+        ],
+        indexes: &[
+            index("jobs_due_idx", "run_at, id"),
+            index("jobs_lease_idx", "locked_until"),
+            index("jobs_status_idx", "status, id"),
+            unique_index("jobs_unique_key_idx", "unique_key"),
+        ],
+        row_security: false,
+        policies: &[],
+    },
+    TableSpec {
+        name: "outbox",
+        columns: &[
+            not_null("id", BIGINT),
+            not_null("aggregate_type", TEXT),
+            not_null("aggregate_id", UUID),
+            not_null("event_type", TEXT),
+            not_null("payload", JSONB),
+            not_null("occurred_at", TIMESTAMPTZ),
+            not_null("attempts", INTEGER),
+            not_null("next_attempt_at", TIMESTAMPTZ),
+            null("last_error", TEXT),
+        ],
+        constraints: &[primary_key("outbox_pkey", &["id"])],
+        indexes: &[index("outbox_aggregate_idx", "aggregate_type, aggregate_id, id")],
+        row_security: false,
+        policies: &[],
+    },
+];
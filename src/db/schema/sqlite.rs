@@ -0,0 +1,90 @@
+//! Schema of the SQLite backend, which only holds the tables behind `db::store`.
+//! There is no migration history: the file is idempotent and re-applied on every start.
+
+use sqlx::SqlitePool;
+use tracing::info;
+
+use super::spec;
+use crate::error::AppError;
+
+const SCHEMA_SQL: &str = include_str!("../../../migrations/sqlite/0001_initial_schema.sql");
+
+const TABLES: &[&str] = &["users", "invites", "password_tokens", "audit_events"];
+
+/// Columns added after the file first shipped, as (table, column, definition).
+/// SQLite has no `ADD COLUMN IF NOT EXISTS`, so older database files get them here.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[("users", "version", "INTEGER NOT NULL DEFAULT 1")];
+
+#[derive(sqlx::FromRow)]
+struct ColumnInfo {
+    name: String,
+    notnull: bool,
+    pk: i64,
+}
+
+pub async fn ensure_schema(pool: &SqlitePool) -> Result<(), AppError> {
+    sqlx::raw_sql(SCHEMA_SQL).execute(pool).await?;
+
+    for (table, column, definition) in ADDED_COLUMNS {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)")
+                .bind(table)
+                .bind(column)
+                .fetch_one(pool)
+                .await?;
+
+        if !exists {
+            sqlx::query(&format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"))
+                .execute(pool)
+                .await?;
+            info!("column '{table}.{column}' added");
+        }
+    }
+
+    check_schema(pool).await
+}
+
+/// Compares column names and nullability with the PostgreSQL spec; column types
+/// differ by design. Every problem is reported at once.
+pub async fn check_schema(pool: &SqlitePool) -> Result<(), AppError> {
+    let mut problems = Vec::new();
+
+    for table in spec::SCHEMA.iter().filter(|table| TABLES.contains(&table.name)) {
+        let columns: Vec<ColumnInfo> =
+            sqlx::query_as("SELECT name, \"notnull\", pk FROM pragma_table_info(?1)")
+                .bind(table.name)
+                .fetch_all(pool)
+                .await?;
+
+        if columns.is_empty() {
+            problems.push(format!("missing table {}", table.name));
+            continue;
+        }
+
+        for expected in table.columns {
+            match columns.iter().find(|column| column.name == expected.name) {
+                None => problems.push(format!("missing column {}.{}", table.name, expected.name)),
+                // SQLite leaves `INTEGER PRIMARY KEY` columns nullable in the catalog.
+                Some(column) if column.pk == 0 && column.notnull == expected.nullable => {
+                    problems.push(format!(
+                        "mismatched column {}.{}: expected nullable={}",
+                        table.name, expected.name, expected.nullable
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(AppError::SchemaMismatch(format!(
+            "{} difference(s) from the expected schema: {}",
+            problems.len(),
+            problems.join("; ")
+        )));
+    }
+
+    info!("sqlite schema validated successfully");
+
+    Ok(())
+}
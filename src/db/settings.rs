@@ -0,0 +1,124 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Channel the trigger from `migrations/0001_initial_schema.sql` notifies whenever a setting changes.
+pub const SETTINGS_CHANNEL: &str = "settings_changed";
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SettingRecord {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SettingChangeRecord {
+    pub id: i64,
+    pub key: String,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+pub async fn list_settings(pool: &PgPool) -> Result<Vec<SettingRecord>, AppError> {
+    let records = sqlx::query_as::<_, SettingRecord>(
+        r#"
+        SELECT key, value, updated_by, updated_at
+        FROM settings
+        ORDER BY key
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
+
+/// Stores `value` for `key`, or removes the override when `value` is `None`,
+/// and appends the change to `settings_history`. Returns `false` when nothing changed.
+pub async fn set_setting(
+    conn: &mut PgConnection,
+    key: &str,
+    value: Option<&serde_json::Value>,
+    changed_by: Option<Uuid>,
+) -> Result<bool, AppError> {
+    let old_value = sqlx::query_scalar::<_, serde_json::Value>(
+        "SELECT value FROM settings WHERE key = $1 FOR UPDATE",
+    )
+    .bind(key)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if old_value.as_ref() == value {
+        return Ok(false);
+    }
+
+    match value {
+        Some(value) => {
+            sqlx::query(
+                r#"
+                INSERT INTO settings (key, value, updated_by, updated_at)
+                VALUES ($1, $2, $3, NOW())
+                ON CONFLICT (key) DO UPDATE
+                SET value = EXCLUDED.value, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+                "#,
+            )
+            .bind(key)
+            .bind(value)
+            .bind(changed_by)
+            .execute(&mut *conn)
+            .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM settings WHERE key = $1")
+                .bind(key)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO settings_history (key, old_value, new_value, changed_by)
+        VALUES ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(key)
+    .bind(old_value)
+    .bind(value)
+    .bind(changed_by)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(true)
+}
+
+/// Newest first; `before_id` is the keyset cursor from the previous page.
+pub async fn list_history(
+    pool: &PgPool,
+    key: Option<&str>,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<SettingChangeRecord>, AppError> {
+    let records = sqlx::query_as::<_, SettingChangeRecord>(
+        r#"
+        SELECT id, key, old_value, new_value, changed_by, changed_at
+        FROM settings_history
+        WHERE ($1::text IS NULL OR key = $1)
+          AND ($2::bigint IS NULL OR id < $2)
+        ORDER BY id DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(key)
+    .bind(before_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
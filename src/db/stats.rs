@@ -0,0 +1,160 @@
+use crate::{db::audit::AuditEventType, error::AppError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsBucket {
+    Day,
+    Week,
+}
+
+impl StatsBucket {
+    /// `date_trunc` field name; also the interval unit.
+    fn unit(self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UserTotals {
+    pub total: i64,
+    pub admins: i64,
+    pub suspended: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RegistrationBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub registrations: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LoginBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub succeeded: i64,
+    pub failed: i64,
+}
+
+pub async fn user_totals(pool: &PgPool) -> Result<UserTotals, AppError> {
+    let totals = sqlx::query_as::<_, UserTotals>(
+        r#"
+        SELECT COUNT(*) AS total,
+               COUNT(*) FILTER (WHERE is_admin AND suspended_at IS NULL) AS admins,
+               COUNT(*) FILTER (WHERE suspended_at IS NOT NULL) AS suspended
+        FROM users
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(totals)
+}
+
+/// Registrations in `[from, to)` per UTC day or ISO week; empty buckets are included.
+pub async fn registrations_per_bucket(
+    pool: &PgPool,
+    bucket: StatsBucket,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<RegistrationBucket>, AppError> {
+    let records = sqlx::query_as::<_, RegistrationBucket>(
+        r#"
+        WITH buckets AS (
+            SELECT generate_series(
+                date_trunc($1, $2::timestamptz, 'UTC'),
+                $3::timestamptz - interval '1 microsecond',
+                ('1 ' || $1)::interval
+            ) AS bucket_start
+        ),
+        counts AS (
+            SELECT date_trunc($1, created_at, 'UTC') AS bucket_start, COUNT(*) AS registrations
+            FROM users
+            WHERE created_at >= $2 AND created_at < $3
+            GROUP BY 1
+        )
+        SELECT buckets.bucket_start, COALESCE(counts.registrations, 0) AS registrations
+        FROM buckets
+        LEFT JOIN counts USING (bucket_start)
+        ORDER BY buckets.bucket_start
+        "#,
+    )
+    .bind(bucket.unit())
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
+
+/// Successful and failed logins in `[from, to)` per bucket, from the audit log.
+pub async fn logins_per_bucket(
+    pool: &PgPool,
+    bucket: StatsBucket,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<LoginBucket>, AppError> {
+    let records = sqlx::query_as::<_, LoginBucket>(
+        r#"
+        WITH buckets AS (
+            SELECT generate_series(
+                date_trunc($1, $2::timestamptz, 'UTC'),
+                $3::timestamptz - interval '1 microsecond',
+                ('1 ' || $1)::interval
+            ) AS bucket_start
+        ),
+        counts AS (
+            SELECT date_trunc($1, occurred_at, 'UTC') AS bucket_start,
+                   COUNT(*) FILTER (WHERE event_type = $4) AS succeeded,
+                   COUNT(*) FILTER (WHERE event_type = $5) AS failed
+            FROM audit_events
+            WHERE event_type IN ($4, $5)
+              AND occurred_at >= $2 AND occurred_at < $3
+            GROUP BY 1
+        )
+        SELECT buckets.bucket_start,
+               COALESCE(counts.succeeded, 0) AS succeeded,
+               COALESCE(counts.failed, 0) AS failed
+        FROM buckets
+        LEFT JOIN counts USING (bucket_start)
+        ORDER BY buckets.bucket_start
+        "#,
+    )
+    .bind(bucket.unit())
+    .bind(from)
+    .bind(to)
+    .bind(AuditEventType::LoginSucceeded.as_str())
+    .bind(AuditEventType::LoginFailed.as_str())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records)
+}
+
+/// Users holding at least one unexpired token that has not been revoked since.
+/// Tokens are stateless, so this is derived from `auth.token_issued` audit events
+/// whose recorded token version still matches the user's.
+pub async fn active_sessions(pool: &PgPool, token_ttl_seconds: i64) -> Result<i64, AppError> {
+    let count = sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT COUNT(DISTINCT users.id)
+        FROM audit_events
+        JOIN users ON users.id = audit_events.target_id
+        WHERE audit_events.event_type = $1
+          AND audit_events.occurred_at > NOW() - make_interval(secs => $2)
+          AND (audit_events.payload->>'token_version')::int = users.token_version
+          AND users.suspended_at IS NULL
+        "#,
+    )
+    .bind(AuditEventType::TokenIssued.as_str())
+    .bind(token_ttl_seconds as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
@@ -0,0 +1,652 @@
+//! In-process store for tests that drive the router without PostgreSQL. It
+//! enforces the same uniqueness rules and conflict messages as the schema.
+
+use std::{
+    cmp::{Ordering, Reverse},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+use super::{
+    AuditRepository, InviteRepository, PasswordTokenRepository, Repositories, Store, UnitOfWork,
+    UserRepository,
+};
+use crate::{
+    db::{
+        audit::{
+            self, AuditEventFilter, AuditEventRecord, ChainVerification, ChainVerifier, NewAuditEvent,
+            SealedAuditEvent,
+        },
+        invites::{InviteRecord, NewInvite},
+        password_tokens,
+        users::{
+            self, NewUser, SortDirection, UserCursor, UserListQuery, UserPage, UserRecord, UserSortField,
+            UserUpdate,
+        },
+    },
+    error::AppError,
+    validation::nickname::{canonical_nickname, nickname_skeleton},
+};
+
+#[derive(Debug, Clone)]
+struct StoredUser {
+    record: UserRecord,
+    nickname_canonical: String,
+    nickname_skeleton: String,
+}
+
+#[derive(Debug, Clone)]
+struct StoredPasswordToken {
+    user_id: Uuid,
+    token_hash: String,
+    expires_at: DateTime<Utc>,
+    used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryData {
+    users: Vec<StoredUser>,
+    invites: Vec<InviteRecord>,
+    password_tokens: Vec<StoredPasswordToken>,
+    audit_events: Vec<AuditEventRecord>,
+}
+
+#[derive(Default)]
+struct Shared {
+    data: MemoryData,
+    /// Bumped by every committed write; a unit of work whose snapshot is older
+    /// than the latest write fails to commit instead of overwriting it.
+    generation: u64,
+}
+
+/// The lock is only held for the duration of a single repository call, so any
+/// number of sessions and units of work can be open at once on the same task.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a set-password token for the user and returns the raw token, as the
+    /// import would have emailed it.
+    pub fn issue_password_token(&self, user_id: Uuid) -> String {
+        let bytes: [u8; 32] = rand::random();
+        let token: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+
+        let mut shared = lock(&self.shared);
+        shared.data.password_tokens.push(StoredPasswordToken {
+            user_id,
+            token_hash: password_tokens::hash_token(&token),
+            expires_at: Utc::now() + password_tokens::PASSWORD_TOKEN_TTL,
+            used_at: None,
+        });
+        shared.generation += 1;
+
+        token
+    }
+
+    /// Every event recorded so far, oldest first.
+    pub fn audit_events(&self) -> Vec<AuditEventRecord> {
+        lock(&self.shared).data.audit_events.clone()
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    shared.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn session(&self) -> Result<Box<dyn Repositories>, AppError> {
+        Ok(Box::new(MemoryRepositories {
+            shared: self.shared.clone(),
+            work: None,
+        }))
+    }
+
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork>, AppError> {
+        let (data, base_generation) = {
+            let shared = lock(&self.shared);
+            (shared.data.clone(), shared.generation)
+        };
+
+        Ok(Box::new(MemoryRepositories {
+            shared: self.shared.clone(),
+            work: Some(Work {
+                data,
+                base_generation,
+                dirty: false,
+            }),
+        }))
+    }
+}
+
+/// A unit of work's private copy of the data, swapped in on commit.
+struct Work {
+    data: MemoryData,
+    base_generation: u64,
+    dirty: bool,
+}
+
+pub struct MemoryRepositories {
+    shared: Arc<Mutex<Shared>>,
+    /// `None` for sessions, whose writes go straight to the shared data.
+    work: Option<Work>,
+}
+
+impl MemoryRepositories {
+    fn read<T>(&self, f: impl FnOnce(&MemoryData) -> T) -> T {
+        match &self.work {
+            Some(work) => f(&work.data),
+            None => f(&lock(&self.shared).data),
+        }
+    }
+
+    fn write<T>(&mut self, f: impl FnOnce(&mut MemoryData) -> Result<T, AppError>) -> Result<T, AppError> {
+        match &mut self.work {
+            Some(work) => {
+                let result = f(&mut work.data);
+                work.dirty |= result.is_ok();
+                result
+            }
+            None => {
+                let mut shared = lock(&self.shared);
+                let result = f(&mut shared.data);
+                if result.is_ok() {
+                    shared.generation += 1;
+                }
+                result
+            }
+        }
+    }
+}
+
+impl MemoryData {
+    fn user_mut(&mut self, user_id: Uuid) -> Option<&mut StoredUser> {
+        self.users.iter_mut().find(|user| user.record.id == user_id)
+    }
+
+    /// The constraint PostgreSQL would report, checking its unique indexes in
+    /// creation order; `exclude` is the row being updated.
+    fn violated_constraint(
+        &self,
+        exclude: Option<Uuid>,
+        nickname: &str,
+        email: &str,
+    ) -> Option<&'static str> {
+        let canonical = canonical_nickname(nickname);
+        let skeleton = nickname_skeleton(&canonical);
+        let email_lower = email.to_lowercase();
+        let others = || self.users.iter().filter(|user| Some(user.record.id) != exclude);
+
+        let checks: [(&'static str, Violates); 5] = [
+            ("users_nickname_key", &|user| user.record.nickname == nickname),
+            ("users_email_key", &|user| user.record.email == email),
+            ("users_nickname_canonical_key", &|user| user.nickname_canonical == canonical),
+            ("users_nickname_skeleton_key", &|user| user.nickname_skeleton == skeleton),
+            ("users_email_lower_key", &|user| user.record.email.to_lowercase() == email_lower),
+        ];
+
+        checks
+            .into_iter()
+            .find(|(_, violates)| others().any(violates))
+            .map(|(constraint, _)| constraint)
+    }
+
+    /// Same rule as `users::ensure_not_last_admin`.
+    fn ensure_not_last_admin(&self, user_id: Uuid) -> Result<(), AppError> {
+        let mut active_admins = self
+            .users
+            .iter()
+            .filter(|user| user.record.is_admin && user.record.suspended_at.is_none());
+
+        if let (Some(only), None) = (active_admins.next(), active_admins.next())
+            && only.record.id == user_id
+        {
+            return Err(AppError::Conflict(
+                "cannot remove or suspend the last remaining admin".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// `Ok(None)` for a missing user; a failed precondition for a stale version.
+    fn current_user(
+        &mut self,
+        user_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<Option<&mut StoredUser>, AppError> {
+        let Some(user) = self.user_mut(user_id) else {
+            return Ok(None);
+        };
+
+        match expected_version {
+            Some(expected_version) if expected_version != user.record.version => {
+                Err(AppError::PreconditionFailed(format!(
+                    "user was modified: version is {}, not {expected_version}",
+                    user.record.version
+                )))
+            }
+            _ => Ok(Some(user)),
+        }
+    }
+}
+
+type Violates<'a> = &'a dyn Fn(&StoredUser) -> bool;
+
+/// The key `list_users` orders by. Timestamps use a fixed-width form so that
+/// comparing the text compares the instants.
+fn sort_key(user: &StoredUser, sort: UserSortField) -> String {
+    match sort {
+        UserSortField::CreatedAt => user.record.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+        UserSortField::Nickname => user.nickname_canonical.clone(),
+        UserSortField::Email => user.record.email.to_lowercase(),
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepositories {
+    async fn find_user_by_id(&mut self, user_id: Uuid) -> Result<Option<UserRecord>, AppError> {
+        Ok(self.read(|data| {
+            data.users
+                .iter()
+                .find(|user| user.record.id == user_id)
+                .map(|user| user.record.clone())
+        }))
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<UserRecord>, AppError> {
+        let email = email.to_lowercase();
+
+        Ok(self.read(|data| {
+            data.users
+                .iter()
+                .find(|user| user.record.email.to_lowercase() == email)
+                .map(|user| user.record.clone())
+        }))
+    }
+
+    async fn find_user_by_nickname(&mut self, nickname: &str) -> Result<Option<UserRecord>, AppError> {
+        let canonical = canonical_nickname(nickname);
+
+        Ok(self.read(|data| {
+            data.users
+                .iter()
+                .find(|user| user.nickname_canonical == canonical)
+                .map(|user| user.record.clone())
+        }))
+    }
+
+    async fn create_user(&mut self, new_user: NewUser) -> Result<UserRecord, AppError> {
+        self.write(|data| {
+            if let Some(constraint) = data.violated_constraint(None, &new_user.nickname, &new_user.email) {
+                return Err(users::unique_violation(Some(constraint)));
+            }
+
+            let nickname_canonical = canonical_nickname(&new_user.nickname);
+            let nickname_skeleton = nickname_skeleton(&nickname_canonical);
+            let record = UserRecord {
+                id: Uuid::new_v4(),
+                nickname: new_user.nickname,
+                email: new_user.email,
+                password_hash: new_user.password_hash,
+                is_admin: new_user.is_admin,
+                token_version: 0,
+                version: 1,
+                suspended_at: None,
+                created_at: Utc::now(),
+            };
+
+            data.users.push(StoredUser {
+                record: record.clone(),
+                nickname_canonical,
+                nickname_skeleton,
+            });
+
+            Ok(record)
+        })
+    }
+
+    async fn update_password_hash(
+        &mut self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<UserRecord>, AppError> {
+        self.write(|data| {
+            Ok(data.user_mut(user_id).map(|user| {
+                user.record.password_hash = password_hash.to_string();
+                user.record.token_version += 1;
+                user.record.clone()
+            }))
+        })
+    }
+
+    async fn list_users(&mut self, query: UserListQuery) -> Result<UserPage, AppError> {
+        let UserListQuery {
+            filter,
+            sort,
+            direction,
+            after,
+            limit,
+        } = query;
+        let search = filter.search.as_deref().map(canonical_nickname);
+
+        let mut matches: Vec<(String, UserRecord)> = self.read(|data| {
+            data.users
+                .iter()
+                .filter(|user| filter.is_admin.is_none_or(|is_admin| user.record.is_admin == is_admin))
+                .filter(|user| {
+                    filter
+                        .suspended
+                        .is_none_or(|suspended| user.record.suspended_at.is_some() == suspended)
+                })
+                .filter(|user| filter.created_after.is_none_or(|after| user.record.created_at >= after))
+                .filter(|user| filter.created_before.is_none_or(|before| user.record.created_at < before))
+                .filter(|user| {
+                    search.as_deref().is_none_or(|search| {
+                        user.nickname_canonical.contains(search)
+                            || user.record.email.to_lowercase().contains(search)
+                    })
+                })
+                .map(|user| (sort_key(user, sort), user.record.clone()))
+                .collect()
+        });
+
+        let ordering = |left: (&str, Uuid), right: (&str, Uuid)| match direction {
+            SortDirection::Asc => left.cmp(&right),
+            SortDirection::Desc => right.cmp(&left),
+        };
+
+        matches.sort_by(|(left_key, left), (right_key, right)| {
+            ordering((left_key, left.id), (right_key, right.id))
+        });
+
+        if let Some(cursor) = after {
+            matches.retain(|(key, user)| {
+                ordering((key, user.id), (&cursor.key, cursor.id)) == Ordering::Greater
+            });
+        }
+
+        let has_more = matches.len() as i64 > limit;
+        matches.truncate(limit as usize);
+
+        let next_cursor = matches.last().filter(|_| has_more).map(|(key, user)| UserCursor {
+            sort,
+            direction,
+            key: key.clone(),
+            id: user.id,
+        });
+
+        Ok(UserPage {
+            users: matches.into_iter().map(|(_, user)| user).collect(),
+            next_cursor,
+        })
+    }
+
+    async fn update_user(
+        &mut self,
+        user_id: Uuid,
+        update: UserUpdate,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        self.write(|data| {
+            if update.suspended == Some(true) {
+                data.ensure_not_last_admin(user_id)?;
+            }
+
+            let Some(current) = data.current_user(user_id, expected_version)? else {
+                return Ok(None);
+            };
+            let nickname = update.nickname.unwrap_or_else(|| current.record.nickname.clone());
+            let email = update.email.unwrap_or_else(|| current.record.email.clone());
+
+            if let Some(constraint) = data.violated_constraint(Some(user_id), &nickname, &email) {
+                return Err(users::unique_violation(Some(constraint)));
+            }
+
+            let Some(user) = data.user_mut(user_id) else {
+                return Ok(None);
+            };
+            user.nickname_canonical = canonical_nickname(&nickname);
+            user.nickname_skeleton = nickname_skeleton(&user.nickname_canonical);
+            user.record.nickname = nickname;
+            user.record.email = email;
+            user.record.version += 1;
+
+            match update.suspended {
+                Some(true) if user.record.suspended_at.is_none() => {
+                    user.record.suspended_at = Some(Utc::now());
+                    user.record.token_version += 1;
+                }
+                Some(false) => user.record.suspended_at = None,
+                _ => {}
+            }
+
+            Ok(Some(user.record.clone()))
+        })
+    }
+
+    async fn set_admin(
+        &mut self,
+        user_id: Uuid,
+        is_admin: bool,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        self.write(|data| {
+            if !is_admin {
+                data.ensure_not_last_admin(user_id)?;
+            }
+
+            Ok(data.current_user(user_id, expected_version)?.map(|user| {
+                if user.record.is_admin != is_admin {
+                    user.record.is_admin = is_admin;
+                    user.record.token_version += 1;
+                    user.record.version += 1;
+                }
+                user.record.clone()
+            }))
+        })
+    }
+
+    async fn delete_user(
+        &mut self,
+        user_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        self.write(|data| {
+            data.ensure_not_last_admin(user_id)?;
+
+            if data.current_user(user_id, expected_version)?.is_none() {
+                return Ok(None);
+            }
+
+            // Mirrors the foreign keys: tokens cascade, invites keep a null creator.
+            data.password_tokens.retain(|token| token.user_id != user_id);
+            for invite in &mut data.invites {
+                if invite.created_by == Some(user_id) {
+                    invite.created_by = None;
+                }
+            }
+
+            let index = data.users.iter().position(|user| user.record.id == user_id);
+            Ok(index.map(|index| data.users.remove(index).record))
+        })
+    }
+}
+
+#[async_trait]
+impl InviteRepository for MemoryRepositories {
+    async fn create_invite(&mut self, new_invite: NewInvite) -> Result<InviteRecord, AppError> {
+        self.write(|data| {
+            if data.invites.iter().any(|invite| invite.code == new_invite.code) {
+                return Err(AppError::Conflict("invite code already exists".to_string()));
+            }
+
+            let record = InviteRecord {
+                id: Uuid::new_v4(),
+                code: new_invite.code,
+                email: new_invite.email,
+                is_admin: new_invite.is_admin,
+                max_uses: new_invite.max_uses,
+                used_count: 0,
+                expires_at: new_invite.expires_at,
+                created_by: new_invite.created_by,
+                created_at: Utc::now(),
+                revoked_at: None,
+            };
+
+            data.invites.push(record.clone());
+
+            Ok(record)
+        })
+    }
+
+    async fn consume_invite(&mut self, code: &str) -> Result<Option<InviteRecord>, AppError> {
+        let now = Utc::now();
+
+        self.write(|data| {
+            Ok(data
+                .invites
+                .iter_mut()
+                .find(|invite| {
+                    invite.code == code
+                        && invite.revoked_at.is_none()
+                        && invite.expires_at.is_none_or(|expires_at| expires_at > now)
+                        && invite.used_count < invite.max_uses
+                })
+                .map(|invite| {
+                    invite.used_count += 1;
+                    invite.clone()
+                }))
+        })
+    }
+
+    async fn list_invites(&mut self) -> Result<Vec<InviteRecord>, AppError> {
+        let mut invites = self.read(|data| data.invites.clone());
+        invites.sort_by_key(|invite| Reverse(invite.created_at));
+
+        Ok(invites)
+    }
+
+    async fn revoke_invite(&mut self, invite_id: Uuid) -> Result<Option<InviteRecord>, AppError> {
+        self.write(|data| {
+            Ok(data.invites.iter_mut().find(|invite| invite.id == invite_id).map(|invite| {
+                invite.revoked_at.get_or_insert_with(Utc::now);
+                invite.clone()
+            }))
+        })
+    }
+}
+
+#[async_trait]
+impl PasswordTokenRepository for MemoryRepositories {
+    async fn consume_password_token(&mut self, token: &str) -> Result<Option<Uuid>, AppError> {
+        let token_hash = password_tokens::hash_token(token.trim());
+        let now = Utc::now();
+
+        self.write(|data| {
+            Ok(data
+                .password_tokens
+                .iter_mut()
+                .find(|stored| {
+                    stored.token_hash == token_hash && stored.used_at.is_none() && stored.expires_at > now
+                })
+                .map(|stored| {
+                    stored.used_at = Some(now);
+                    stored.user_id
+                }))
+        })
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryRepositories {
+    async fn record_event(&mut self, event: NewAuditEvent) -> Result<AuditEventRecord, AppError> {
+        self.write(|data| {
+            let prev_hash = data.audit_events.last().map(|last| last.hash.clone());
+            let SealedAuditEvent {
+                occurred_at,
+                event,
+                prev_hash,
+                hash,
+            } = audit::seal_event(prev_hash, event);
+
+            let record = AuditEventRecord {
+                id: data.audit_events.len() as i64 + 1,
+                occurred_at,
+                event_type: event.event_type.as_str().to_string(),
+                actor_id: event.actor_id,
+                target_id: event.target_id,
+                ip: event.ip,
+                request_id: event.request_id,
+                payload: event.payload,
+                prev_hash,
+                hash,
+            };
+
+            data.audit_events.push(record.clone());
+
+            Ok(record)
+        })
+    }
+
+    async fn list_events(
+        &mut self,
+        filter: &AuditEventFilter,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, AppError> {
+        Ok(self.read(|data| {
+            data.audit_events
+                .iter()
+                .rev()
+                .filter(|event| filter.event_type.as_ref().is_none_or(|kind| &event.event_type == kind))
+                .filter(|event| filter.actor_id.is_none_or(|actor_id| event.actor_id == Some(actor_id)))
+                .filter(|event| filter.target_id.is_none_or(|target_id| event.target_id == Some(target_id)))
+                .filter(|event| filter.from.is_none_or(|from| event.occurred_at >= from))
+                .filter(|event| filter.to.is_none_or(|to| event.occurred_at < to))
+                .filter(|event| before_id.is_none_or(|before_id| event.id < before_id))
+                .take(limit as usize)
+                .cloned()
+                .collect()
+        }))
+    }
+
+    async fn verify_chain(&mut self) -> Result<ChainVerification, AppError> {
+        let mut verifier = ChainVerifier::default();
+
+        Ok(self.read(|data| verifier.check(&data.audit_events)).unwrap_or_else(|| verifier.finish()))
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for MemoryRepositories {
+    async fn commit(self: Box<Self>) -> Result<(), AppError> {
+        let Some(work) = self.work else {
+            return Ok(());
+        };
+        if !work.dirty {
+            return Ok(());
+        }
+
+        let mut shared = lock(&self.shared);
+        if shared.generation != work.base_generation {
+            return Err(AppError::Conflict(
+                "the in-memory store changed since this unit of work began; retry".to_string(),
+            ));
+        }
+
+        shared.data = work.data;
+        shared.generation += 1;
+
+        Ok(())
+    }
+}
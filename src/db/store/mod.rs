@@ -0,0 +1,144 @@
+//! Storage behind trait objects so handlers don't depend on a concrete pool.
+//!
+//! Every table gets a `*Repository` trait with one method per query the handlers
+//! need; [`Repositories`] bundles them. A [`Store`] hands out either a session,
+//! whose writes apply immediately, or a [`UnitOfWork`], whose writes apply only on
+//! `commit` and are discarded when it is dropped. New tables add a trait here,
+//! add it to `Repositories`, and implement it in `postgres`, `sqlite` and `memory`.
+
+// Only constructed by the in-process router tests.
+#[cfg(test)]
+pub mod memory;
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        audit::{AuditEventFilter, AuditEventRecord, ChainVerification, NewAuditEvent},
+        invites::{InviteRecord, NewInvite},
+        users::{NewUser, UserListQuery, UserPage, UserRecord, UserUpdate},
+    },
+    error::AppError,
+};
+
+#[async_trait]
+pub trait UserRepository: Send {
+    async fn find_user_by_id(&mut self, user_id: Uuid) -> Result<Option<UserRecord>, AppError>;
+
+    /// Case-insensitive.
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<UserRecord>, AppError>;
+
+    /// Matches on the canonical form, so case and compatibility variants find the same user.
+    async fn find_user_by_nickname(&mut self, nickname: &str) -> Result<Option<UserRecord>, AppError>;
+
+    /// Fails with a conflict naming the field when the nickname or email is taken.
+    async fn create_user(&mut self, new_user: NewUser) -> Result<UserRecord, AppError>;
+
+    /// Replaces the password hash and revokes every token issued before the change.
+    async fn update_password_hash(
+        &mut self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<UserRecord>, AppError>;
+
+    /// One keyset page; see [`users::list_users`](crate::db::users::list_users).
+    async fn list_users(&mut self, query: UserListQuery) -> Result<UserPage, AppError>;
+
+    /// Bumps the version; suspending also revokes tokens. Fails with a conflict for
+    /// the last active admin and with a failed precondition for a stale version.
+    async fn update_user(
+        &mut self,
+        user_id: Uuid,
+        update: UserUpdate,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError>;
+
+    /// Revokes the user's tokens when the role changes; same failures as `update_user`.
+    async fn set_admin(
+        &mut self,
+        user_id: Uuid,
+        is_admin: bool,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError>;
+
+    /// Same failures as `update_user`.
+    async fn delete_user(
+        &mut self,
+        user_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError>;
+}
+
+#[async_trait]
+pub trait InviteRepository: Send {
+    async fn create_invite(&mut self, new_invite: NewInvite) -> Result<InviteRecord, AppError>;
+
+    /// Takes one use of a redeemable invite; `None` when it is unknown, revoked,
+    /// expired or exhausted.
+    async fn consume_invite(&mut self, code: &str) -> Result<Option<InviteRecord>, AppError>;
+
+    /// Newest first.
+    async fn list_invites(&mut self) -> Result<Vec<InviteRecord>, AppError>;
+
+    /// Keeps the first revocation time when revoked twice.
+    async fn revoke_invite(&mut self, invite_id: Uuid) -> Result<Option<InviteRecord>, AppError>;
+}
+
+#[async_trait]
+pub trait PasswordTokenRepository: Send {
+    /// Marks the token used and returns its user, or `None` when it is unknown,
+    /// expired or already used.
+    async fn consume_password_token(&mut self, token: &str) -> Result<Option<Uuid>, AppError>;
+}
+
+#[async_trait]
+pub trait AuditRepository: Send {
+    /// Appends to the hash chain.
+    async fn record_event(&mut self, event: NewAuditEvent) -> Result<AuditEventRecord, AppError>;
+
+    /// Newest first; `before_id` is the keyset cursor from the previous page.
+    async fn list_events(
+        &mut self,
+        filter: &AuditEventFilter,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, AppError>;
+
+    /// Recomputes every hash in id order.
+    async fn verify_chain(&mut self) -> Result<ChainVerification, AppError>;
+}
+
+pub trait Repositories:
+    UserRepository + InviteRepository + PasswordTokenRepository + AuditRepository
+{
+}
+
+impl<T> Repositories for T where
+    T: UserRepository + InviteRepository + PasswordTokenRepository + AuditRepository + ?Sized
+{
+}
+
+#[async_trait]
+pub trait UnitOfWork: Repositories {
+    async fn commit(self: Box<Self>) -> Result<(), AppError>;
+}
+
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Repositories whose writes apply immediately.
+    async fn session(&self) -> Result<Box<dyn Repositories>, AppError>;
+
+    /// Repositories whose writes apply together on `commit`.
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork>, AppError>;
+
+    /// Repositories for reads made on behalf of `user_id` that tolerate replication
+    /// lag. Backends without replicas read from the primary; never write through it.
+    async fn read_session(&self, user_id: Uuid) -> Result<Box<dyn Repositories>, AppError> {
+        let _ = user_id;
+        self.session().await
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::DerefMut;
+
+use async_trait::async_trait;
+use sqlx::{Connection, PgConnection, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{
+    AuditRepository, InviteRepository, PasswordTokenRepository, Repositories, Store, UnitOfWork,
+    UserRepository,
+};
+use crate::{
+    db::{
+        audit::{self, AuditEventFilter, AuditEventRecord, ChainVerification, NewAuditEvent},
+        invites::{self, InviteRecord, NewInvite},
+        password_tokens,
+        routing::DatabasePools,
+        users::{self, NewUser, UserListQuery, UserPage, UserRecord, UserUpdate},
+    },
+    error::AppError,
+};
+
+#[derive(Clone)]
+pub struct PgStore {
+    pools: DatabasePools,
+}
+
+impl PgStore {
+    pub fn new(pools: DatabasePools) -> Self {
+        Self { pools }
+    }
+}
+
+#[async_trait]
+impl Store for PgStore {
+    async fn session(&self) -> Result<Box<dyn Repositories>, AppError> {
+        let conn = self.pools.primary().acquire().await?;
+
+        Ok(Box::new(PgRepositories { conn }))
+    }
+
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork>, AppError> {
+        let conn = self.pools.primary().begin().await?;
+
+        Ok(Box::new(PgRepositories { conn }))
+    }
+
+    async fn read_session(&self, user_id: Uuid) -> Result<Box<dyn Repositories>, AppError> {
+        let conn = self.pools.reader_for(user_id).acquire().await?;
+
+        Ok(Box::new(PgRepositories { conn }))
+    }
+}
+
+/// Repositories over a pooled connection (session) or an open transaction (unit of work).
+pub struct PgRepositories<C> {
+    conn: C,
+}
+
+#[async_trait]
+impl<C> UserRepository for PgRepositories<C>
+where
+    C: DerefMut<Target = PgConnection> + Send,
+{
+    async fn find_user_by_id(&mut self, user_id: Uuid) -> Result<Option<UserRecord>, AppError> {
+        users::find_user_by_id(&mut *self.conn, user_id).await
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<UserRecord>, AppError> {
+        users::find_user_by_email(&mut *self.conn, email).await
+    }
+
+    async fn find_user_by_nickname(&mut self, nickname: &str) -> Result<Option<UserRecord>, AppError> {
+        users::find_user_by_nickname(&mut *self.conn, nickname).await
+    }
+
+    async fn create_user(&mut self, new_user: NewUser) -> Result<UserRecord, AppError> {
+        users::create_user(&mut self.conn, new_user).await
+    }
+
+    async fn update_password_hash(
+        &mut self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<UserRecord>, AppError> {
+        users::update_password_hash(&mut self.conn, user_id, password_hash).await
+    }
+
+    async fn list_users(&mut self, query: UserListQuery) -> Result<UserPage, AppError> {
+        users::list_users(&mut self.conn, query).await
+    }
+
+    async fn update_user(
+        &mut self,
+        user_id: Uuid,
+        update: UserUpdate,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        users::update_user(&mut self.conn, user_id, update, expected_version).await
+    }
+
+    async fn set_admin(
+        &mut self,
+        user_id: Uuid,
+        is_admin: bool,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        users::set_admin(&mut self.conn, user_id, is_admin, expected_version).await
+    }
+
+    async fn delete_user(
+        &mut self,
+        user_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        users::delete_user(&mut self.conn, user_id, expected_version).await
+    }
+}
+
+#[async_trait]
+impl<C> InviteRepository for PgRepositories<C>
+where
+    C: DerefMut<Target = PgConnection> + Send,
+{
+    async fn create_invite(&mut self, new_invite: NewInvite) -> Result<InviteRecord, AppError> {
+        invites::create_invite(&mut *self.conn, new_invite).await
+    }
+
+    async fn consume_invite(&mut self, code: &str) -> Result<Option<InviteRecord>, AppError> {
+        invites::consume_invite(&mut *self.conn, code).await
+    }
+
+    async fn list_invites(&mut self) -> Result<Vec<InviteRecord>, AppError> {
+        invites::list_invites(&mut *self.conn).await
+    }
+
+    async fn revoke_invite(&mut self, invite_id: Uuid) -> Result<Option<InviteRecord>, AppError> {
+        invites::revoke_invite(&mut *self.conn, invite_id).await
+    }
+}
+
+#[async_trait]
+impl<C> PasswordTokenRepository for PgRepositories<C>
+where
+    C: DerefMut<Target = PgConnection> + Send,
+{
+    async fn consume_password_token(&mut self, token: &str) -> Result<Option<Uuid>, AppError> {
+        password_tokens::consume_password_token(&mut *self.conn, token).await
+    }
+}
+
+#[async_trait]
+impl<C> AuditRepository for PgRepositories<C>
+where
+    C: DerefMut<Target = PgConnection> + Send,
+{
+    /// The chain lock is transaction-scoped, so a session gets its own transaction
+    /// and a unit of work a savepoint.
+    async fn record_event(&mut self, event: NewAuditEvent) -> Result<AuditEventRecord, AppError> {
+        let mut tx = self.conn.begin().await?;
+        let record = audit::record_event(&mut tx, event).await?;
+        tx.commit().await?;
+
+        Ok(record)
+    }
+
+    async fn list_events(
+        &mut self,
+        filter: &AuditEventFilter,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, AppError> {
+        audit::list_events(&mut self.conn, filter, before_id, limit).await
+    }
+
+    async fn verify_chain(&mut self) -> Result<ChainVerification, AppError> {
+        audit::verify_chain(&mut self.conn).await
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for PgRepositories<Transaction<'static, Postgres>> {
+    async fn commit(self: Box<Self>) -> Result<(), AppError> {
+        self.conn.commit().await?;
+
+        Ok(())
+    }
+}
+
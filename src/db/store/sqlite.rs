@@ -0,0 +1,708 @@
+use std::ops::DerefMut;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::{Connection, QueryBuilder, Sqlite, SqliteConnection, SqlitePool, Transaction};
+use uuid::Uuid;
+
+use super::{
+    AuditRepository, InviteRepository, PasswordTokenRepository, Repositories, Store, UnitOfWork,
+    UserRepository,
+};
+use crate::{
+    db::{
+        audit::{
+            self, AuditEventFilter, AuditEventRecord, ChainVerification, ChainVerifier, NewAuditEvent,
+            SealedAuditEvent,
+        },
+        invites::{InviteRecord, NewInvite},
+        password_tokens,
+        users::{self, NewUser, UserListQuery, UserListRow, UserPage, UserRecord, UserUpdate},
+    },
+    error::AppError,
+    validation::nickname::{canonical_nickname, nickname_skeleton},
+};
+
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn session(&self) -> Result<Box<dyn Repositories>, AppError> {
+        let conn = self.pool.acquire().await?;
+
+        Ok(Box::new(SqliteRepositories { conn }))
+    }
+
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork>, AppError> {
+        let conn = self.pool.begin().await?;
+
+        Ok(Box::new(SqliteRepositories { conn }))
+    }
+}
+
+pub struct SqliteRepositories<C> {
+    conn: C,
+}
+
+/// Whether another row holds the nickname, email, canonical nickname, skeleton and
+/// lowercased email; `None` when there is no other row or no value was written.
+type Collisions = (Option<bool>, Option<bool>, Option<bool>, Option<bool>, Option<bool>);
+
+/// Maps unique violations on `users` to the conflict PostgreSQL would report.
+///
+/// SQLite names whichever index it happened to check first, so "ALICE" next to
+/// "alice" can fail on the skeleton index and read as "too similar". Instead, the
+/// written values are compared against the other rows in the order PostgreSQL
+/// checks its indexes, and the first one that collides names the conflict.
+async fn map_user_write_error(
+    conn: &mut SqliteConnection,
+    error: sqlx::Error,
+    user_id: Option<Uuid>,
+    nickname: Option<&str>,
+    email: Option<&str>,
+) -> AppError {
+    let sqlx::Error::Database(db_error) = &error else {
+        return AppError::from(error);
+    };
+    if !db_error.is_unique_violation() {
+        return AppError::from(error);
+    }
+
+    let canonical = nickname.map(canonical_nickname);
+    let skeleton = canonical.as_deref().map(nickname_skeleton);
+
+    let collisions = sqlx::query_as::<_, Collisions>(
+        r#"
+        SELECT MAX(nickname = ?1), MAX(email = ?2), MAX(nickname_canonical = ?3),
+               MAX(nickname_skeleton = ?4), MAX(lower(email) = lower(?2))
+        FROM users
+        WHERE id IS NOT ?5
+        "#,
+    )
+    .bind(nickname)
+    .bind(email)
+    .bind(canonical)
+    .bind(skeleton)
+    .bind(user_id)
+    .fetch_one(conn)
+    .await;
+
+    let (nickname, email, canonical, skeleton, email_lower) = match collisions {
+        Ok(collisions) => collisions,
+        Err(error) => return AppError::from(error),
+    };
+
+    let constraint = [
+        ("users_nickname_key", nickname),
+        ("users_email_key", email),
+        ("users_nickname_canonical_key", canonical),
+        ("users_nickname_skeleton_key", skeleton),
+        ("users_email_lower_key", email_lower),
+    ]
+    .into_iter()
+    .find(|(_, collides)| *collides == Some(true))
+    .map(|(constraint, _)| constraint);
+
+    users::unique_violation(constraint)
+}
+
+#[async_trait]
+impl<C> UserRepository for SqliteRepositories<C>
+where
+    C: DerefMut<Target = SqliteConnection> + Send,
+{
+    async fn find_user_by_id(&mut self, user_id: Uuid) -> Result<Option<UserRecord>, AppError> {
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            FROM users
+            WHERE id = ?1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    async fn find_user_by_email(&mut self, email: &str) -> Result<Option<UserRecord>, AppError> {
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            FROM users
+            WHERE lower(email) = lower(?1)
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    async fn find_user_by_nickname(&mut self, nickname: &str) -> Result<Option<UserRecord>, AppError> {
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            FROM users
+            WHERE nickname_canonical = ?1
+            "#,
+        )
+        .bind(canonical_nickname(nickname))
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    async fn create_user(&mut self, new_user: NewUser) -> Result<UserRecord, AppError> {
+        let nickname_canonical = canonical_nickname(&new_user.nickname);
+        let nickname_skeleton = nickname_skeleton(&nickname_canonical);
+
+        let query_result = sqlx::query_as::<_, UserRecord>(
+            r#"
+            INSERT INTO users (id, nickname, email, password_hash, is_admin, created_at, nickname_canonical, nickname_skeleton)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(&new_user.nickname)
+        .bind(&new_user.email)
+        .bind(new_user.password_hash)
+        .bind(new_user.is_admin)
+        .bind(Utc::now())
+        .bind(nickname_canonical)
+        .bind(nickname_skeleton)
+        .fetch_one(&mut *self.conn)
+        .await;
+
+        match query_result {
+            Ok(record) => Ok(record),
+            Err(error) => Err(map_user_write_error(
+                &mut self.conn,
+                error,
+                None,
+                Some(&new_user.nickname),
+                Some(&new_user.email),
+            )
+            .await),
+        }
+    }
+
+    async fn update_password_hash(
+        &mut self,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<UserRecord>, AppError> {
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            UPDATE users
+            SET password_hash = ?2, token_version = token_version + 1
+            WHERE id = ?1
+            RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(password_hash)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Same query as on PostgreSQL; created_at keys compare as the RFC 3339 text
+    /// they are stored as.
+    async fn list_users(&mut self, query: UserListQuery) -> Result<UserPage, AppError> {
+        let sort_expression = query.sort.expression();
+
+        let mut builder = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, \
+             created_at, CAST({sort_expression} AS TEXT) AS sort_key FROM users WHERE 1"
+        ));
+
+        if let Some(is_admin) = query.filter.is_admin {
+            builder.push(" AND is_admin = ").push_bind(is_admin);
+        }
+
+        match query.filter.suspended {
+            Some(true) => {
+                builder.push(" AND suspended_at IS NOT NULL");
+            }
+            Some(false) => {
+                builder.push(" AND suspended_at IS NULL");
+            }
+            None => {}
+        }
+
+        if let Some(created_after) = query.filter.created_after {
+            builder.push(" AND created_at >= ").push_bind(created_after);
+        }
+
+        if let Some(created_before) = query.filter.created_before {
+            builder.push(" AND created_at < ").push_bind(created_before);
+        }
+
+        if let Some(search) = query.filter.search.as_deref() {
+            let pattern = format!("%{}%", users::escape_like(&canonical_nickname(search)));
+            builder
+                .push(" AND (nickname_canonical LIKE ")
+                .push_bind(pattern.clone())
+                .push(" ESCAPE '\\' OR lower(email) LIKE ")
+                .push_bind(pattern)
+                .push(" ESCAPE '\\')");
+        }
+
+        if let Some(cursor) = query.after {
+            builder
+                .push(format!(" AND ({sort_expression}, id) {} (", query.direction.comparison()))
+                .push_bind(cursor.key)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+
+        let direction = query.direction.keyword();
+        builder
+            .push(format!(" ORDER BY {sort_expression} {direction}, id {direction} LIMIT "))
+            .push_bind(query.limit + 1);
+
+        let rows: Vec<UserListRow> = builder.build_query_as().fetch_all(&mut *self.conn).await?;
+
+        Ok(UserPage::from_rows(rows, query.sort, query.direction, query.limit))
+    }
+
+    async fn update_user(
+        &mut self,
+        user_id: Uuid,
+        update: UserUpdate,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        let mut tx = self.conn.begin().await?;
+
+        if update.suspended == Some(true) {
+            ensure_not_last_admin(&mut tx, user_id).await?;
+        }
+
+        // Every change below starts with a comma.
+        let mut builder = QueryBuilder::<Sqlite>::new("UPDATE users SET version = version + 1");
+
+        if let Some(nickname) = &update.nickname {
+            let canonical = canonical_nickname(nickname);
+            let skeleton = nickname_skeleton(&canonical);
+            builder
+                .push(", nickname = ")
+                .push_bind(nickname.clone())
+                .push(", nickname_canonical = ")
+                .push_bind(canonical)
+                .push(", nickname_skeleton = ")
+                .push_bind(skeleton);
+        }
+
+        if let Some(email) = &update.email {
+            builder.push(", email = ").push_bind(email.clone());
+        }
+
+        match update.suspended {
+            Some(true) => {
+                builder
+                    .push(
+                        ", token_version = token_version + CASE WHEN suspended_at IS NULL THEN 1 ELSE 0 END, \
+                         suspended_at = COALESCE(suspended_at, ",
+                    )
+                    .push_bind(Utc::now())
+                    .push(")");
+            }
+            Some(false) => {
+                builder.push(", suspended_at = NULL");
+            }
+            None => {}
+        }
+
+        builder.push(" WHERE id = ").push_bind(user_id);
+
+        if let Some(expected_version) = expected_version {
+            builder.push(" AND version = ").push_bind(expected_version);
+        }
+
+        builder.push(
+            " RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at",
+        );
+
+        let query_result = builder.build_query_as::<UserRecord>().fetch_optional(&mut *tx).await;
+        let record = match query_result {
+            Ok(record) => record,
+            Err(error) => {
+                let (nickname, email) = (update.nickname.as_deref(), update.email.as_deref());
+                return Err(map_user_write_error(&mut tx, error, Some(user_id), nickname, email).await);
+            }
+        };
+
+        let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+        tx.commit().await?;
+
+        Ok(record)
+    }
+
+    async fn set_admin(
+        &mut self,
+        user_id: Uuid,
+        is_admin: bool,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        let mut tx = self.conn.begin().await?;
+
+        if !is_admin {
+            ensure_not_last_admin(&mut tx, user_id).await?;
+        }
+
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            UPDATE users
+            SET is_admin = ?2,
+                token_version = token_version + CASE WHEN is_admin = ?2 THEN 0 ELSE 1 END,
+                version = version + CASE WHEN is_admin = ?2 THEN 0 ELSE 1 END
+            WHERE id = ?1 AND (?3 IS NULL OR version = ?3)
+            RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(is_admin)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+        tx.commit().await?;
+
+        Ok(record)
+    }
+
+    async fn delete_user(
+        &mut self,
+        user_id: Uuid,
+        expected_version: Option<i64>,
+    ) -> Result<Option<UserRecord>, AppError> {
+        let mut tx = self.conn.begin().await?;
+
+        ensure_not_last_admin(&mut tx, user_id).await?;
+
+        let record = sqlx::query_as::<_, UserRecord>(
+            r#"
+            DELETE FROM users
+            WHERE id = ?1 AND (?2 IS NULL OR version = ?2)
+            RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(expected_version)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+        tx.commit().await?;
+
+        Ok(record)
+    }
+}
+
+/// See `users::unless_stale`.
+async fn unless_stale(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    expected_version: Option<i64>,
+    record: Option<UserRecord>,
+) -> Result<Option<UserRecord>, AppError> {
+    let (Some(expected_version), None) = (expected_version, &record) else {
+        return Ok(record);
+    };
+
+    let current_version: Option<i64> = sqlx::query_scalar("SELECT version FROM users WHERE id = ?1")
+        .bind(user_id)
+        .fetch_optional(conn)
+        .await?;
+
+    match current_version {
+        Some(current_version) => Err(AppError::PreconditionFailed(format!(
+            "user was modified: version is {current_version}, not {expected_version}"
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// No row locks are needed: the pool has a single connection, so the check and
+/// the write that follows it cannot interleave with another writer.
+async fn ensure_not_last_admin(conn: &mut SqliteConnection, user_id: Uuid) -> Result<(), AppError> {
+    let active_admins: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM users WHERE is_admin AND suspended_at IS NULL")
+            .fetch_all(conn)
+            .await?;
+
+    if active_admins.len() == 1 && active_admins[0] == user_id {
+        return Err(AppError::Conflict(
+            "cannot remove or suspend the last remaining admin".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl<C> InviteRepository for SqliteRepositories<C>
+where
+    C: DerefMut<Target = SqliteConnection> + Send,
+{
+    async fn create_invite(&mut self, new_invite: NewInvite) -> Result<InviteRecord, AppError> {
+        let query_result = sqlx::query_as::<_, InviteRecord>(
+            r#"
+            INSERT INTO invites (id, code, email, is_admin, max_uses, expires_at, created_by, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(new_invite.code)
+        .bind(new_invite.email)
+        .bind(new_invite.is_admin)
+        .bind(new_invite.max_uses)
+        .bind(new_invite.expires_at)
+        .bind(new_invite.created_by)
+        .bind(Utc::now())
+        .fetch_one(&mut *self.conn)
+        .await;
+
+        match query_result {
+            Ok(record) => Ok(record),
+            Err(sqlx::Error::Database(db_error)) if db_error.is_unique_violation() => {
+                Err(AppError::Conflict("invite code already exists".to_string()))
+            }
+            Err(other) => Err(AppError::from(other)),
+        }
+    }
+
+    async fn consume_invite(&mut self, code: &str) -> Result<Option<InviteRecord>, AppError> {
+        let record = sqlx::query_as::<_, InviteRecord>(
+            r#"
+            UPDATE invites
+            SET used_count = used_count + 1
+            WHERE code = ?1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR julianday(expires_at) > julianday('now'))
+              AND used_count < max_uses
+            RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+            "#,
+        )
+        .bind(code)
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+
+    async fn list_invites(&mut self) -> Result<Vec<InviteRecord>, AppError> {
+        let records = sqlx::query_as::<_, InviteRecord>(
+            r#"
+            SELECT id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+            FROM invites
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&mut *self.conn)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn revoke_invite(&mut self, invite_id: Uuid) -> Result<Option<InviteRecord>, AppError> {
+        let record = sqlx::query_as::<_, InviteRecord>(
+            r#"
+            UPDATE invites
+            SET revoked_at = COALESCE(revoked_at, ?2)
+            WHERE id = ?1
+            RETURNING id, code, email, is_admin, max_uses, used_count, expires_at, created_by, created_at, revoked_at
+            "#,
+        )
+        .bind(invite_id)
+        .bind(Utc::now())
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(record)
+    }
+}
+
+#[async_trait]
+impl<C> PasswordTokenRepository for SqliteRepositories<C>
+where
+    C: DerefMut<Target = SqliteConnection> + Send,
+{
+    async fn consume_password_token(&mut self, token: &str) -> Result<Option<Uuid>, AppError> {
+        let user_id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE password_tokens
+            SET used_at = ?2
+            WHERE token_hash = ?1
+              AND used_at IS NULL
+              AND julianday(expires_at) > julianday('now')
+            RETURNING user_id
+            "#,
+        )
+        .bind(password_tokens::hash_token(token.trim()))
+        .bind(Utc::now())
+        .fetch_optional(&mut *self.conn)
+        .await?;
+
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl<C> AuditRepository for SqliteRepositories<C>
+where
+    C: DerefMut<Target = SqliteConnection> + Send,
+{
+    /// Reading the previous hash and appending happen in one (nested) transaction;
+    /// the pool has a single connection, so no other writer can slip in between.
+    async fn record_event(&mut self, event: NewAuditEvent) -> Result<AuditEventRecord, AppError> {
+        let mut tx = self.conn.begin().await?;
+
+        let prev_hash: Option<String> =
+            sqlx::query_scalar("SELECT hash FROM audit_events ORDER BY id DESC LIMIT 1")
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        let SealedAuditEvent {
+            occurred_at,
+            event,
+            prev_hash,
+            hash,
+        } = audit::seal_event(prev_hash, event);
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO audit_events (occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            RETURNING id
+            "#,
+        )
+        .bind(occurred_at)
+        .bind(event.event_type.as_str())
+        .bind(event.actor_id)
+        .bind(event.target_id)
+        .bind(event.ip.as_deref())
+        .bind(event.request_id.as_deref())
+        .bind(event.payload.to_string())
+        .bind(&prev_hash)
+        .bind(&hash)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(AuditEventRecord {
+            id,
+            occurred_at,
+            event_type: event.event_type.as_str().to_string(),
+            actor_id: event.actor_id,
+            target_id: event.target_id,
+            ip: event.ip,
+            request_id: event.request_id,
+            payload: event.payload,
+            prev_hash,
+            hash,
+        })
+    }
+
+    async fn list_events(
+        &mut self,
+        filter: &AuditEventFilter,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<AuditEventRecord>, AppError> {
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            "SELECT id, occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash \
+             FROM audit_events WHERE 1",
+        );
+
+        if let Some(event_type) = &filter.event_type {
+            builder.push(" AND event_type = ").push_bind(event_type.clone());
+        }
+
+        if let Some(actor_id) = filter.actor_id {
+            builder.push(" AND actor_id = ").push_bind(actor_id);
+        }
+
+        if let Some(target_id) = filter.target_id {
+            builder.push(" AND target_id = ").push_bind(target_id);
+        }
+
+        if let Some(from) = filter.from {
+            builder.push(" AND occurred_at >= ").push_bind(from);
+        }
+
+        if let Some(to) = filter.to {
+            builder.push(" AND occurred_at < ").push_bind(to);
+        }
+
+        if let Some(before_id) = before_id {
+            builder.push(" AND id < ").push_bind(before_id);
+        }
+
+        builder.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        let records = builder.build_query_as().fetch_all(&mut *self.conn).await?;
+
+        Ok(records)
+    }
+
+    async fn verify_chain(&mut self) -> Result<ChainVerification, AppError> {
+        let mut verifier = ChainVerifier::default();
+        let mut after_id = 0_i64;
+
+        loop {
+            let batch = sqlx::query_as::<_, AuditEventRecord>(
+                r#"
+                SELECT id, occurred_at, event_type, actor_id, target_id, ip, request_id, payload, prev_hash, hash
+                FROM audit_events
+                WHERE id > ?1
+                ORDER BY id
+                LIMIT ?2
+                "#,
+            )
+            .bind(after_id)
+            .bind(audit::VERIFY_BATCH_SIZE)
+            .fetch_all(&mut *self.conn)
+            .await?;
+
+            let Some(last) = batch.last() else {
+                break;
+            };
+            after_id = last.id;
+
+            if let Some(invalid) = verifier.check(&batch) {
+                return Ok(invalid);
+            }
+        }
+
+        Ok(verifier.finish())
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for SqliteRepositories<Transaction<'static, Sqlite>> {
+    async fn commit(self: Box<Self>) -> Result<(), AppError> {
+        self.conn.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+//! Row-level security context. Tenant tables (see `0004_row_level_security.sql`)
+//! only show the rows the `swarm.*` settings allow, so tenant queries run in a
+//! transaction opened here. Settings are transaction-local and never leak to the
+//! next user of a pooled connection.
+
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Who is asking. `organization_id` is only set once membership was verified.
+#[derive(Debug, Clone, Copy)]
+pub struct TenantContext {
+    pub user_id: Uuid,
+    pub organization_id: Option<Uuid>,
+}
+
+/// Starts a transaction scoped to `context`.
+pub async fn begin(
+    pool: &PgPool,
+    context: TenantContext,
+) -> Result<Transaction<'static, Postgres>, AppError> {
+    let mut tx = pool.begin().await?;
+    enter(&mut tx, context).await?;
+
+    Ok(tx)
+}
+
+/// Rescopes an open transaction to `context`, for work that acts as several
+/// users in turn within one transaction.
+pub async fn enter(conn: &mut PgConnection, context: TenantContext) -> Result<(), AppError> {
+    sqlx::query(
+        "SELECT set_config('swarm.current_user', $1, true), set_config('swarm.current_org', $2, true)",
+    )
+    .bind(context.user_id.to_string())
+    .bind(context.organization_id.map(|id| id.to_string()).unwrap_or_default())
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Enters an organization the caller just created, before any membership exists.
+pub(crate) async fn enter_organization(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+) -> Result<(), AppError> {
+    set_local(conn, "swarm.current_org", &organization_id.to_string()).await
+}
+
+/// Makes the invite with this code visible for redemption.
+pub(crate) async fn present_invite_code(conn: &mut PgConnection, code: &str) -> Result<(), AppError> {
+    set_local(conn, "swarm.invite_code", code).await
+}
+
+async fn set_local(conn: &mut PgConnection, name: &str, value: &str) -> Result<(), AppError> {
+    sqlx::query("SELECT set_config($1, $2, true)")
+        .bind(name)
+        .bind(value)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
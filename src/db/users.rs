@@ -1,6 +1,12 @@
-use crate::error::AppError;
+use crate::{
+    db::outbox,
+    error::AppError,
+    events::DomainEvent,
+    validation::nickname::{canonical_nickname, nickname_skeleton},
+};
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, PgConnection, PgExecutor, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -10,6 +16,10 @@ pub struct UserRecord {
     pub email: String,
     pub password_hash: String,
     pub is_admin: bool,
+    pub token_version: i32,
+    /// Bumped by profile, suspension and role changes; sent as the `ETag`.
+    pub version: i64,
+    pub suspended_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -20,14 +30,19 @@ pub struct NewUser {
     pub is_admin: bool,
 }
 
-pub async fn create_user(pool: &PgPool, new_user: NewUser) -> Result<UserRecord, AppError> {
+/// Every write below appends its domain events to the outbox within the same
+/// transaction; outside one it opens its own, inside one a savepoint.
+pub async fn create_user(conn: &mut PgConnection, new_user: NewUser) -> Result<UserRecord, AppError> {
     let user_id = Uuid::new_v4();
+    let nickname_canonical = canonical_nickname(&new_user.nickname);
+    let nickname_skeleton = nickname_skeleton(&nickname_canonical);
 
+    let mut tx = conn.begin().await?;
     let query_result = sqlx::query_as::<_, UserRecord>(
         r#"
-        INSERT INTO users (id, nickname, email, password_hash, is_admin)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, nickname, email, password_hash, is_admin, created_at
+        INSERT INTO users (id, nickname, email, password_hash, is_admin, nickname_canonical, nickname_skeleton)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
         "#,
     )
     .bind(user_id)
@@ -35,46 +50,583 @@ pub async fn create_user(pool: &PgPool, new_user: NewUser) -> Result<UserRecord,
     .bind(new_user.email)
     .bind(new_user.password_hash)
     .bind(new_user.is_admin)
-    .fetch_one(pool)
+    .bind(nickname_canonical)
+    .bind(nickname_skeleton)
+    .fetch_one(&mut *tx)
     .await;
+    let record = query_result.map_err(map_write_error)?;
 
-    match query_result {
-        Ok(record) => Ok(record),
-        Err(sqlx::Error::Database(db_error)) if db_error.code().as_deref() == Some("23505") => {
-            Err(AppError::Conflict(
-                "user with this nickname or email already exists".to_string(),
-            ))
+    outbox::append(
+        &mut *tx,
+        &[DomainEvent::UserCreated {
+            user_id: record.id,
+            nickname: record.nickname.clone(),
+            email: record.email.clone(),
+            is_admin: record.is_admin,
+        }],
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// Maps unique violations on `users` to a conflict naming the offending field.
+fn map_write_error(error: sqlx::Error) -> AppError {
+    match error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            unique_violation(db_error.constraint())
         }
-        Err(other) => Err(AppError::from(other)),
+        other => AppError::from(other),
     }
 }
 
-pub async fn find_user_by_email(pool: &PgPool, email: &str) -> Result<Option<UserRecord>, AppError> {
+/// Conflict for a violated unique constraint on `users`, named as in the schema.
+pub fn unique_violation(constraint: Option<&str>) -> AppError {
+    let message = match constraint {
+        Some("users_nickname_key" | "users_nickname_canonical_key") => "nickname is already taken",
+        Some("users_email_key" | "users_email_lower_key") => "email is already registered",
+        Some("users_nickname_skeleton_key") => "nickname is too similar to an existing nickname",
+        _ => "user with this nickname or email already exists",
+    };
+
+    AppError::Conflict(message.to_string())
+}
+
+pub async fn find_user_by_email<'e>(
+    executor: impl PgExecutor<'e>,
+    email: &str,
+) -> Result<Option<UserRecord>, AppError> {
     let record = sqlx::query_as::<_, UserRecord>(
         r#"
-        SELECT id, nickname, email, password_hash, is_admin, created_at
+        SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
         FROM users
-        WHERE email = $1
+        WHERE lower(email) = lower($1)
         "#,
     )
     .bind(email)
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     Ok(record)
 }
 
-pub async fn find_user_by_id(pool: &PgPool, user_id: Uuid) -> Result<Option<UserRecord>, AppError> {
+/// Case-insensitive lookup through `users.nickname_canonical`.
+pub async fn find_user_by_nickname<'e>(
+    executor: impl PgExecutor<'e>,
+    nickname: &str,
+) -> Result<Option<UserRecord>, AppError> {
     let record = sqlx::query_as::<_, UserRecord>(
         r#"
-        SELECT id, nickname, email, password_hash, is_admin, created_at
+        SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
         FROM users
+        WHERE nickname_canonical = $1
+        "#,
+    )
+    .bind(canonical_nickname(nickname))
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+pub async fn find_user_by_id<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<Option<UserRecord>, AppError> {
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+        FROM users
+        WHERE id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record)
+}
+
+/// Grants or revokes the admin role and revokes the user's tokens when it changes.
+/// Revoking fails with a conflict if the user is the last active admin. With an
+/// `expected_version`, a user changed since then fails with a failed precondition.
+pub async fn set_admin(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    is_admin: bool,
+    expected_version: Option<i64>,
+) -> Result<Option<UserRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+
+    if !is_admin {
+        ensure_not_last_admin(&mut tx, user_id).await?;
+    }
+
+    let was_admin: Option<bool> = sqlx::query_scalar("SELECT is_admin FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        UPDATE users
+        SET is_admin = $2,
+            token_version = token_version + CASE WHEN is_admin = $2 THEN 0 ELSE 1 END,
+            version = version + CASE WHEN is_admin = $2 THEN 0 ELSE 1 END
+        WHERE id = $1 AND ($3::bigint IS NULL OR version = $3)
+        RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(is_admin)
+    .bind(expected_version)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(record) = record.as_ref().filter(|record| was_admin != Some(record.is_admin)) {
+        outbox::append(
+            &mut *tx,
+            &[DomainEvent::UserRoleChanged {
+                user_id: record.id,
+                is_admin: record.is_admin,
+            }],
+        )
+        .await?;
+    }
+
+    let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// Replaces the password hash and revokes every token issued before the change.
+pub async fn update_password_hash(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    password_hash: &str,
+) -> Result<Option<UserRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        UPDATE users
+        SET password_hash = $2, token_version = token_version + 1
         WHERE id = $1
+        RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
         "#,
     )
     .bind(user_id)
-    .fetch_optional(pool)
+    .bind(password_hash)
+    .fetch_optional(&mut *tx)
     .await?;
 
+    if let Some(record) = &record {
+        outbox::append(&mut *tx, &[DomainEvent::UserPasswordChanged { user_id: record.id }]).await?;
+    }
+    tx.commit().await?;
+
     Ok(record)
 }
+
+pub async fn revoke_tokens(conn: &mut PgConnection, user_id: Uuid) -> Result<Option<UserRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        UPDATE users
+        SET token_version = token_version + 1
+        WHERE id = $1
+        RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(record) = &record {
+        outbox::append(&mut *tx, &[DomainEvent::UserTokensRevoked { user_id: record.id }]).await?;
+    }
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+pub async fn revoke_all_tokens(conn: &mut PgConnection) -> Result<u64, AppError> {
+    let mut tx = conn.begin().await?;
+
+    let user_ids: Vec<Uuid> = sqlx::query_scalar("UPDATE users SET token_version = token_version + 1 RETURNING id")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let events: Vec<_> = user_ids
+        .iter()
+        .map(|&user_id| DomainEvent::UserTokensRevoked { user_id })
+        .collect();
+    outbox::append(&mut *tx, &events).await?;
+    tx.commit().await?;
+
+    Ok(user_ids.len() as u64)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    Nickname,
+    Email,
+}
+
+impl UserSortField {
+    /// Valid in PostgreSQL and SQLite alike.
+    pub(crate) fn expression(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::Nickname => "nickname_canonical",
+            Self::Email => "lower(email)",
+        }
+    }
+
+    /// Cursor keys travel as text; this cast turns them back into the column type.
+    fn key_cast(self) -> &'static str {
+        match self {
+            Self::CreatedAt => "::timestamptz",
+            Self::Nickname | Self::Email => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortDirection {
+    pub(crate) fn keyword(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+
+    pub(crate) fn comparison(self) -> &'static str {
+        match self {
+            Self::Asc => ">",
+            Self::Desc => "<",
+        }
+    }
+}
+
+/// Keyset position: the sort key of the last returned row plus its id as a tiebreaker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserCursor {
+    pub sort: UserSortField,
+    pub direction: SortDirection,
+    pub key: String,
+    pub id: Uuid,
+}
+
+#[derive(Debug, Default)]
+pub struct UserListFilter {
+    pub is_admin: Option<bool>,
+    pub suspended: Option<bool>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub search: Option<String>,
+}
+
+pub struct UserListQuery {
+    pub filter: UserListFilter,
+    pub sort: UserSortField,
+    pub direction: SortDirection,
+    pub after: Option<UserCursor>,
+    pub limit: i64,
+}
+
+pub struct UserPage {
+    pub users: Vec<UserRecord>,
+    pub next_cursor: Option<UserCursor>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct UserListRow {
+    #[sqlx(flatten)]
+    pub user: UserRecord,
+    pub sort_key: String,
+}
+
+impl UserPage {
+    /// `rows` are up to `limit + 1` matches in sort order; the extra one only
+    /// tells that another page follows.
+    pub(crate) fn from_rows(
+        mut rows: Vec<UserListRow>,
+        sort: UserSortField,
+        direction: SortDirection,
+        limit: i64,
+    ) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        let next_cursor = rows.last().filter(|_| has_more).map(|row| UserCursor {
+            sort,
+            direction,
+            key: row.sort_key.clone(),
+            id: row.user.id,
+        });
+
+        Self {
+            users: rows.into_iter().map(|row| row.user).collect(),
+            next_cursor,
+        }
+    }
+}
+
+pub async fn list_users(conn: &mut PgConnection, query: UserListQuery) -> Result<UserPage, AppError> {
+    let sort_expression = query.sort.expression();
+
+    let mut builder = QueryBuilder::<Postgres>::new(format!(
+        "SELECT id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at, \
+         {sort_expression}::text AS sort_key FROM users WHERE TRUE"
+    ));
+
+    if let Some(is_admin) = query.filter.is_admin {
+        builder.push(" AND is_admin = ").push_bind(is_admin);
+    }
+
+    match query.filter.suspended {
+        Some(true) => {
+            builder.push(" AND suspended_at IS NOT NULL");
+        }
+        Some(false) => {
+            builder.push(" AND suspended_at IS NULL");
+        }
+        None => {}
+    }
+
+    if let Some(created_after) = query.filter.created_after {
+        builder.push(" AND created_at >= ").push_bind(created_after);
+    }
+
+    if let Some(created_before) = query.filter.created_before {
+        builder.push(" AND created_at < ").push_bind(created_before);
+    }
+
+    if let Some(search) = query.filter.search.as_deref() {
+        let pattern = format!("%{}%", escape_like(&canonical_nickname(search)));
+        builder
+            .push(" AND (nickname_canonical LIKE ")
+            .push_bind(pattern.clone())
+            .push(" OR lower(email) LIKE ")
+            .push_bind(pattern)
+            .push(")");
+    }
+
+    if let Some(cursor) = query.after {
+        builder
+            .push(format!(" AND ({sort_expression}, id) {} (", query.direction.comparison()))
+            .push_bind(cursor.key)
+            .push(query.sort.key_cast())
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+
+    let direction = query.direction.keyword();
+    builder
+        .push(format!(" ORDER BY {sort_expression} {direction}, id {direction} LIMIT "))
+        .push_bind(query.limit + 1);
+
+    let rows: Vec<UserListRow> = builder.build_query_as().fetch_all(conn).await?;
+
+    Ok(UserPage::from_rows(rows, query.sort, query.direction, query.limit))
+}
+
+#[derive(Debug, Default)]
+pub struct UserUpdate {
+    pub nickname: Option<String>,
+    pub email: Option<String>,
+    pub suspended: Option<bool>,
+}
+
+/// Applies the given changes and bumps the version. Suspending an account also
+/// revokes its tokens; suspending the last active admin fails with a conflict. With
+/// an `expected_version`, a user changed since then fails with a failed precondition.
+pub async fn update_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    update: UserUpdate,
+    expected_version: Option<i64>,
+) -> Result<Option<UserRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+
+    if update.suspended == Some(true) {
+        ensure_not_last_admin(&mut tx, user_id).await?;
+    }
+
+    let (nickname_changed, email_changed, suspended) =
+        (update.nickname.is_some(), update.email.is_some(), update.suspended);
+
+    // Every change below starts with a comma.
+    let mut builder = QueryBuilder::<Postgres>::new("UPDATE users SET version = version + 1");
+
+    if let Some(nickname) = update.nickname {
+        let canonical = canonical_nickname(&nickname);
+        let skeleton = nickname_skeleton(&canonical);
+        builder
+            .push(", nickname = ")
+            .push_bind(nickname)
+            .push(", nickname_canonical = ")
+            .push_bind(canonical)
+            .push(", nickname_skeleton = ")
+            .push_bind(skeleton);
+    }
+
+    if let Some(email) = update.email {
+        builder.push(", email = ").push_bind(email);
+    }
+
+    match update.suspended {
+        Some(true) => {
+            builder.push(
+                ", suspended_at = COALESCE(suspended_at, NOW()), \
+                 token_version = token_version + CASE WHEN suspended_at IS NULL THEN 1 ELSE 0 END",
+            );
+        }
+        Some(false) => {
+            builder.push(", suspended_at = NULL");
+        }
+        None => {}
+    }
+
+    builder.push(" WHERE id = ").push_bind(user_id);
+
+    if let Some(expected_version) = expected_version {
+        builder.push(" AND version = ").push_bind(expected_version);
+    }
+
+    builder.push(
+        " RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at",
+    );
+
+    let record = builder
+        .build_query_as::<UserRecord>()
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(map_write_error)?;
+
+    if let Some(record) = &record {
+        outbox::append(
+            &mut *tx,
+            &[DomainEvent::UserUpdated {
+                user_id: record.id,
+                version: record.version,
+                nickname: nickname_changed.then(|| record.nickname.clone()),
+                email: email_changed.then(|| record.email.clone()),
+                suspended,
+            }],
+        )
+        .await?;
+    }
+
+    let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// Deleting the last active admin fails with a conflict. With an `expected_version`,
+/// a user changed since then fails with a failed precondition.
+pub async fn delete_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    expected_version: Option<i64>,
+) -> Result<Option<UserRecord>, AppError> {
+    let mut tx = conn.begin().await?;
+
+    ensure_not_last_admin(&mut tx, user_id).await?;
+
+    let record = sqlx::query_as::<_, UserRecord>(
+        r#"
+        DELETE FROM users
+        WHERE id = $1 AND ($2::bigint IS NULL OR version = $2)
+        RETURNING id, nickname, email, password_hash, is_admin, token_version, version, suspended_at, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(expected_version)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if let Some(record) = &record {
+        outbox::append(&mut *tx, &[DomainEvent::UserDeleted { user_id: record.id }]).await?;
+    }
+
+    let record = unless_stale(&mut tx, user_id, expected_version, record).await?;
+    tx.commit().await?;
+
+    Ok(record)
+}
+
+/// A conditional write that matched no row either hit a missing user (`None`) or
+/// a stale version, which fails with a failed precondition.
+async fn unless_stale(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    expected_version: Option<i64>,
+    record: Option<UserRecord>,
+) -> Result<Option<UserRecord>, AppError> {
+    let (Some(expected_version), None) = (expected_version, &record) else {
+        return Ok(record);
+    };
+
+    let current_version: Option<i64> = sqlx::query_scalar("SELECT version FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(conn)
+        .await?;
+
+    match current_version {
+        Some(current_version) => Err(AppError::PreconditionFailed(format!(
+            "user was modified: version is {current_version}, not {expected_version}"
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Locks every active admin row so concurrent demotions, suspensions and deletions
+/// are serialized, then refuses the change if `user_id` is the only one left.
+async fn ensure_not_last_admin(conn: &mut PgConnection, user_id: Uuid) -> Result<(), AppError> {
+    let active_admins: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id
+        FROM users
+        WHERE is_admin AND suspended_at IS NULL
+        ORDER BY id
+        FOR UPDATE
+        "#,
+    )
+    .fetch_all(conn)
+    .await?;
+
+    if active_admins.len() == 1 && active_admins[0] == user_id {
+        return Err(AppError::Conflict(
+            "cannot remove or suspend the last remaining admin".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
@@ -0,0 +1,183 @@
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::{Delivery, DomainEvent, Envelope, EventBus};
+use crate::{
+    db::{
+        health::DatabaseHealth,
+        notify,
+        outbox::{self, MAX_BROADCAST_BYTES, OUTBOX_CHANNEL, PUBLISHED_CHANNEL},
+    },
+    error::AppError,
+};
+
+/// Fallback for missed notifications, for deferred events and for standby
+/// instances waiting on the dispatch lock.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const ERROR_DELAY: Duration = Duration::from_secs(5);
+
+const RETRY_BASE_SECONDS: i64 = 5;
+const RETRY_MAX_SECONDS: i64 = 600;
+
+/// Starts publishing outbox events to `bus` once the database is ready. Every
+/// instance runs a dispatcher, but the dispatch lock lets only one publish at a
+/// time; each delivered event is then broadcast to every instance's
+/// [`Delivery::EveryInstance`] subscribers.
+pub fn spawn(pool: PgPool, bus: EventBus, health: DatabaseHealth) {
+    let wakeup = Arc::new(Notify::new());
+
+    tokio::spawn({
+        let pool = pool.clone();
+        let wakeup = wakeup.clone();
+        let health = health.clone();
+        async move {
+            health.wait_until_ready().await;
+
+            notify::watch(pool, OUTBOX_CHANNEL, || {
+                wakeup.notify_waiters();
+                async {}
+            })
+            .await;
+        }
+    });
+
+    tokio::spawn({
+        let pool = pool.clone();
+        let bus = bus.clone();
+        let health = health.clone();
+        async move {
+            health.wait_until_ready().await;
+
+            notify::listen(pool, PUBLISHED_CHANNEL, |payload| {
+                let bus = bus.clone();
+                async move {
+                    if let Some(payload) = payload {
+                        publish_to_instance(&bus, &payload).await;
+                    }
+                }
+            })
+            .await;
+        }
+    });
+
+    tokio::spawn(async move {
+        health.wait_until_ready().await;
+        info!("outbox dispatcher started");
+
+        loop {
+            match dispatch_next(&pool, &bus).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    tokio::select! {
+                        _ = wakeup.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+                Err(error) => {
+                    warn!("failed to dispatch outbox events: {error}");
+                    tokio::time::sleep(ERROR_DELAY).await;
+                }
+            }
+        }
+    });
+}
+
+/// Publishes the oldest ready event in a transaction of its own that holds the
+/// dispatch lock, so a slow subscriber only ever pins one event. `false` when no
+/// event is ready or another instance holds the lock. The event is only deleted
+/// when the transaction commits, so a crash midway delivers it again.
+async fn dispatch_next(pool: &PgPool, bus: &EventBus) -> Result<bool, AppError> {
+    let mut tx = pool.begin().await?;
+
+    if !outbox::try_lock_dispatch(&mut tx).await? {
+        return Ok(false);
+    }
+
+    let Some(record) = outbox::fetch_next(&mut tx).await? else {
+        return Ok(false);
+    };
+
+    let result = match DomainEvent::decode(&record) {
+        Ok(event) => {
+            let envelope = Envelope {
+                id: record.id,
+                occurred_at: record.occurred_at,
+                event,
+            };
+            bus.publish(&envelope, Delivery::Once).await.map(|()| envelope)
+        }
+        // Possibly written by a newer build; left for an instance that knows it.
+        Err(error) => Err(error),
+    };
+
+    match result {
+        Ok(envelope) => {
+            outbox::remove(&mut tx, record.id).await?;
+            broadcast(&mut tx, &envelope).await?;
+        }
+        Err(message) => {
+            let attempts = record.attempts + 1;
+            let retry_at = Utc::now() + chrono::Duration::seconds(retry_delay_seconds(attempts));
+            warn!(
+                event_id = record.id,
+                event_type = record.event_type,
+                attempt = attempts,
+                %retry_at,
+                "event delivery failed, will retry: {message}"
+            );
+
+            outbox::defer(&mut tx, record.id, &message, retry_at).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Queues the event for every instance; PostgreSQL sends it when the deletion commits.
+async fn broadcast(conn: &mut PgConnection, envelope: &Envelope) -> Result<(), AppError> {
+    let payload = serde_json::to_string(envelope)
+        .map_err(|error| AppError::Internal(format!("failed to encode domain event: {error}")))?;
+
+    if payload.len() > MAX_BROADCAST_BYTES {
+        warn!(
+            event_id = envelope.id,
+            bytes = payload.len(),
+            "event is too large to broadcast; other instances will not see it"
+        );
+        return Ok(());
+    }
+
+    outbox::broadcast(conn, &payload).await
+}
+
+async fn publish_to_instance(bus: &EventBus, payload: &str) {
+    let envelope = match serde_json::from_str::<Envelope>(payload) {
+        Ok(envelope) => envelope,
+        Err(error) => {
+            warn!("cannot decode broadcast event: {error}");
+            return;
+        }
+    };
+
+    if let Err(message) = bus.publish(&envelope, Delivery::EveryInstance).await {
+        warn!(
+            event_id = envelope.id,
+            event_type = envelope.event.event_type(),
+            "event handling failed on this instance: {message}"
+        );
+    }
+}
+
+/// 5s, 10s, 20s, ... capped at ten minutes. Events are never dropped.
+fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+
+    RETRY_BASE_SECONDS
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_SECONDS)
+}
@@ -0,0 +1,164 @@
+//! Domain events about users, appended to the `outbox` table (see `db::outbox`) in
+//! the same transaction as the change and published to the subscribers on the
+//! `EventBus` by the `dispatcher`. Delivery is at least once and in order per
+//! aggregate, so subscribers must tolerate seeing an event again.
+//!
+//! Subscribers are either handled once per deployment, by whichever instance
+//! dispatches the event, or on every instance, for per-process state such as
+//! caches. The latter get each event over a notification once it was delivered.
+
+pub mod dispatcher;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+use crate::{db::outbox::OutboxRecord, error::AppError};
+
+/// A slow subscriber fails the delivery instead of stalling the dispatcher.
+const HANDLER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stored as `event_type` plus the variant's fields as `payload`. Renaming a
+/// variant or a field strands the events not yet delivered under the old shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event_type", content = "payload", rename_all = "snake_case")]
+// Users are the only aggregate so far; the prefix keeps event types unambiguous.
+#[allow(clippy::enum_variant_names)]
+pub enum DomainEvent {
+    UserCreated {
+        user_id: Uuid,
+        nickname: String,
+        email: String,
+        is_admin: bool,
+    },
+    /// Carries only the fields the update set.
+    UserUpdated {
+        user_id: Uuid,
+        version: i64,
+        nickname: Option<String>,
+        email: Option<String>,
+        suspended: Option<bool>,
+    },
+    /// Only emitted when the role actually changed.
+    UserRoleChanged { user_id: Uuid, is_admin: bool },
+    UserPasswordChanged { user_id: Uuid },
+    UserTokensRevoked { user_id: Uuid },
+    UserDeleted { user_id: Uuid },
+}
+
+impl DomainEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            Self::UserCreated { .. } => "user_created",
+            Self::UserUpdated { .. } => "user_updated",
+            Self::UserRoleChanged { .. } => "user_role_changed",
+            Self::UserPasswordChanged { .. } => "user_password_changed",
+            Self::UserTokensRevoked { .. } => "user_tokens_revoked",
+            Self::UserDeleted { .. } => "user_deleted",
+        }
+    }
+
+    /// The entity whose events must be delivered in the order they were appended.
+    pub fn aggregate(&self) -> (&'static str, Uuid) {
+        match self {
+            Self::UserCreated { user_id, .. }
+            | Self::UserUpdated { user_id, .. }
+            | Self::UserRoleChanged { user_id, .. }
+            | Self::UserPasswordChanged { user_id }
+            | Self::UserTokensRevoked { user_id }
+            | Self::UserDeleted { user_id } => ("user", *user_id),
+        }
+    }
+
+    pub fn payload(&self) -> Result<serde_json::Value, AppError> {
+        let mut value = serde_json::to_value(self)
+            .map_err(|error| AppError::Internal(format!("failed to encode domain event: {error}")))?;
+
+        Ok(value
+            .get_mut("payload")
+            .map(serde_json::Value::take)
+            .unwrap_or_default())
+    }
+
+    /// Fails for event types or payloads this build does not understand.
+    pub fn decode(record: &OutboxRecord) -> Result<Self, String> {
+        let value = serde_json::json!({ "event_type": record.event_type, "payload": record.payload });
+
+        serde_json::from_value(value).map_err(|error| format!("cannot decode event: {error}"))
+    }
+}
+
+/// An event as handed to subscribers. `id` is the outbox row, stable across
+/// redeliveries, so subscribers can use it to skip duplicates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    // Not read by the built-in subscribers yet.
+    #[allow(dead_code)]
+    pub id: i64,
+    #[allow(dead_code)]
+    pub occurred_at: DateTime<Utc>,
+    pub event: DomainEvent,
+}
+
+/// Where a subscriber runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Once per deployment, on whichever instance dispatches the event, retried
+    /// until it succeeds.
+    Once,
+    /// On every instance, at most once: errors are only logged, and events sent
+    /// while an instance was disconnected from the database are not replayed.
+    EveryInstance,
+}
+
+#[async_trait]
+pub trait EventHandler: Send + Sync {
+    /// Names the subscriber in logs and in the outbox's `last_error`.
+    fn name(&self) -> &'static str;
+
+    fn delivery(&self) -> Delivery {
+        Delivery::Once
+    }
+
+    /// An error leaves a [`Delivery::Once`] event in the outbox to be delivered
+    /// again, to every such subscriber.
+    async fn handle(&self, envelope: &Envelope) -> Result<(), AppError>;
+}
+
+/// In-process subscribers, registered at startup before the dispatcher runs.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    handlers: Vec<Arc<dyn EventHandler>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, handler: Arc<dyn EventHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Hands the event to the subscribers with `delivery`, even after one failed;
+    /// the errors are joined so the stored `last_error` names each failing subscriber.
+    pub async fn publish(&self, envelope: &Envelope, delivery: Delivery) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        for handler in self.handlers.iter().filter(|handler| handler.delivery() == delivery) {
+            match tokio::time::timeout(HANDLER_TIMEOUT, handler.handle(envelope)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(error)) => errors.push(format!("{}: {error}", handler.name())),
+                Err(_) => errors.push(format!(
+                    "{}: timed out after {}s",
+                    handler.name(),
+                    HANDLER_TIMEOUT.as_secs()
+                )),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Feature flags: an in-process snapshot of the `feature_flags` tables that is
+//! reloaded whenever Postgres notifies `FEATURE_FLAGS_CHANNEL`.
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        feature_flags::{self, FeatureFlagRecord, FEATURE_FLAGS_CHANNEL},
+        notify,
+    },
+    error::AppError,
+};
+
+#[derive(Debug)]
+struct FlagRule {
+    enabled: bool,
+    rollout_percentage: u8,
+    user_ids: HashSet<Uuid>,
+}
+
+impl FlagRule {
+    /// On for everyone when enabled, otherwise for targeted users and a stable
+    /// `rollout_percentage` slice of everybody else.
+    fn is_enabled_for(&self, key: &str, user_id: Uuid) -> bool {
+        self.enabled
+            || self.user_ids.contains(&user_id)
+            || rollout_bucket(key, user_id) < self.rollout_percentage
+    }
+}
+
+impl From<FeatureFlagRecord> for FlagRule {
+    fn from(value: FeatureFlagRecord) -> Self {
+        Self {
+            enabled: value.enabled,
+            rollout_percentage: value.rollout_percentage.clamp(0, 100) as u8,
+            user_ids: value.user_ids.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FeatureFlags {
+    rules: Arc<RwLock<Arc<HashMap<String, FlagRule>>>>,
+}
+
+impl FeatureFlags {
+    /// Replaces the snapshot with the current database contents.
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), AppError> {
+        let records = feature_flags::list_flags(pool).await?;
+        let rules = records
+            .into_iter()
+            .map(|record| (record.key.clone(), FlagRule::from(record)))
+            .collect();
+
+        *self.rules.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(rules);
+
+        Ok(())
+    }
+
+    /// Every known flag evaluated for one user.
+    pub fn evaluate_all(&self, user_id: Uuid) -> BTreeMap<String, bool> {
+        let rules = self.snapshot();
+
+        rules
+            .iter()
+            .map(|(key, rule)| (key.clone(), rule.is_enabled_for(key, user_id)))
+            .collect()
+    }
+
+    fn snapshot(&self) -> Arc<HashMap<String, FlagRule>> {
+        self.rules
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Keeps the snapshot fresh for the lifetime of the process.
+    pub async fn listen_for_changes(self, pool: PgPool) {
+        notify::watch(pool.clone(), FEATURE_FLAGS_CHANNEL, || self.reload_logged(&pool)).await;
+    }
+
+    async fn reload_logged(&self, pool: &PgPool) {
+        if let Err(error) = self.reload(pool).await {
+            warn!("failed to reload feature flags: {error}");
+        }
+    }
+}
+
+/// Stable 0..100 bucket per flag and user, so raising the percentage only adds users.
+fn rollout_bucket(key: &str, user_id: Uuid) -> u8 {
+    let digest = Sha256::new()
+        .chain_update(key.as_bytes())
+        .chain_update(b":")
+        .chain_update(user_id.as_bytes())
+        .finalize();
+
+    let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    (value % 100) as u8
+}
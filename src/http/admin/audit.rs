@@ -0,0 +1,162 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    db::{
+        audit::{AuditEventFilter, ChainVerification},
+        store::Store,
+    },
+    error::AppError,
+    models::{AuditEventListResponse, AuditEventResponse},
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 500;
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditFilterParams {
+    #[serde(default)]
+    pub event_type: Option<String>,
+    #[serde(default)]
+    pub actor_id: Option<Uuid>,
+    #[serde(default)]
+    pub target_id: Option<Uuid>,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl From<AuditFilterParams> for AuditEventFilter {
+    fn from(value: AuditFilterParams) -> Self {
+        Self {
+            event_type: value.event_type.filter(|event_type| !event_type.is_empty()),
+            actor_id: value.actor_id,
+            target_id: value.target_id,
+            from: value.from,
+            to: value.to,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListAuditParams {
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page: only events with a smaller id are returned.
+    #[serde(default)]
+    pub cursor: Option<i64>,
+    #[serde(flatten)]
+    pub filter: AuditFilterParams,
+}
+
+pub async fn list(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<ListAuditParams>,
+) -> Result<Json<AuditEventListResponse>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+
+    let filter = AuditEventFilter::from(params.filter);
+    let mut events = state
+        .store
+        .read_session(admin.id)
+        .await?
+        .list_events(&filter, params.cursor, limit + 1)
+        .await?;
+
+    let has_more = events.len() as i64 > limit;
+    events.truncate(limit as usize);
+    let next_cursor = events.last().filter(|_| has_more).map(|event| event.id);
+
+    Ok(Json(AuditEventListResponse {
+        events: events.into_iter().map(AuditEventResponse::from).collect(),
+        next_cursor,
+    }))
+}
+
+/// Streams every matching event as JSON lines, newest first, fetching in batches
+/// so large exports never hold a connection for the whole download.
+pub async fn export(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<AuditFilterParams>,
+) -> Result<Response, AppError> {
+    let filter = AuditEventFilter::from(params);
+    let store = state.store.clone();
+
+    let batches = stream::unfold(Some(None), move |cursor: Option<Option<i64>>| {
+        let store = store.clone();
+        let filter = filter.clone();
+        async move {
+            let before_id = cursor?;
+            match export_batch(store.as_ref(), admin.id, &filter, before_id).await {
+                Ok((chunk, next)) if !chunk.is_empty() => Some((Ok(chunk), next.map(Some))),
+                Ok(_) => None,
+                Err(error) => Some((Err(std::io::Error::other(error.to_string())), None)),
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/x-ndjson"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"audit-events.jsonl\""),
+        ],
+        Body::from_stream(batches),
+    )
+        .into_response())
+}
+
+pub async fn verify(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<ChainVerification>, AppError> {
+    let verification = state.store.read_session(admin.id).await?.verify_chain().await?;
+
+    Ok(Json(verification))
+}
+
+/// One export batch serialized as JSON lines, plus the cursor for the next one.
+async fn export_batch(
+    store: &dyn Store,
+    reader: Uuid,
+    filter: &AuditEventFilter,
+    before_id: Option<i64>,
+) -> Result<(Vec<u8>, Option<i64>), AppError> {
+    let events = store
+        .read_session(reader)
+        .await?
+        .list_events(filter, before_id, EXPORT_BATCH_SIZE)
+        .await?;
+
+    let next = (events.len() as i64 == EXPORT_BATCH_SIZE)
+        .then(|| events.last().map(|event| event.id))
+        .flatten();
+
+    let mut chunk = Vec::new();
+    for event in events {
+        serde_json::to_writer(&mut chunk, &AuditEventResponse::from(event))
+            .map_err(|error| AppError::Internal(format!("failed to serialize audit event: {error}")))?;
+        chunk.push(b'\n');
+    }
+
+    Ok((chunk, next))
+}
@@ -0,0 +1,211 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    db::{
+        audit::{self, AuditEventType},
+        feature_flags::{self, FeatureFlagUpdate, NewFeatureFlag},
+    },
+    error::AppError,
+    http::context::RequestContext,
+    models::FeatureFlagResponse,
+};
+
+const MAX_KEY_LEN: usize = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFeatureFlagRequest {
+    pub key: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub rollout_percentage: i16,
+    #[serde(default)]
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateFeatureFlagRequest {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub rollout_percentage: Option<i16>,
+    #[serde(default)]
+    pub user_ids: Option<Vec<Uuid>>,
+}
+
+pub async fn list(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<FeatureFlagResponse>>, AppError> {
+    let records = feature_flags::list_flags(state.pg_read(admin.id)?).await?;
+
+    Ok(Json(records.into_iter().map(FeatureFlagResponse::from).collect()))
+}
+
+pub async fn get(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<FeatureFlagResponse>, AppError> {
+    let record = feature_flags::find_flag(state.pg_read(admin.id)?, &key)
+        .await?
+        .ok_or_else(|| AppError::NotFound("feature flag not found".to_string()))?;
+
+    Ok(Json(FeatureFlagResponse::from(record)))
+}
+
+pub async fn create(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<CreateFeatureFlagRequest>,
+) -> Result<Json<FeatureFlagResponse>, AppError> {
+    let key = validate_key(&payload.key)?;
+    validate_rollout_percentage(payload.rollout_percentage)?;
+
+    let mut tx = state.pg()?.begin().await?;
+
+    let record = feature_flags::create_flag(
+        &mut tx,
+        NewFeatureFlag {
+            key,
+            description: payload.description.trim().to_string(),
+            enabled: payload.enabled,
+            rollout_percentage: payload.rollout_percentage,
+            user_ids: payload.user_ids,
+        },
+    )
+    .await?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::FeatureFlagCreated)
+            .actor(admin.id)
+            .payload(flag_payload(&record)),
+    )
+    .await?;
+
+    tx.commit().await?;
+    state.features.reload(state.pg()?).await?;
+
+    Ok(Json(FeatureFlagResponse::from(record)))
+}
+
+pub async fn update(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(key): Path<String>,
+    Json(payload): Json<UpdateFeatureFlagRequest>,
+) -> Result<Json<FeatureFlagResponse>, AppError> {
+    if let Some(rollout_percentage) = payload.rollout_percentage {
+        validate_rollout_percentage(rollout_percentage)?;
+    }
+
+    let mut tx = state.pg()?.begin().await?;
+
+    let record = feature_flags::update_flag(
+        &mut tx,
+        &key,
+        FeatureFlagUpdate {
+            description: payload.description.map(|description| description.trim().to_string()),
+            enabled: payload.enabled,
+            rollout_percentage: payload.rollout_percentage,
+            user_ids: payload.user_ids,
+        },
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound("feature flag not found".to_string()))?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::FeatureFlagUpdated)
+            .actor(admin.id)
+            .payload(flag_payload(&record)),
+    )
+    .await?;
+
+    tx.commit().await?;
+    state.features.reload(state.pg()?).await?;
+
+    Ok(Json(FeatureFlagResponse::from(record)))
+}
+
+pub async fn delete(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(key): Path<String>,
+) -> Result<Json<FeatureFlagResponse>, AppError> {
+    let mut tx = state.pg()?.begin().await?;
+
+    let record = feature_flags::find_flag(&mut *tx, &key)
+        .await?
+        .ok_or_else(|| AppError::NotFound("feature flag not found".to_string()))?;
+    feature_flags::delete_flag(&mut *tx, &key).await?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::FeatureFlagDeleted)
+            .actor(admin.id)
+            .payload(json!({ "key": record.key })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    state.features.reload(state.pg()?).await?;
+
+    Ok(Json(FeatureFlagResponse::from(record)))
+}
+
+/// Lowercase ASCII letters, digits, `_`, `-` and `.`, starting with a letter, e.g. `workspace.beta`.
+fn validate_key(value: &str) -> Result<String, AppError> {
+    let key = value.trim();
+
+    let valid = key.len() <= MAX_KEY_LEN
+        && key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "key must start with a lowercase letter and contain at most {MAX_KEY_LEN} lowercase letters, digits, '_', '-' or '.'"
+        )));
+    }
+
+    Ok(key.to_string())
+}
+
+fn validate_rollout_percentage(value: i16) -> Result<(), AppError> {
+    if !(0..=100).contains(&value) {
+        return Err(AppError::BadRequest(
+            "rollout_percentage must be between 0 and 100".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn flag_payload(record: &feature_flags::FeatureFlagRecord) -> serde_json::Value {
+    json!({
+        "key": record.key,
+        "enabled": record.enabled,
+        "rollout_percentage": record.rollout_percentage,
+        "user_ids": record.user_ids,
+    })
+}
@@ -0,0 +1,127 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    db::{
+        audit::AuditEventType,
+        invites::{self, NewInvite},
+    },
+    error::AppError,
+    http::context::RequestContext,
+    models::InviteResponse,
+    validation::normalize_and_validate_email,
+};
+
+const MAX_INVITE_USES: i32 = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateInviteRequest {
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub max_uses: Option<i32>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn create(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<CreateInviteRequest>,
+) -> Result<Json<InviteResponse>, AppError> {
+    let max_uses = payload.max_uses.unwrap_or(1);
+    if !(1..=MAX_INVITE_USES).contains(&max_uses) {
+        return Err(AppError::BadRequest(format!(
+            "max_uses must be between 1 and {MAX_INVITE_USES}"
+        )));
+    }
+
+    if payload.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(AppError::BadRequest(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let email = payload
+        .email
+        .as_deref()
+        .map(normalize_and_validate_email)
+        .transpose()?;
+
+    let mut tx = state.store.begin().await?;
+
+    let invite = tx
+        .create_invite(NewInvite {
+            code: invites::generate_invite_code(),
+            email,
+            is_admin: payload.is_admin,
+            max_uses,
+            expires_at: payload.expires_at,
+            created_by: Some(admin.id),
+        })
+        .await?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::InviteCreated)
+            .actor(admin.id)
+            .payload(json!({
+                "invite_id": invite.id,
+                "email": invite.email,
+                "is_admin": invite.is_admin,
+                "max_uses": invite.max_uses,
+                "expires_at": invite.expires_at,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(InviteResponse::from(invite)))
+}
+
+pub async fn list(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<InviteResponse>>, AppError> {
+    let records = state.store.read_session(admin.id).await?.list_invites().await?;
+
+    Ok(Json(records.into_iter().map(InviteResponse::from).collect()))
+}
+
+pub async fn revoke(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(invite_id): Path<Uuid>,
+) -> Result<Json<InviteResponse>, AppError> {
+    let mut tx = state.store.begin().await?;
+
+    let invite = tx
+        .revoke_invite(invite_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("invite not found".to_string()))?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::InviteRevoked)
+            .actor(admin.id)
+            .payload(json!({ "invite_id": invite.id })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(InviteResponse::from(invite)))
+}
@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    db::{
+        audit::{self, AuditEventType},
+        jobs::{self, JobFilter, JobStatus},
+    },
+    error::AppError,
+    http::context::RequestContext,
+    models::{JobListResponse, JobResponse},
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct ListJobsParams {
+    #[serde(default)]
+    pub status: Option<JobStatus>,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page: only jobs with a smaller id are returned.
+    #[serde(default)]
+    pub cursor: Option<i64>,
+}
+
+/// Queued, running and dead jobs, newest first. Succeeded jobs are deleted.
+pub async fn list(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<ListJobsParams>,
+) -> Result<Json<JobListResponse>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+
+    let filter = JobFilter {
+        status: params.status,
+        kind: params.kind.filter(|kind| !kind.is_empty()),
+    };
+    let mut records = jobs::list_jobs(state.pg_read(admin.id)?, &filter, params.cursor, limit + 1).await?;
+
+    let has_more = records.len() as i64 > limit;
+    records.truncate(limit as usize);
+    let next_cursor = records.last().filter(|_| has_more).map(|job| job.id);
+
+    Ok(Json(JobListResponse {
+        jobs: records.into_iter().map(JobResponse::from).collect(),
+        next_cursor,
+    }))
+}
+
+pub async fn get(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Path(job_id): Path<i64>,
+) -> Result<Json<JobResponse>, AppError> {
+    let job = jobs::find_job(state.pg_read(admin.id)?, job_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("job not found".to_string()))?;
+
+    Ok(Json(JobResponse::from(job)))
+}
+
+/// Requeues a dead job with a fresh set of attempts.
+pub async fn retry(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(job_id): Path<i64>,
+) -> Result<Json<JobResponse>, AppError> {
+    let mut tx = state.pg()?.begin().await?;
+
+    let Some(job) = jobs::retry_dead_job(&mut tx, job_id).await? else {
+        return Err(match jobs::find_job(&mut *tx, job_id).await? {
+            Some(job) => AppError::Conflict(format!(
+                "job is {}; only dead jobs can be retried",
+                job.status
+            )),
+            None => AppError::NotFound("job not found".to_string()),
+        });
+    };
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::JobRetried)
+            .actor(admin.id)
+            .payload(json!({
+                "job_id": job.id,
+                "kind": job.kind,
+                "last_error": job.last_error,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(JobResponse::from(job)))
+}
@@ -1,3 +1,11 @@
+pub mod audit;
+pub mod features;
+pub mod invites;
+pub mod jobs;
+pub mod settings;
+pub mod stats;
+pub mod users;
+
 use axum::{extract::State, Json};
 use serde::Serialize;
 
@@ -18,7 +26,7 @@ pub async fn ping(
     Json(AdminPingResponse {
         status: "ok",
         admin_id: admin.id.to_string(),
-        nickname: admin.nickname,
-        email: admin.email,
+        nickname: admin.user.nickname,
+        email: admin.user.email,
     })
 }
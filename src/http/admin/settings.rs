@@ -0,0 +1,145 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    db::{
+        audit::{self, AuditEventType},
+        settings,
+    },
+    error::AppError,
+    http::context::RequestContext,
+    models::{SettingChangeListResponse, SettingChangeResponse, SettingResponse},
+    settings::{RuntimeSettings, SettingKey},
+};
+
+const DEFAULT_HISTORY_PAGE_SIZE: i64 = 50;
+const MAX_HISTORY_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Deserialize)]
+pub struct SettingsHistoryParams {
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub cursor: Option<i64>,
+}
+
+pub async fn list(
+    AdminUser(_admin): AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<SettingResponse>>, AppError> {
+    Ok(Json(setting_responses(&state).await?))
+}
+
+/// Sets several settings at once: `{"registration_mode": "closed", "jwt_ttl_seconds": null}`.
+/// `null` removes the override so the env default applies again. Either every
+/// value is valid and applied, or nothing changes.
+pub async fn update(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<Map<String, Value>>,
+) -> Result<Json<Vec<SettingResponse>>, AppError> {
+    if payload.is_empty() {
+        return Err(AppError::BadRequest("no settings given".to_string()));
+    }
+
+    // Values are validated against a copy of the current settings, exactly as
+    // `Settings::reload` will read them back, and stored in normalized form.
+    let mut candidate = RuntimeSettings::clone(&state.settings.current());
+    let mut updates = BTreeMap::new();
+    for (name, value) in payload {
+        let key = SettingKey::parse(&name)
+            .ok_or_else(|| AppError::BadRequest(format!("unknown setting '{name}'")))?;
+
+        let stored = match value {
+            Value::Null => None,
+            value => {
+                candidate.apply(key, &value)?;
+                Some(candidate.value(key))
+            }
+        };
+        updates.insert(key.as_str(), stored);
+    }
+
+    let mut tx = state.pg()?.begin().await?;
+
+    let mut changes = Map::new();
+    for (key, value) in &updates {
+        if settings::set_setting(&mut tx, key, value.as_ref(), Some(admin.id)).await? {
+            changes.insert(key.to_string(), value.clone().unwrap_or(Value::Null));
+        }
+    }
+
+    if !changes.is_empty() {
+        audit::record_event(
+            &mut tx,
+            context
+                .event(AuditEventType::SettingsUpdated)
+                .actor(admin.id)
+                .payload(json!({ "changes": changes })),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+    state.settings.reload(state.pg()?).await?;
+
+    Ok(Json(setting_responses(&state).await?))
+}
+
+pub async fn history(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<SettingsHistoryParams>,
+) -> Result<Json<SettingChangeListResponse>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+    if !(1..=MAX_HISTORY_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {MAX_HISTORY_PAGE_SIZE}"
+        )));
+    }
+
+    let key = params.key.as_deref().filter(|key| !key.is_empty());
+    let mut changes =
+        settings::list_history(state.pg_read(admin.id)?, key, params.cursor, limit + 1).await?;
+
+    let has_more = changes.len() as i64 > limit;
+    changes.truncate(limit as usize);
+    let next_cursor = changes.last().filter(|_| has_more).map(|change| change.id);
+
+    Ok(Json(SettingChangeListResponse {
+        changes: changes.into_iter().map(SettingChangeResponse::from).collect(),
+        next_cursor,
+    }))
+}
+
+async fn setting_responses(state: &AppState) -> Result<Vec<SettingResponse>, AppError> {
+    let stored = settings::list_settings(state.pg()?).await?;
+    let current = state.settings.current();
+    let defaults = state.settings.defaults();
+
+    Ok(SettingKey::ALL
+        .into_iter()
+        .map(|key| {
+            let record = stored.iter().find(|record| record.key == key.as_str());
+
+            SettingResponse {
+                key: key.as_str(),
+                value: current.value(key),
+                default: defaults.value(key),
+                overridden: record.is_some(),
+                updated_by: record.and_then(|record| record.updated_by),
+                updated_at: record.map(|record| record.updated_at),
+            }
+        })
+        .collect())
+}
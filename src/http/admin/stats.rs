@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::time::Duration as StdDuration;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    cache::TtlCache,
+    db::stats::{self, StatsBucket},
+    error::AppError,
+    events::{Delivery, DomainEvent, Envelope, EventHandler},
+    models::{AdminStatsResponse, LoginStatsResponse, RegistrationBucketResponse, UserTotalsResponse},
+};
+
+/// Long enough to absorb dashboard refreshes, short enough to look live.
+const STATS_CACHE_TTL: StdDuration = StdDuration::from_secs(30);
+
+const MAX_BUCKETS: i64 = 400;
+
+/// Keyed by the raw query so requests without `to` ("up to now") share an entry.
+pub type StatsCache = TtlCache<StatsParams, AdminStatsResponse>;
+
+pub fn new_stats_cache() -> StatsCache {
+    TtlCache::new(STATS_CACHE_TTL)
+}
+
+/// Drops cached stats when the user totals change, on every instance. An event
+/// missed while disconnected is covered by the TTL.
+#[async_trait]
+impl EventHandler for StatsCache {
+    fn name(&self) -> &'static str {
+        "stats_cache"
+    }
+
+    fn delivery(&self) -> Delivery {
+        Delivery::EveryInstance
+    }
+
+    async fn handle(&self, envelope: &Envelope) -> Result<(), AppError> {
+        match envelope.event {
+            DomainEvent::UserCreated { .. }
+            | DomainEvent::UserRoleChanged { .. }
+            | DomainEvent::UserDeleted { .. }
+            | DomainEvent::UserUpdated { suspended: Some(_), .. } => self.clear(),
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct StatsParams {
+    #[serde(default)]
+    pub bucket: Option<StatsBucket>,
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+pub async fn stats(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<StatsParams>,
+) -> Result<Json<AdminStatsResponse>, AppError> {
+    if let Some(cached) = state.stats_cache.get(&params) {
+        return Ok(Json(cached));
+    }
+
+    let bucket = params.bucket.unwrap_or(StatsBucket::Day);
+    let bucket_length = match bucket {
+        StatsBucket::Day => Duration::days(1),
+        StatsBucket::Week => Duration::weeks(1),
+    };
+
+    let to = params.to.unwrap_or_else(Utc::now);
+    let from = params.from.unwrap_or(to - bucket_length * 30);
+    if from >= to {
+        return Err(AppError::BadRequest("from must be before to".to_string()));
+    }
+    if (to - from).num_seconds() / bucket_length.num_seconds() > MAX_BUCKETS {
+        return Err(AppError::BadRequest(format!(
+            "range covers more than {MAX_BUCKETS} buckets; use a larger bucket or a shorter range"
+        )));
+    }
+
+    let pool = state.pg_read(admin.id)?;
+    let (users, registrations, logins, active_sessions) = tokio::try_join!(
+        stats::user_totals(pool),
+        stats::registrations_per_bucket(pool, bucket, from, to),
+        stats::logins_per_bucket(pool, bucket, from, to),
+        stats::active_sessions(pool, state.settings.current().jwt_ttl_seconds),
+    )?;
+
+    let response = AdminStatsResponse {
+        generated_at: Utc::now(),
+        from,
+        to,
+        bucket,
+        users: UserTotalsResponse::from(users),
+        active_sessions,
+        registrations: registrations
+            .into_iter()
+            .map(RegistrationBucketResponse::from)
+            .collect(),
+        logins: LoginStatsResponse::from(logins),
+    };
+
+    state.stats_cache.insert(params, response.clone());
+
+    Ok(Json(response))
+}
@@ -0,0 +1,405 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::AdminUser,
+    bulk::{
+        export::export_users,
+        import::{self, ImportNotification, ImportOptions, ImportReport},
+        BulkFormat,
+    },
+    db::{
+        audit::AuditEventType,
+        users::{
+            SortDirection, UserCursor, UserListFilter, UserListQuery, UserSortField, UserUpdate,
+        },
+    },
+    error::AppError,
+    http::{context::RequestContext, etag::{IfMatch, Versioned}},
+    models::{AdminUserListResponse, AdminUserResponse},
+    validation::{normalize_and_validate_email, validate_nickname},
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleFilter {
+    Admin,
+    User,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListUsersParams {
+    #[serde(default)]
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: UserSortField,
+    #[serde(default)]
+    pub direction: SortDirection,
+    #[serde(default)]
+    pub role: Option<RoleFilter>,
+    #[serde(default)]
+    pub suspended: Option<bool>,
+    #[serde(default)]
+    pub created_after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub q: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportUsersParams {
+    /// Defaults to the request's `Content-Type` (`text/csv` or `application/x-ndjson`).
+    #[serde(default)]
+    pub format: Option<BulkFormat>,
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub notify: ImportNotification,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportUsersParams {
+    #[serde(default)]
+    pub format: Option<BulkFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub suspended: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleAction {
+    Grant,
+    Revoke,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeRoleRequest {
+    pub role: Role,
+    pub action: RoleAction,
+    /// Must be `true` when admins revoke their own admin role.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+pub async fn list(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<ListUsersParams>,
+) -> Result<Json<AdminUserListResponse>, AppError> {
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::BadRequest(format!(
+            "limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+
+    let after = params.cursor.as_deref().map(decode_cursor).transpose()?;
+    let cursor_matches_sort = after
+        .as_ref()
+        .is_none_or(|cursor| cursor.sort == params.sort && cursor.direction == params.direction);
+    if !cursor_matches_sort {
+        return Err(AppError::BadRequest(
+            "cursor was issued for a different sort order".to_string(),
+        ));
+    }
+
+    let search = params
+        .q
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .map(str::to_string);
+
+    let page = state
+        .store
+        .read_session(admin.id)
+        .await?
+        .list_users(UserListQuery {
+            filter: UserListFilter {
+                is_admin: params.role.map(|role| matches!(role, RoleFilter::Admin)),
+                suspended: params.suspended,
+                created_after: params.created_after,
+                created_before: params.created_before,
+                search,
+            },
+            sort: params.sort,
+            direction: params.direction,
+            after,
+            limit,
+        })
+        .await?;
+
+    Ok(Json(AdminUserListResponse {
+        users: page.users.into_iter().map(AdminUserResponse::from).collect(),
+        next_cursor: page.next_cursor.as_ref().map(encode_cursor).transpose()?,
+    }))
+}
+
+pub async fn get(
+    AdminUser(_admin): AdminUser,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Versioned<AdminUserResponse>, AppError> {
+    // The ETag is sent back as If-Match, so it comes from the primary: a lagging
+    // replica would hand out a version the next update rejects.
+    let user = state
+        .store
+        .session()
+        .await?
+        .find_user_by_id(user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user not found".to_string()))?;
+
+    Ok(Versioned(user.version, AdminUserResponse::from(user)))
+}
+
+pub async fn update(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(user_id): Path<Uuid>,
+    if_match: IfMatch,
+    Json(payload): Json<UpdateUserRequest>,
+) -> Result<Versioned<AdminUserResponse>, AppError> {
+    let expected_version = if_match.required()?;
+
+    if payload.suspended == Some(true) && user_id == admin.id {
+        return Err(AppError::BadRequest(
+            "admins cannot suspend their own account".to_string(),
+        ));
+    }
+
+    let update = UserUpdate {
+        nickname: payload.nickname.as_deref().map(validate_nickname).transpose()?,
+        email: payload
+            .email
+            .as_deref()
+            .map(normalize_and_validate_email)
+            .transpose()?,
+        suspended: payload.suspended,
+    };
+
+    let changes = json!({
+        "nickname": update.nickname,
+        "email": update.email,
+        "suspended": update.suspended,
+    });
+
+    let mut tx = state.store.begin().await?;
+
+    let user = tx
+        .update_user(user_id, update, expected_version)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user not found".to_string()))?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::UserUpdated)
+            .actor(admin.id)
+            .target(user.id)
+            .payload(json!({ "changes": changes })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    // The user's next token check must not see the old row on a lagging replica.
+    state.record_write(user_id);
+
+    Ok(Versioned(user.version, AdminUserResponse::from(user)))
+}
+
+pub async fn delete(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(user_id): Path<Uuid>,
+    if_match: IfMatch,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let expected_version = if_match.optional()?;
+
+    if user_id == admin.id {
+        return Err(AppError::BadRequest(
+            "admins cannot delete their own account".to_string(),
+        ));
+    }
+
+    let mut tx = state.store.begin().await?;
+
+    let user = tx
+        .delete_user(user_id, expected_version)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user not found".to_string()))?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::UserDeleted)
+            .actor(admin.id)
+            .target(user.id)
+            .payload(json!({
+                "nickname": user.nickname,
+                "email": user.email,
+                "is_admin": user.is_admin,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    state.record_write(user_id);
+
+    Ok(Json(AdminUserResponse::from(user)))
+}
+
+/// Grants or revokes a role. The target's existing tokens stop working immediately
+/// because `set_admin` bumps their token version.
+pub async fn change_role(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Path(user_id): Path<Uuid>,
+    if_match: IfMatch,
+    Json(payload): Json<ChangeRoleRequest>,
+) -> Result<Versioned<AdminUserResponse>, AppError> {
+    let expected_version = if_match.required()?;
+    let Role::Admin = payload.role;
+    let is_admin = matches!(payload.action, RoleAction::Grant);
+
+    if !is_admin && user_id == admin.id && !payload.confirm {
+        return Err(AppError::BadRequest(
+            "revoking your own admin role requires \"confirm\": true".to_string(),
+        ));
+    }
+
+    let mut tx = state.store.begin().await?;
+
+    let user = tx
+        .set_admin(user_id, is_admin, expected_version)
+        .await?
+        .ok_or_else(|| AppError::NotFound("user not found".to_string()))?;
+
+    let event_type = if is_admin {
+        AuditEventType::RoleGranted
+    } else {
+        AuditEventType::RoleRevoked
+    };
+    tx.record_event(
+        context
+            .event(event_type)
+            .actor(admin.id)
+            .target(user.id)
+            .payload(json!({ "role": "admin" })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    state.record_write(user_id);
+
+    Ok(Versioned(user.version, AdminUserResponse::from(user)))
+}
+
+/// Imports users from a CSV or JSONL request body. See `bulk::import` for the rules.
+pub async fn import(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Query(params): Query<ImportUsersParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ImportReport>, AppError> {
+    let format = params
+        .format
+        .or_else(|| {
+            headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(BulkFormat::from_content_type)
+        })
+        .ok_or_else(|| {
+            AppError::BadRequest(
+                "format must be given as ?format=csv|jsonl or via Content-Type".to_string(),
+            )
+        })?;
+
+    let report = import::import_users(
+        state.pg()?,
+        &body,
+        ImportOptions {
+            format,
+            dry_run: params.dry_run,
+            notify: params.notify,
+            actor_id: Some(admin.id),
+        },
+        |event_type| context.event(event_type).actor(admin.id),
+    )
+    .await?;
+
+    Ok(Json(report))
+}
+
+/// Streams every user as `PublicUser` in CSV (default) or JSONL.
+pub async fn export(
+    AdminUser(admin): AdminUser,
+    State(state): State<AppState>,
+    Query(params): Query<ExportUsersParams>,
+) -> Result<Response, AppError> {
+    let format = params.format.unwrap_or(BulkFormat::Csv);
+    let stream = export_users(state.store.clone(), admin.id, format).map_err(std::io::Error::other);
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"users.{}\"", format.extension()),
+            ),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+fn encode_cursor(cursor: &UserCursor) -> Result<String, AppError> {
+    let json = serde_json::to_vec(cursor)
+        .map_err(|error| AppError::Internal(format!("failed to encode cursor: {error}")))?;
+
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+fn decode_cursor(value: &str) -> Result<UserCursor, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(value)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| AppError::BadRequest("cursor is malformed".to_string()))
+}
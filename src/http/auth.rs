@@ -1,13 +1,21 @@
 use axum::{extract::State, Json};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use serde::Deserialize;
+use serde_json::json;
+use std::sync::LazyLock;
 
 use crate::{
     app_state::AppState,
     auth::extractor::AuthUser,
-    db::users::{self, NewUser},
+    config::RegistrationMode,
+    db::{
+        audit::{self, AuditEventType},
+        users::{NewUser, UserRecord},
+    },
     error::AppError,
+    http::{context::RequestContext, etag::Versioned},
     models::{AuthResponse, PublicUser},
+    validation::{normalize_and_validate_email, validate_nickname, validate_password},
 };
 
 #[derive(Debug, Deserialize)]
@@ -15,36 +23,115 @@ pub struct RegisterRequest {
     pub nickname: String,
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub invite_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
-    pub email: String,
+    /// Email address or nickname. `email` is still accepted for older clients.
+    #[serde(alias = "email")]
+    pub identifier: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetPasswordRequest {
+    pub token: String,
     pub password: String,
 }
 
+/// Verified against when no user matches the identifier, so unknown accounts
+/// take as long to reject as wrong passwords.
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash("swarm-dummy-password", DEFAULT_COST).expect("bcrypt hashing of a constant must succeed")
+});
+
 pub async fn register(
     State(state): State<AppState>,
+    context: RequestContext,
     Json(payload): Json<RegisterRequest>,
 ) -> Result<Json<AuthResponse>, AppError> {
+    let invite_code = payload
+        .invite_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|code| !code.is_empty());
+
+    let settings = state.settings.current();
+
+    match settings.registration_mode {
+        RegistrationMode::Closed => {
+            return Err(AppError::Forbidden("registration is closed".to_string()));
+        }
+        RegistrationMode::InviteOnly if invite_code.is_none() => {
+            return Err(AppError::Forbidden(
+                "registration requires an invite code".to_string(),
+            ));
+        }
+        _ => {}
+    }
+
     let nickname = validate_nickname(&payload.nickname)?;
     let email = normalize_and_validate_email(&payload.email)?;
+    state.email_policy.ensure_allowed(&email)?;
     validate_password(&payload.password)?;
 
     let password_hash = hash(payload.password, DEFAULT_COST)?;
 
-    let created_user = users::create_user(
-        &state.db,
-        NewUser {
+    let mut tx = state.store.begin().await?;
+
+    let mut is_admin = false;
+    let mut invite_id = None;
+    if let Some(code) = invite_code {
+        let invite = tx
+            .consume_invite(code)
+            .await?
+            .ok_or_else(|| {
+                AppError::Forbidden("invite code is invalid, expired or already used".to_string())
+            })?;
+
+        let invited_elsewhere = invite
+            .email
+            .as_deref()
+            .is_some_and(|invited| invited.to_lowercase() != email.to_lowercase());
+        if invited_elsewhere {
+            return Err(AppError::Forbidden(
+                "invite code was issued for a different email address".to_string(),
+            ));
+        }
+
+        is_admin = invite.is_admin;
+        invite_id = Some(invite.id);
+    }
+
+    let created_user = tx
+        .create_user(NewUser {
             nickname,
             email,
             password_hash,
-            is_admin: false,
-        },
+            is_admin,
+        })
+        .await?;
+
+    let token = state.jwt.issue_token(&created_user, None, settings.jwt_ttl_seconds)?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::UserRegistered)
+            .actor(created_user.id)
+            .target(created_user.id)
+            .payload(json!({
+                "nickname": created_user.nickname,
+                "email": created_user.email,
+                "is_admin": created_user.is_admin,
+                "invite_id": invite_id,
+            })),
     )
     .await?;
+    tx.record_event(token_issued_event(&context, &created_user)).await?;
 
-    let token = state.jwt.issue_token(&created_user)?;
+    tx.commit().await?;
 
     Ok(Json(AuthResponse {
         token,
@@ -54,22 +141,43 @@ pub async fn register(
 
 pub async fn login(
     State(state): State<AppState>,
+    context: RequestContext,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<AuthResponse>, AppError> {
-    let email = normalize_and_validate_email(&payload.email)?;
+    let user = find_user_by_identifier(&state, &payload.identifier).await?;
 
-    let user = users::find_user_by_email(&state.db, &email)
-        .await?
-        .ok_or_else(|| AppError::Unauthorized("invalid email or password".to_string()))?;
+    let password_hash = user
+        .as_ref()
+        .map_or(DUMMY_PASSWORD_HASH.as_str(), |user| user.password_hash.as_str());
+    let password_is_valid = verify(payload.password, password_hash)?;
+
+    let user = match user {
+        Some(user) if password_is_valid => user,
+        user => {
+            let reason = if user.is_some() { "wrong_password" } else { "unknown_identifier" };
+            record_login_failure(&state, &context, &payload.identifier, user.as_ref(), reason).await?;
+            return Err(AppError::Unauthorized("invalid credentials".to_string()));
+        }
+    };
 
-    let password_is_valid = verify(payload.password, &user.password_hash)?;
-    if !password_is_valid {
-        return Err(AppError::Unauthorized(
-            "invalid email or password".to_string(),
-        ));
+    if user.suspended_at.is_some() {
+        record_login_failure(&state, &context, &payload.identifier, Some(&user), "account_suspended")
+            .await?;
+        return Err(AppError::Forbidden("account is suspended".to_string()));
     }
 
-    let token = state.jwt.issue_token(&user)?;
+    let token = state.jwt.issue_token(&user, None, state.settings.current().jwt_ttl_seconds)?;
+
+    let mut tx = state.store.begin().await?;
+    tx.record_event(
+        context
+            .event(AuditEventType::LoginSucceeded)
+            .actor(user.id)
+            .target(user.id),
+    )
+    .await?;
+    tx.record_event(token_issued_event(&context, &user)).await?;
+    tx.commit().await?;
 
     Ok(Json(AuthResponse {
         token,
@@ -77,50 +185,289 @@ pub async fn login(
     }))
 }
 
-pub async fn me(
-    auth_user: AuthUser,
+/// Redeems a set-password token sent by email (e.g. after a bulk import) and logs the user in.
+pub async fn set_password(
     State(state): State<AppState>,
-) -> Result<Json<PublicUser>, AppError> {
-    let user = users::find_user_by_id(&state.db, auth_user.id)
+    context: RequestContext,
+    Json(payload): Json<SetPasswordRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    validate_password(&payload.password)?;
+
+    let password_hash = hash(payload.password, DEFAULT_COST)?;
+
+    let mut tx = state.store.begin().await?;
+
+    let invalid_token =
+        || AppError::Forbidden("password token is invalid, expired or already used".to_string());
+
+    let user_id = tx
+        .consume_password_token(&payload.token)
         .await?
-        .ok_or_else(|| AppError::Unauthorized("user from token no longer exists".to_string()))?;
+        .ok_or_else(invalid_token)?;
 
-    Ok(Json(PublicUser::from(user)))
-}
+    let user = tx
+        .update_password_hash(user_id, &password_hash)
+        .await?
+        .ok_or_else(invalid_token)?;
 
-fn validate_nickname(value: &str) -> Result<String, AppError> {
-    let trimmed = value.trim();
-    if trimmed.len() < 3 || trimmed.len() > 32 {
-        return Err(AppError::BadRequest(
-            "nickname length must be between 3 and 32 characters".to_string(),
-        ));
+    if user.suspended_at.is_some() {
+        return Err(AppError::Forbidden("account is suspended".to_string()));
     }
 
-    Ok(trimmed.to_string())
+    let token = state.jwt.issue_token(&user, None, state.settings.current().jwt_ttl_seconds)?;
+
+    tx.record_event(
+        context
+            .event(AuditEventType::PasswordSet)
+            .actor(user.id)
+            .target(user.id),
+    )
+    .await?;
+    tx.record_event(token_issued_event(&context, &user)).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(AuthResponse {
+        token,
+        user: PublicUser::from(user),
+    }))
 }
 
-fn normalize_and_validate_email(value: &str) -> Result<String, AppError> {
-    let trimmed = value.trim().to_lowercase();
-    let valid = trimmed.contains('@')
-        && !trimmed.starts_with('@')
-        && !trimmed.ends_with('@')
-        && !trimmed.contains(' ');
-
-    if !valid {
-        return Err(AppError::BadRequest(
-            "email must be a valid address".to_string(),
-        ));
+/// `AuthUser` read the user from the primary, so the ETag never lags behind the
+/// user's own writes.
+pub async fn me(auth_user: AuthUser) -> Versioned<PublicUser> {
+    Versioned(auth_user.user.version, PublicUser::from(auth_user.user))
+}
+
+/// Identifiers containing `@` are looked up as emails, anything else as a nickname.
+/// Malformed identifiers simply match nobody.
+async fn find_user_by_identifier(
+    state: &AppState,
+    identifier: &str,
+) -> Result<Option<UserRecord>, AppError> {
+    let identifier = identifier.trim();
+    let mut session = state.store.session().await?;
+
+    if identifier.contains('@') {
+        let Ok(email) = normalize_and_validate_email(identifier) else {
+            return Ok(None);
+        };
+
+        return session.find_user_by_email(&email).await;
     }
 
-    Ok(trimmed)
+    session.find_user_by_nickname(identifier).await
+}
+
+fn token_issued_event(context: &RequestContext, user: &UserRecord) -> audit::NewAuditEvent {
+    context
+        .event(AuditEventType::TokenIssued)
+        .actor(user.id)
+        .target(user.id)
+        .payload(json!({ "token_version": user.token_version }))
 }
 
-fn validate_password(value: &str) -> Result<(), AppError> {
-    if value.len() < 8 {
-        return Err(AppError::BadRequest(
-            "password must contain at least 8 characters".to_string(),
-        ));
+async fn record_login_failure(
+    state: &AppState,
+    context: &RequestContext,
+    identifier: &str,
+    user: Option<&UserRecord>,
+    reason: &str,
+) -> Result<(), AppError> {
+    let mut event = context.event(AuditEventType::LoginFailed).payload(json!({
+        "identifier": identifier.trim(),
+        "reason": reason,
+    }));
+    if let Some(user) = user {
+        event = event.target(user.id);
     }
 
+    state.store.session().await?.record_event(event).await?;
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Method, Request, StatusCode},
+        Router,
+    };
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    use crate::{
+        app_state::AppState,
+        auth::jwt::JwtService,
+        config::RegistrationMode,
+        db::{
+            health::{DatabaseHealth, DatabaseState},
+            store::{memory::MemoryStore, Store},
+            users::UserUpdate,
+        },
+        features::FeatureFlags,
+        http::{self, admin::stats::new_stats_cache},
+        settings::{RuntimeSettings, Settings},
+    };
+
+    fn app(store: &MemoryStore) -> Router {
+        let db_health = DatabaseHealth::default();
+        db_health.set(DatabaseState::Ready);
+
+        http::router(AppState {
+            db: None,
+            store: Arc::new(store.clone()),
+            db_health,
+            jwt: JwtService::new("router-test-secret".to_string()),
+            settings: Settings::new(RuntimeSettings {
+                registration_mode: RegistrationMode::Open,
+                maintenance_banner: None,
+                jwt_ttl_seconds: 3600,
+            }),
+            email_policy: Arc::default(),
+            trust_proxy_headers: false,
+            stats_cache: Arc::new(new_stats_cache()),
+            features: FeatureFlags::default(),
+        })
+    }
+
+    async fn send(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        }
+        .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    async fn register(app: &Router, nickname: &str, email: &str) -> (StatusCode, Value) {
+        let body = json!({ "nickname": nickname, "email": email, "password": "correct horse battery" });
+        send(app, Method::POST, "/auth/register", None, Some(body)).await
+    }
+
+    #[tokio::test]
+    async fn register_returns_a_token_that_me_accepts() {
+        let store = MemoryStore::new();
+        let app = app(&store);
+
+        let (status, body) = register(&app, "alice", "Alice@Example.com").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["user"]["email"], "Alice@example.com");
+
+        let token = body["token"].as_str().unwrap();
+        let (status, me) = send(&app, Method::GET, "/auth/me", Some(token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(me["nickname"], "alice");
+        assert_eq!(me["id"], body["user"]["id"]);
+
+        let events: Vec<_> = store.audit_events().into_iter().map(|event| event.event_type).collect();
+        assert_eq!(events, ["user.registered", "auth.token_issued"]);
+    }
+
+    #[tokio::test]
+    async fn register_reports_which_field_is_taken() {
+        let store = MemoryStore::new();
+        let app = app(&store);
+        register(&app, "alice", "alice@example.com").await;
+
+        let (status, body) = register(&app, "ALICE", "other@example.com").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body["error"].as_str().unwrap().contains("nickname is already taken"));
+
+        let (status, body) = register(&app, "bob", "ALICE@example.com").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(body["error"].as_str().unwrap().contains("email is already registered"));
+    }
+
+    #[tokio::test]
+    async fn login_accepts_email_or_nickname_and_rejects_wrong_passwords() {
+        let store = MemoryStore::new();
+        let app = app(&store);
+        register(&app, "alice", "alice@example.com").await;
+
+        for identifier in ["alice@example.com", "Alice"] {
+            let body = json!({ "identifier": identifier, "password": "correct horse battery" });
+            let (status, body) = send(&app, Method::POST, "/auth/login", None, Some(body)).await;
+            assert_eq!(status, StatusCode::OK, "{identifier}");
+            assert_eq!(body["user"]["nickname"], "alice");
+        }
+
+        let body = json!({ "email": "alice@example.com", "password": "wrong horse battery" });
+        let (status, _) = send(&app, Method::POST, "/auth/login", None, Some(body)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let failure = store.audit_events().pop().unwrap();
+        assert_eq!(failure.event_type, "auth.login_failed");
+        assert_eq!(failure.payload["reason"], "wrong_password");
+    }
+
+    #[tokio::test]
+    async fn set_password_redeems_the_token_once_and_revokes_older_tokens() {
+        let store = MemoryStore::new();
+        let app = app(&store);
+        let (_, registered) = register(&app, "alice", "alice@example.com").await;
+        let old_token = registered["token"].as_str().unwrap();
+
+        let user_id = registered["user"]["id"].as_str().unwrap().parse().unwrap();
+        let password_token = store.issue_password_token(user_id);
+        let request = json!({ "token": password_token, "password": "a brand new password" });
+
+        let (status, body) =
+            send(&app, Method::POST, "/auth/set-password", None, Some(request.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        let new_token = body["token"].as_str().unwrap();
+
+        let (status, _) = send(&app, Method::POST, "/auth/set-password", None, Some(request)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, _) = send(&app, Method::GET, "/auth/me", Some(old_token), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, Method::GET, "/auth/me", Some(new_token), None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let body = json!({ "identifier": "alice", "password": "a brand new password" });
+        let (status, _) = send(&app, Method::POST, "/auth/login", None, Some(body)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn me_rejects_missing_tokens_and_tokens_of_suspended_accounts() {
+        let store = MemoryStore::new();
+        let app = app(&store);
+
+        let (status, _) = send(&app, Method::GET, "/auth/me", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (_, registered) = register(&app, "alice", "alice@example.com").await;
+        let token = registered["token"].as_str().unwrap();
+        let user_id = registered["user"]["id"].as_str().unwrap().parse().unwrap();
+
+        let suspend = UserUpdate {
+            suspended: Some(true),
+            ..Default::default()
+        };
+        store.session().await.unwrap().update_user(user_id, suspend, None).await.unwrap();
+
+        let (status, _) = send(&app, Method::GET, "/auth/me", Some(token), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,109 @@
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request},
+    http::{request::Parts, HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    db::audit::{AuditEventType, NewAuditEvent},
+    error::AppError,
+};
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Who is calling and through which request; attached to audit events.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub request_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl RequestContext {
+    pub fn event(&self, event_type: AuditEventType) -> NewAuditEvent {
+        let mut event = NewAuditEvent::new(event_type);
+        event.ip = self.ip.clone();
+        event.request_id = self.request_id.clone();
+        event
+    }
+}
+
+/// Keeps a sane client-supplied `X-Request-Id` or generates one, and echoes it back.
+pub async fn assign_request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty()
+                && value.len() <= MAX_REQUEST_ID_LEN
+                && value.bytes().all(|byte| byte.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    request
+        .extensions_mut()
+        .insert(RequestId(request_id.clone()));
+
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
+
+    response
+}
+
+impl FromRequestParts<AppState> for RequestContext {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let request_id = parts
+            .extensions
+            .get::<RequestId>()
+            .map(|request_id| request_id.0.clone());
+
+        let forwarded_ip = state
+            .trust_proxy_headers
+            .then(|| forwarded_client_ip(&parts.headers))
+            .flatten();
+
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+
+        Ok(Self {
+            request_id,
+            ip: forwarded_ip.or(peer_ip),
+        })
+    }
+}
+
+/// `X-Real-IP` as set by the bundled nginx, falling back to the first `X-Forwarded-For` hop.
+fn forwarded_client_ip(headers: &HeaderMap) -> Option<String> {
+    let real_ip = headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim);
+
+    let forwarded_for = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim);
+
+    real_ip
+        .or(forwarded_for)
+        .and_then(|value| value.parse::<std::net::IpAddr>().ok())
+        .map(|ip| ip.to_string())
+}
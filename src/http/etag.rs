@@ -0,0 +1,78 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+/// A JSON body sent with its version as a strong `ETag`, e.g. `"3"`.
+pub struct Versioned<T>(pub i64, pub T);
+
+impl<T: Serialize> IntoResponse for Versioned<T> {
+    fn into_response(self) -> Response {
+        let Versioned(version, body) = self;
+
+        ([(header::ETAG, format!("\"{version}\""))], Json(body)).into_response()
+    }
+}
+
+/// The request's `If-Match` precondition; handlers decide whether it is mandatory.
+#[derive(Debug, Clone, Copy)]
+pub enum IfMatch {
+    Absent,
+    /// `*`: any current version.
+    Any,
+    Version(i64),
+    /// Weak, malformed or not an ETag this API issued; matches no version.
+    Unmatchable,
+}
+
+impl IfMatch {
+    /// The version the client last saw, `None` for `*`. A missing header fails with
+    /// 428 so clients cannot skip the check by accident.
+    pub fn required(self) -> Result<Option<i64>, AppError> {
+        match self {
+            Self::Absent => Err(AppError::PreconditionRequired(
+                "send If-Match with the ETag of the user you are changing".to_string(),
+            )),
+            other => other.optional(),
+        }
+    }
+
+    /// Like [`required`](Self::required), but a missing header imposes no condition.
+    pub fn optional(self) -> Result<Option<i64>, AppError> {
+        match self {
+            Self::Absent | Self::Any => Ok(None),
+            Self::Version(version) => Ok(Some(version)),
+            Self::Unmatchable => Err(AppError::PreconditionFailed(
+                "If-Match does not name a current version".to_string(),
+            )),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(Self::Absent);
+        };
+
+        let value = value.to_str().unwrap_or_default().trim();
+        if value == "*" {
+            return Ok(Self::Any);
+        }
+
+        Ok(value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .and_then(|version| version.parse().ok())
+            .map_or(Self::Unmatchable, Self::Version))
+    }
+}
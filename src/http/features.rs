@@ -0,0 +1,10 @@
+use axum::{extract::State, Json};
+
+use crate::{app_state::AppState, auth::extractor::AuthUser, models::FeaturesResponse};
+
+/// Every feature flag evaluated for the caller, from the in-process cache.
+pub async fn features(auth_user: AuthUser, State(state): State<AppState>) -> Json<FeaturesResponse> {
+    Json(FeaturesResponse {
+        features: state.features.evaluate_all(auth_user.id),
+    })
+}
@@ -1,11 +1,116 @@
-use axum::Json;
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+    Json,
+};
 use serde::Serialize;
 
+use crate::{app_state::AppState, db::health::DatabaseState, error::AppError};
+
+/// A health check must answer quickly even when every pooled connection is busy.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
 #[derive(Serialize)]
 pub struct HealthResponse {
+    /// `ok`, or `degraded` while the database is not usable.
     status: &'static str,
+    database: DatabaseStatus,
 }
 
-pub async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse { status: "ok" })
+#[derive(Serialize)]
+struct DatabaseStatus {
+    /// `connecting`, `ready`, `unreachable` or `failed`.
+    state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connect_attempts: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pool_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    idle_connections: Option<usize>,
+    /// Configured read replicas; they are not pinged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replicas: Option<usize>,
+}
+
+/// 200 when the database is ready and answers a ping, 503 otherwise.
+pub async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let mut database = match state.db_health.current() {
+        DatabaseState::Connecting {
+            attempts,
+            last_error,
+        } => DatabaseStatus::new("connecting", last_error).attempts(attempts),
+        DatabaseState::Ready => DatabaseStatus::new("ready", None),
+        DatabaseState::Failed { error } => DatabaseStatus::new("failed", Some(error)),
+    };
+
+    if let Some(pools) = &state.db {
+        let pool = pools.primary();
+        if database.state == "ready" {
+            let ping = tokio::time::timeout(PING_TIMEOUT, sqlx::query("SELECT 1").execute(pool)).await;
+
+            let error = match ping {
+                Ok(Ok(_)) => None,
+                Ok(Err(error)) => Some(error.to_string()),
+                Err(_) => Some("ping timed out".to_string()),
+            };
+
+            if error.is_some() {
+                database = DatabaseStatus::new("unreachable", error);
+            }
+        }
+
+        database.pool_size = Some(pool.size());
+        database.idle_connections = Some(pool.num_idle());
+        database.replicas = Some(pools.replica_count()).filter(|count| *count > 0);
+    }
+
+    let (status_code, status) = if database.state == "ready" {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+
+    (status_code, Json(HealthResponse { status, database }))
+}
+
+/// Every endpoint but `/health` answers 503 until the startup sequence has finished,
+/// instead of running against a schema that is not migrated yet.
+pub async fn require_database_ready(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    match state.db_health.current() {
+        DatabaseState::Ready => Ok(next.run(request).await),
+        DatabaseState::Connecting { .. } => Err(AppError::ServiceUnavailable(
+            "the database is still starting; see GET /health".to_string(),
+        )),
+        DatabaseState::Failed { .. } => Err(AppError::ServiceUnavailable(
+            "database startup failed; see GET /health".to_string(),
+        )),
+    }
+}
+
+impl DatabaseStatus {
+    fn new(state: &'static str, error: Option<String>) -> Self {
+        Self {
+            state,
+            error,
+            connect_attempts: None,
+            pool_size: None,
+            idle_connections: None,
+            replicas: None,
+        }
+    }
+
+    fn attempts(mut self, attempts: u32) -> Self {
+        self.connect_attempts = Some(attempts);
+        self
+    }
 }
@@ -1,18 +1,77 @@
 pub mod admin;
 pub mod auth;
+pub mod context;
+pub mod etag;
+pub mod features;
 pub mod health;
+pub mod orgs;
+pub mod settings;
 
-use axum::{routing::get, Router};
-use axum::routing::post;
+use axum::{extract::DefaultBodyLimit, middleware, routing::get, Router};
+use axum::routing::{delete, post};
 
 use crate::app_state::AppState;
 
+/// Large enough for `bulk::import::MAX_IMPORT_ROWS` rows of generous width.
+const IMPORT_BODY_LIMIT: usize = 8 * 1024 * 1024;
+
 pub fn router(state: AppState) -> Router {
     Router::new()
-        .route("/health", get(health::health))
         .route("/auth/register", post(auth::register))
         .route("/auth/login", post(auth::login))
+        .route("/auth/set-password", post(auth::set_password))
         .route("/auth/me", get(auth::me))
+        .route("/features", get(features::features))
+        .route("/settings", get(settings::public_settings))
+        .route("/orgs", get(orgs::list).post(orgs::create))
+        .route("/orgs/switch", post(orgs::switch))
+        .route("/orgs/join", post(orgs::join))
+        .route("/org", get(orgs::current))
+        .route("/org/members", get(orgs::members))
+        .route("/org/invites", post(orgs::create_invite))
         .route("/admin/ping", get(admin::ping))
+        .route(
+            "/admin/invites",
+            get(admin::invites::list).post(admin::invites::create),
+        )
+        .route("/admin/invites/{id}", delete(admin::invites::revoke))
+        .route("/admin/users", get(admin::users::list))
+        .route(
+            "/admin/users/import",
+            post(admin::users::import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route("/admin/users/export", get(admin::users::export))
+        .route(
+            "/admin/users/{id}",
+            get(admin::users::get)
+                .patch(admin::users::update)
+                .delete(admin::users::delete),
+        )
+        .route("/admin/users/{id}/roles", post(admin::users::change_role))
+        .route(
+            "/admin/features",
+            get(admin::features::list).post(admin::features::create),
+        )
+        .route(
+            "/admin/features/{key}",
+            get(admin::features::get)
+                .patch(admin::features::update)
+                .delete(admin::features::delete),
+        )
+        .route(
+            "/admin/settings",
+            get(admin::settings::list).put(admin::settings::update),
+        )
+        .route("/admin/settings/history", get(admin::settings::history))
+        .route("/admin/stats", get(admin::stats::stats))
+        .route("/admin/audit", get(admin::audit::list))
+        .route("/admin/audit/export", get(admin::audit::export))
+        .route("/admin/audit/verify", get(admin::audit::verify))
+        .route("/admin/jobs", get(admin::jobs::list))
+        .route("/admin/jobs/{id}", get(admin::jobs::get))
+        .route("/admin/jobs/{id}/retry", post(admin::jobs::retry))
+        .route_layer(middleware::from_fn_with_state(state.clone(), health::require_database_ready))
+        .route("/health", get(health::health))
+        .layer(middleware::from_fn(context::assign_request_id))
         .with_state(state)
 }
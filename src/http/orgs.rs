@@ -0,0 +1,283 @@
+use axum::{extract::State, Json};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    auth::extractor::{AuthUser, OrgMember},
+    db::{
+        audit::{self, AuditEventType},
+        invites,
+        organizations::{self, NewOrganization, NewOrganizationInvite, OrganizationRole},
+        tenant,
+    },
+    error::AppError,
+    http::context::RequestContext,
+    models::{
+        AuthResponse, OrganizationInviteResponse, OrganizationMemberResponse,
+        OrganizationResponse, PublicUser,
+    },
+    validation::{
+        normalize_and_validate_email,
+        organization::{validate_name, validate_slug},
+    },
+};
+
+const DEFAULT_INVITE_TTL_HOURS: i64 = 7 * 24;
+const MAX_INVITE_TTL_HOURS: i64 = 30 * 24;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationRequest {
+    pub slug: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SwitchOrganizationRequest {
+    /// `null` leaves every organization.
+    pub organization_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinOrganizationRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOrganizationInviteRequest {
+    /// Only this account may accept the invite when set.
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default = "default_invite_role")]
+    pub role: OrganizationRole,
+    #[serde(default)]
+    pub expires_in_hours: Option<i64>,
+}
+
+fn default_invite_role() -> OrganizationRole {
+    OrganizationRole::Member
+}
+
+/// Every organization the caller belongs to.
+pub async fn list(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<OrganizationResponse>>, AppError> {
+    let mut tx = tenant::begin(state.pg_read(auth_user.id)?, auth_user.tenant()).await?;
+    let records = organizations::list_memberships(&mut *tx, auth_user.id).await?;
+    tx.commit().await?;
+
+    Ok(Json(records.into_iter().map(OrganizationResponse::from).collect()))
+}
+
+/// Creates an organization owned by the caller. Switch to it to act within it.
+pub async fn create(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<Json<OrganizationResponse>, AppError> {
+    let slug = validate_slug(&payload.slug)?;
+    let name = validate_name(&payload.name)?;
+
+    let mut tx = tenant::begin(state.pg()?, auth_user.tenant()).await?;
+
+    let membership = organizations::create_organization(
+        &mut tx,
+        NewOrganization {
+            slug,
+            name,
+            owner_id: auth_user.id,
+        },
+    )
+    .await?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::OrganizationCreated)
+            .actor(auth_user.id)
+            .payload(json!({
+                "organization_id": membership.organization.id,
+                "slug": membership.organization.slug,
+                "name": membership.organization.name,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(OrganizationResponse::from(membership)))
+}
+
+/// Issues a token scoped to the given organization, or to none.
+pub async fn switch(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<SwitchOrganizationRequest>,
+) -> Result<Json<AuthResponse>, AppError> {
+    if let Some(organization_id) = payload.organization_id {
+        let mut tx = tenant::begin(state.pg()?, auth_user.tenant()).await?;
+        organizations::find_membership(&mut *tx, organization_id, auth_user.id)
+            .await?
+            .ok_or_else(|| AppError::Forbidden("not a member of this organization".to_string()))?;
+        tx.commit().await?;
+    }
+
+    let user = auth_user.user;
+    let token = state.jwt.issue_token(
+        &user,
+        payload.organization_id,
+        state.settings.current().jwt_ttl_seconds,
+    )?;
+
+    state
+        .store
+        .session()
+        .await?
+        .record_event(
+            context
+                .event(AuditEventType::TokenIssued)
+                .actor(user.id)
+                .target(user.id)
+                .payload(json!({
+                    "token_version": user.token_version,
+                    "organization_id": payload.organization_id,
+                })),
+        )
+        .await?;
+
+    Ok(Json(AuthResponse {
+        token,
+        user: PublicUser::from(user),
+    }))
+}
+
+/// Accepts an organization invite for the caller's account.
+pub async fn join(
+    auth_user: AuthUser,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<JoinOrganizationRequest>,
+) -> Result<Json<OrganizationResponse>, AppError> {
+    let mut tx = tenant::begin(state.pg()?, auth_user.tenant()).await?;
+
+    let membership =
+        organizations::accept_invite(&mut tx, payload.code.trim(), auth_user.id, &auth_user.user.email)
+            .await?
+            .ok_or_else(|| {
+                AppError::Forbidden(
+                    "invite code is invalid, expired, already used or for another email".to_string(),
+                )
+            })?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::OrganizationJoined)
+            .actor(auth_user.id)
+            .target(auth_user.id)
+            .payload(json!({
+                "organization_id": membership.organization.id,
+                "role": membership.role,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(OrganizationResponse::from(membership)))
+}
+
+/// The organization selected in the caller's token.
+pub async fn current(
+    member: OrgMember,
+    State(state): State<AppState>,
+) -> Result<Json<OrganizationResponse>, AppError> {
+    let mut tx = tenant::begin(state.pg_read(member.user.id)?, member.tenant()).await?;
+    let membership = organizations::find_membership(&mut *tx, member.organization_id, member.user.id)
+        .await?
+        .ok_or_else(|| AppError::Forbidden("not a member of the selected organization".to_string()))?;
+    tx.commit().await?;
+
+    Ok(Json(OrganizationResponse::from(membership)))
+}
+
+pub async fn members(
+    member: OrgMember,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<OrganizationMemberResponse>>, AppError> {
+    let mut tx = tenant::begin(state.pg_read(member.user.id)?, member.tenant()).await?;
+    let records = organizations::list_members(&mut *tx, member.organization_id).await?;
+    tx.commit().await?;
+
+    Ok(Json(records.into_iter().map(OrganizationMemberResponse::from).collect()))
+}
+
+/// Admins invite members; only owners invite admins. Ownership is never granted by invite.
+pub async fn create_invite(
+    member: OrgMember,
+    State(state): State<AppState>,
+    context: RequestContext,
+    Json(payload): Json<CreateOrganizationInviteRequest>,
+) -> Result<Json<OrganizationInviteResponse>, AppError> {
+    match payload.role {
+        OrganizationRole::Member => member.require_role(OrganizationRole::Admin)?,
+        OrganizationRole::Admin => member.require_role(OrganizationRole::Owner)?,
+        OrganizationRole::Owner => {
+            return Err(AppError::BadRequest(
+                "invites can grant the member or admin role".to_string(),
+            ));
+        }
+    }
+
+    let ttl_hours = payload.expires_in_hours.unwrap_or(DEFAULT_INVITE_TTL_HOURS);
+    if !(1..=MAX_INVITE_TTL_HOURS).contains(&ttl_hours) {
+        return Err(AppError::BadRequest(format!(
+            "expires_in_hours must be between 1 and {MAX_INVITE_TTL_HOURS}"
+        )));
+    }
+
+    let email = payload
+        .email
+        .as_deref()
+        .map(normalize_and_validate_email)
+        .transpose()?;
+
+    let mut tx = tenant::begin(state.pg()?, member.tenant()).await?;
+
+    let invite = organizations::create_invite(
+        &mut *tx,
+        NewOrganizationInvite {
+            organization_id: member.organization_id,
+            code: invites::generate_invite_code(),
+            email,
+            role: payload.role,
+            expires_at: Utc::now() + Duration::hours(ttl_hours),
+            created_by: member.user.id,
+        },
+    )
+    .await?;
+
+    audit::record_event(
+        &mut tx,
+        context
+            .event(AuditEventType::OrganizationInviteCreated)
+            .actor(member.user.id)
+            .payload(json!({
+                "organization_id": invite.organization_id,
+                "invite_id": invite.id,
+                "email": invite.email,
+                "role": invite.role,
+                "expires_at": invite.expires_at,
+            })),
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(OrganizationInviteResponse::from(invite)))
+}
@@ -0,0 +1,13 @@
+use axum::{extract::State, Json};
+
+use crate::{app_state::AppState, models::PublicSettingsResponse};
+
+/// Settings the frontend needs before login, e.g. to show the maintenance banner.
+pub async fn public_settings(State(state): State<AppState>) -> Json<PublicSettingsResponse> {
+    let settings = state.settings.current();
+
+    Json(PublicSettingsResponse {
+        registration_mode: settings.registration_mode,
+        maintenance_banner: settings.maintenance_banner.clone(),
+    })
+}
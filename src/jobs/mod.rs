@@ -0,0 +1,166 @@
+//! Background work that should not hold up a request: emails and periodic cleanups.
+//! Jobs are rows in the `jobs` table (see `db::jobs`), enqueued in the same
+//! transaction as the change that needs them and run by the `worker` pool.
+
+pub mod worker;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        invites,
+        jobs::{self, JobRecord, NewJob},
+        password_tokens, users,
+    },
+    error::AppError,
+    mail::Mailer,
+};
+
+const PURGE_INTERVAL: Duration = Duration::hours(1);
+
+/// Everything a job may need while it runs.
+#[derive(Clone)]
+pub struct JobContext {
+    pub pool: PgPool,
+    pub mailer: Mailer,
+}
+
+/// Stored as `kind` plus the variant's fields as `payload`. Renaming a variant or
+/// a field strands the jobs already queued under the old shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum Job {
+    /// Skipped once the invite was revoked, used up or has expired.
+    SendInviteEmail {
+        invite_id: Uuid,
+        nickname: Option<String>,
+    },
+    /// Issues the set-password token only when sending, so the raw token is never
+    /// stored; each attempt replaces the token of the previous one.
+    SendSetPasswordEmail { user_id: Uuid },
+    /// Deletes used and expired password tokens, then schedules its next run.
+    PurgePasswordTokens,
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SendInviteEmail { .. } => "send_invite_email",
+            Self::SendSetPasswordEmail { .. } => "send_set_password_email",
+            Self::PurgePasswordTokens => "purge_password_tokens",
+        }
+    }
+
+    fn max_attempts(&self) -> i32 {
+        match self {
+            Self::SendInviteEmail { .. } | Self::SendSetPasswordEmail { .. } => 8,
+            Self::PurgePasswordTokens => 3,
+        }
+    }
+
+    /// Recurring jobs keep at most one pending run.
+    fn unique_key(&self) -> Option<String> {
+        match self {
+            Self::PurgePasswordTokens => Some(self.kind().to_string()),
+            _ => None,
+        }
+    }
+
+    /// Queues the job to run as soon as a worker is free.
+    pub async fn enqueue<'e>(self, executor: impl PgExecutor<'e>) -> Result<Option<i64>, AppError> {
+        self.enqueue_at(executor, Utc::now()).await
+    }
+
+    /// Queues the job for `run_at`. `None` when an identical recurring job is already pending.
+    pub async fn enqueue_at<'e>(
+        self,
+        executor: impl PgExecutor<'e>,
+        run_at: DateTime<Utc>,
+    ) -> Result<Option<i64>, AppError> {
+        let kind = self.kind();
+        let max_attempts = self.max_attempts();
+        let unique_key = self.unique_key();
+
+        let mut value = serde_json::to_value(self)
+            .map_err(|error| AppError::Internal(format!("failed to encode job: {error}")))?;
+        let payload = value
+            .get_mut("payload")
+            .map(serde_json::Value::take)
+            .unwrap_or_default();
+
+        jobs::enqueue(
+            executor,
+            NewJob {
+                kind,
+                payload,
+                max_attempts,
+                run_at,
+                unique_key,
+            },
+        )
+        .await
+    }
+
+    /// Fails for kinds or payloads this build does not understand.
+    pub fn decode(record: &JobRecord) -> Result<Self, String> {
+        let mut value = serde_json::json!({ "kind": record.kind });
+        if !record.payload.is_null() {
+            value["payload"] = record.payload.clone();
+        }
+
+        serde_json::from_value(value).map_err(|error| format!("cannot decode job: {error}"))
+    }
+
+    pub async fn run(self, context: &JobContext) -> Result<(), AppError> {
+        match self {
+            Self::SendInviteEmail { invite_id, nickname } => {
+                let Some(invite) = invites::find_invite(&context.pool, invite_id).await? else {
+                    info!(%invite_id, "invite no longer exists; email skipped");
+                    return Ok(());
+                };
+
+                let redeemable = invite.revoked_at.is_none()
+                    && invite.used_count < invite.max_uses
+                    && invite.expires_at.is_none_or(|expires_at| expires_at > Utc::now());
+                let Some(email) = invite.email.filter(|_| redeemable) else {
+                    info!(%invite_id, "invite is no longer redeemable or has no email; email skipped");
+                    return Ok(());
+                };
+
+                context
+                    .mailer
+                    .send_invite(&email, &invite.code, nickname.as_deref())
+                    .await
+            }
+            Self::SendSetPasswordEmail { user_id } => {
+                let Some(user) = users::find_user_by_id(&context.pool, user_id).await? else {
+                    info!(%user_id, "user no longer exists; set-password email skipped");
+                    return Ok(());
+                };
+
+                let token = password_tokens::issue_password_token(&context.pool, user.id).await?;
+
+                context
+                    .mailer
+                    .send_set_password(&user.email, &user.nickname, &token)
+                    .await
+            }
+            Self::PurgePasswordTokens => {
+                let deleted = password_tokens::purge_password_tokens(&context.pool).await?;
+                if deleted > 0 {
+                    info!(deleted, "purged used and expired password tokens");
+                }
+
+                Self::PurgePasswordTokens
+                    .enqueue_at(&context.pool, Utc::now() + PURGE_INTERVAL)
+                    .await?;
+
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::Utc;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
+
+use super::{Job, JobContext};
+use crate::db::{
+    health::DatabaseHealth,
+    jobs::{self, JobRecord, JOBS_CHANNEL},
+    notify,
+};
+
+/// How long a claimed job belongs to its worker. Runs are cut off before it ends,
+/// so a job is only picked up twice when its worker died.
+const LEASE: Duration = Duration::from_secs(300);
+const RUN_TIMEOUT: Duration = Duration::from_secs(240);
+
+/// Fallback for missed notifications and for jobs scheduled in the future.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const ERROR_DELAY: Duration = Duration::from_secs(5);
+
+const RETRY_BASE_SECONDS: i64 = 30;
+const RETRY_MAX_SECONDS: i64 = 3600;
+
+/// Starts `workers` job workers and the recurring maintenance jobs. Workers wait
+/// until the database is ready, then wake on `jobs_enqueued` notifications.
+pub fn spawn(context: JobContext, health: DatabaseHealth, workers: usize) {
+    let wakeup = Arc::new(Notify::new());
+
+    tokio::spawn({
+        let pool = context.pool.clone();
+        let wakeup = wakeup.clone();
+        async move {
+            health.wait_until_ready().await;
+
+            if let Err(error) = Job::PurgePasswordTokens.enqueue(&pool).await {
+                warn!("failed to schedule password token purge: {error}");
+            }
+
+            notify::watch(pool, JOBS_CHANNEL, || {
+                wakeup.notify_waiters();
+                async {}
+            })
+            .await;
+        }
+    });
+
+    for index in 0..workers {
+        let name = format!("{}/{index}", std::process::id());
+        tokio::spawn(run_worker(context.clone(), name, wakeup.clone()));
+    }
+
+    info!(workers, "job workers started");
+}
+
+async fn run_worker(context: JobContext, name: String, wakeup: Arc<Notify>) {
+    loop {
+        match jobs::claim(&context.pool, &name, LEASE).await {
+            Ok(Some(job)) => process(&context, job).await,
+            Ok(None) => {
+                tokio::select! {
+                    _ = wakeup.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+            Err(error) => {
+                warn!(worker = name, "failed to claim a job: {error}");
+                tokio::time::sleep(ERROR_DELAY).await;
+            }
+        }
+    }
+}
+
+async fn process(context: &JobContext, job: JobRecord) {
+    // `Err((error, retryable))`
+    let result = match Job::decode(&job) {
+        Ok(_) if job.attempts > job.max_attempts => Err((
+            "lease expired during the final attempt".to_string(),
+            false,
+        )),
+        Ok(task) => match tokio::time::timeout(RUN_TIMEOUT, task.run(context)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => Err((error.to_string(), true)),
+            Err(_) => Err((format!("timed out after {}s", RUN_TIMEOUT.as_secs()), true)),
+        },
+        Err(error) => Err((error, false)),
+    };
+
+    let stored = match result {
+        Ok(()) => {
+            info!(job_id = job.id, kind = job.kind, attempt = job.attempts, "job succeeded");
+            jobs::complete(&context.pool, &job).await
+        }
+        Err((message, retryable)) => {
+            let retry_at = (retryable && job.attempts < job.max_attempts)
+                .then(|| Utc::now() + chrono::Duration::seconds(retry_delay_seconds(job.attempts)));
+
+            match retry_at {
+                Some(retry_at) => warn!(
+                    job_id = job.id,
+                    kind = job.kind,
+                    attempt = job.attempts,
+                    %retry_at,
+                    "job failed, will retry: {message}"
+                ),
+                None => error!(
+                    job_id = job.id,
+                    kind = job.kind,
+                    attempt = job.attempts,
+                    "job failed permanently: {message}"
+                ),
+            }
+
+            jobs::fail(&context.pool, &job, &message, retry_at).await
+        }
+    };
+
+    match stored {
+        Ok(true) => {}
+        Ok(false) => warn!(job_id = job.id, "job lease was lost before its outcome was recorded"),
+        Err(error) => warn!(job_id = job.id, "failed to record job outcome: {error}"),
+    }
+}
+
+/// 30s, 1m, 2m, ... capped at an hour.
+fn retry_delay_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+
+    RETRY_BASE_SECONDS
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_SECONDS)
+}
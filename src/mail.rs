@@ -0,0 +1,96 @@
+use lettre::{
+    message::Mailbox, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use tracing::info;
+
+use crate::{config::AppConfig, db::password_tokens::PASSWORD_TOKEN_TTL, error::AppError};
+
+/// Sends account emails over SMTP, or only logs their recipient and subject when
+/// `SMTP_URL` is not set.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+    public_base_url: String,
+}
+
+impl Mailer {
+    pub fn from_config(config: &AppConfig) -> Result<Self, AppError> {
+        let from = config
+            .mail_from
+            .parse::<Mailbox>()
+            .map_err(|error| AppError::Internal(format!("MAIL_FROM is not a valid mailbox: {error}")))?;
+
+        let transport = config
+            .smtp_url
+            .as_deref()
+            .map(|url| {
+                AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                    .map(|builder| builder.build())
+                    .map_err(|error| AppError::Internal(format!("SMTP_URL is invalid: {error}")))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            transport,
+            from,
+            public_base_url: config.public_base_url.clone(),
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    pub async fn send_invite(&self, to: &str, code: &str, nickname: Option<&str>) -> Result<(), AppError> {
+        let greeting = nickname.map_or_else(|| "Hello,".to_string(), |nickname| format!("Hello {nickname},"));
+        let body = format!(
+            "{greeting}\n\n\
+             You have been invited to Swarm. Create your account here:\n\n\
+             {}/register?invite={code}\n\n\
+             Invite code: {code}\n",
+            self.public_base_url
+        );
+
+        self.send(to, "You are invited to Swarm", body).await
+    }
+
+    pub async fn send_set_password(&self, to: &str, nickname: &str, token: &str) -> Result<(), AppError> {
+        let body = format!(
+            "Hello {nickname},\n\n\
+             An account has been created for you on Swarm. Choose your password here:\n\n\
+             {}/set-password?token={token}\n\n\
+             The link can be used once and expires in {} hours.\n",
+            self.public_base_url,
+            PASSWORD_TOKEN_TTL.num_hours()
+        );
+
+        self.send(to, "Set your Swarm password", body).await
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), AppError> {
+        let Some(transport) = &self.transport else {
+            // The body carries live invite codes and set-password tokens.
+            info!(to, subject, "SMTP_URL is not set; email not sent");
+            return Ok(());
+        };
+
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|error| AppError::BadRequest(format!("cannot send email to <{to}>: {error}")))?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .body(body)
+            .map_err(|error| AppError::Internal(format!("failed to build email: {error}")))?;
+
+        transport
+            .send(message)
+            .await
+            .map_err(|error| AppError::ServiceUnavailable(format!("failed to send email: {error}")))?;
+
+        Ok(())
+    }
+}
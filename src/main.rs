@@ -1,56 +1,241 @@
 mod app_state;
 mod auth;
+mod backup;
+mod bulk;
+mod cache;
+mod cli;
 mod config;
 mod db;
 mod error;
+mod events;
+mod features;
 mod http;
+mod jobs;
+mod mail;
 mod models;
+mod settings;
+mod validation;
 
 use app_state::AppState;
 use auth::jwt::JwtService;
-use config::AppConfig;
-use sqlx::postgres::PgPoolOptions;
-use tracing::{info, warn};
+use clap::Parser;
+use cli::{Cli, Command};
+use config::{AppConfig, SchemaCheckMode};
+use db::{
+    health::{DatabaseHealth, DatabaseState},
+    routing::DatabasePools,
+    store::{Store, postgres::PgStore},
+};
+use events::EventBus;
+use features::FeatureFlags;
+use jobs::JobContext;
+use settings::{RuntimeSettings, Settings};
+use sqlx::PgPool;
+use std::{net::SocketAddr, process::ExitCode, sync::Arc};
+use mail::Mailer;
+use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, EnvFilter};
+use validation::email::EmailDomainPolicy;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Logs go to stderr so commands such as `export-users` can write data to stdout.
     fmt()
         .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
     let config = AppConfig::load();
 
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            serve(config).await;
+            ExitCode::SUCCESS
+        }
+        command => match cli::run(command, &config).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+async fn serve(config: AppConfig) {
     if config.jwt_secret_is_ephemeral {
         warn!(
             "JWT_SECRET is not set; generated ephemeral secret for this process. All JWT tokens will become invalid after restart"
         );
     }
 
-    let db_pool = PgPoolOptions::new()
-        .max_connections(12)
-        .connect(&config.database_url)
-        .await
-        .expect("failed to connect to PostgreSQL (fail-fast startup)");
+    let database_kind = db::DatabaseKind::from_url(&config.database_url)
+        .expect("unsupported DATABASE_URL (fail-fast startup)");
 
-    db::schema::ensure_schema(&db_pool)
-        .await
-        .expect("database schema validation/creation failed (fail-fast startup)");
+    let features = FeatureFlags::default();
+    let settings = Settings::new(RuntimeSettings::from_config(&config));
+    let db_health = DatabaseHealth::default();
+
+    let (db_pools, store): (Option<DatabasePools>, Arc<dyn Store>) = match database_kind {
+        db::DatabaseKind::Postgres => {
+            let db_pool = db::connect_lazy(&config.database_url, &config.database_pool)
+                .expect("invalid PostgreSQL configuration (fail-fast startup)");
+
+            let mut startup = tokio::spawn(prepare_database(
+                db_pool.clone(),
+                config.schema_check_mode,
+                features.clone(),
+                settings.clone(),
+                db_health.clone(),
+            ));
 
-    let jwt_service = JwtService::new(config.jwt_secret.clone(), config.jwt_ttl_seconds);
+            // A failed migration or schema check is recorded in `db_health`; the
+            // server then stays degraded whether it failed before or after the deadline.
+            let deadline = config.database_pool.startup_deadline;
+            match tokio::time::timeout(deadline, &mut startup).await {
+                Ok(result) => result.expect("database startup task panicked"),
+                Err(_) => warn!(
+                    "database not ready after {}s; serving in degraded mode while startup continues",
+                    deadline.as_secs()
+                ),
+            }
+
+            let replicas = config
+                .database_replica_urls
+                .iter()
+                .map(|url| db::connect_lazy(url, &config.database_pool))
+                .collect::<Result<Vec<_>, _>>()
+                .expect("invalid DATABASE_REPLICA_URLS (fail-fast startup)");
+            if !replicas.is_empty() {
+                info!("routing lag-tolerant reads to {} replica(s)", replicas.len());
+            }
+
+            let db_pools = DatabasePools::new(db_pool, replicas, config.replica_sticky_window);
+            let store = Arc::new(PgStore::new(db_pools.clone()));
+            (Some(db_pools), store)
+        }
+        #[cfg(feature = "sqlite")]
+        db::DatabaseKind::Sqlite => {
+            let sqlite_pool = db::connect_sqlite(&config.database_url)
+                .await
+                .expect("failed to open SQLite database (fail-fast startup)");
+
+            db::schema::sqlite::ensure_schema(&sqlite_pool)
+                .await
+                .expect("database schema validation/creation failed (fail-fast startup)");
+            db_health.set(DatabaseState::Ready);
+
+            if !config.database_replica_urls.is_empty() {
+                warn!("DATABASE_REPLICA_URLS is ignored on SQLite");
+            }
+            warn!(
+                "running on SQLite: authentication, invites and the admin user and audit endpoints work; \
+                 statistics, user import, feature flags, runtime settings and organizations need PostgreSQL"
+            );
+            (None, Arc::new(db::store::sqlite::SqliteStore::new(sqlite_pool)))
+        }
+    };
+
+    let email_policy = match &config.disposable_email_domains_file {
+        Some(path) => {
+            let policy = EmailDomainPolicy::load(path)
+                .expect("failed to load disposable email domains (fail-fast startup)");
+            info!(
+                "loaded {} disposable email domains from {}",
+                policy.blocked_domain_count(),
+                path.display()
+            );
+            policy
+        }
+        None => EmailDomainPolicy::default(),
+    };
+
+    let mailer = Mailer::from_config(&config)
+        .expect("invalid mail configuration (fail-fast startup)");
+    if !mailer.is_configured() {
+        warn!("SMTP_URL is not set; outgoing emails will only be logged");
+    }
+
+    let jwt_service = JwtService::new(config.jwt_secret.clone());
+    let stats_cache = Arc::new(http::admin::stats::new_stats_cache());
+
+    if let Some(pools) = &db_pools {
+        let mut bus = EventBus::default();
+        bus.subscribe(stats_cache.clone());
+        events::dispatcher::spawn(pools.primary().clone(), bus, db_health.clone());
+
+        if config.job_workers > 0 {
+            jobs::worker::spawn(
+                JobContext {
+                    pool: pools.primary().clone(),
+                    mailer: mailer.clone(),
+                },
+                db_health.clone(),
+                config.job_workers,
+            );
+        } else {
+            info!("JOB_WORKERS=0; background jobs are left to other instances");
+        }
+    }
 
     let app_state = AppState {
-        db: db_pool,
+        db: db_pools,
+        store,
+        db_health,
         jwt: jwt_service,
+        settings: settings.clone(),
+        email_policy: Arc::new(email_policy),
+        trust_proxy_headers: config.trust_proxy_headers,
+        stats_cache,
+        features,
     };
 
+    info!("registration mode: {:?}", settings.current().registration_mode);
+
     let listener = tokio::net::TcpListener::bind(config.addr)
         .await
         .expect("failed to bind TCP listener");
 
     info!("server listening on http://{}", config.addr);
 
-    axum::serve(listener, http::router(app_state))
-        .await
-        .expect("server failed");
+    axum::serve(
+        listener,
+        http::router(app_state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("server failed");
+}
+
+/// Waits for PostgreSQL, applies migrations and loads the cached feature flags and
+/// settings, then starts their change listeners. Until this finishes the server is
+/// degraded; a failure leaves it degraded and is reported by `/health`.
+async fn prepare_database(
+    pool: PgPool,
+    schema_check_mode: SchemaCheckMode,
+    features: FeatureFlags,
+    settings: Settings,
+    health: DatabaseHealth,
+) {
+    let result = async {
+        db::wait_for_database(&pool, None, &health).await?;
+        db::schema::ensure_schema(&pool, schema_check_mode).await?;
+        features.reload(&pool).await?;
+        settings.reload(&pool).await
+    }
+    .await;
+
+    if let Err(error) = result {
+        error!("database startup failed; serving in degraded mode until restarted: {error}");
+        health.set(DatabaseState::Failed {
+            error: error.to_string(),
+        });
+        return;
+    }
+
+    tokio::spawn(features.listen_for_changes(pool.clone()));
+    tokio::spawn(settings.listen_for_changes(pool));
+    health.set(DatabaseState::Ready);
+    info!("database ready");
 }
@@ -1,6 +1,19 @@
-use crate::db::users::UserRecord;
+use crate::{
+    config::RegistrationMode,
+    db::{
+        audit::AuditEventRecord,
+        feature_flags::FeatureFlagRecord,
+        invites::InviteRecord,
+        jobs::JobRecord,
+        organizations::{MemberRecord, MembershipRecord, OrganizationInviteRecord},
+        settings::SettingChangeRecord,
+        stats::{LoginBucket, RegistrationBucket, StatsBucket, UserTotals},
+        users::UserRecord,
+    },
+};
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize)]
@@ -24,8 +37,388 @@ impl From<UserRecord> for PublicUser {
     }
 }
 
+/// `PublicUser` plus fields that only admins may see.
+#[derive(Debug, Serialize)]
+pub struct AdminUserResponse {
+    #[serde(flatten)]
+    pub user: PublicUser,
+    pub token_version: i32,
+    pub version: i64,
+    pub suspended_at: Option<DateTime<Utc>>,
+}
+
+impl From<UserRecord> for AdminUserResponse {
+    fn from(value: UserRecord) -> Self {
+        Self {
+            token_version: value.token_version,
+            version: value.version,
+            suspended_at: value.suspended_at,
+            user: PublicUser::from(value),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
     pub user: PublicUser,
 }
+
+#[derive(Debug, Serialize)]
+pub struct InviteResponse {
+    pub id: Uuid,
+    pub code: String,
+    pub email: Option<String>,
+    pub is_admin: bool,
+    pub max_uses: i32,
+    pub used_count: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl From<InviteRecord> for InviteResponse {
+    fn from(value: InviteRecord) -> Self {
+        Self {
+            id: value.id,
+            code: value.code,
+            email: value.email,
+            is_admin: value.is_admin,
+            max_uses: value.max_uses,
+            used_count: value.used_count,
+            expires_at: value.expires_at,
+            created_by: value.created_by,
+            created_at: value.created_at,
+            revoked_at: value.revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventResponse {
+    pub id: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub event_type: String,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub ip: Option<String>,
+    pub request_id: Option<String>,
+    pub payload: serde_json::Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl From<AuditEventRecord> for AuditEventResponse {
+    fn from(value: AuditEventRecord) -> Self {
+        Self {
+            id: value.id,
+            occurred_at: value.occurred_at,
+            event_type: value.event_type,
+            actor_id: value.actor_id,
+            target_id: value.target_id,
+            ip: value.ip,
+            request_id: value.request_id,
+            payload: value.payload,
+            prev_hash: value.prev_hash,
+            hash: value.hash,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEventListResponse {
+    pub events: Vec<AuditEventResponse>,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobResponse {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub unique_key: Option<String>,
+    pub locked_by: Option<String>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<JobRecord> for JobResponse {
+    fn from(value: JobRecord) -> Self {
+        Self {
+            id: value.id,
+            kind: value.kind,
+            payload: value.payload,
+            status: value.status,
+            attempts: value.attempts,
+            max_attempts: value.max_attempts,
+            run_at: value.run_at,
+            unique_key: value.unique_key,
+            locked_by: value.locked_by,
+            locked_until: value.locked_until,
+            last_error: value.last_error,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobListResponse {
+    pub jobs: Vec<JobResponse>,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserTotalsResponse {
+    pub total: i64,
+    /// Admins that are not suspended.
+    pub admins: i64,
+    pub suspended: i64,
+}
+
+impl From<UserTotals> for UserTotalsResponse {
+    fn from(value: UserTotals) -> Self {
+        Self {
+            total: value.total,
+            admins: value.admins,
+            suspended: value.suspended,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrationBucketResponse {
+    pub bucket_start: DateTime<Utc>,
+    pub registrations: i64,
+}
+
+impl From<RegistrationBucket> for RegistrationBucketResponse {
+    fn from(value: RegistrationBucket) -> Self {
+        Self {
+            bucket_start: value.bucket_start,
+            registrations: value.registrations,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginBucketResponse {
+    pub bucket_start: DateTime<Utc>,
+    pub succeeded: i64,
+    pub failed: i64,
+    /// `failed / (succeeded + failed)`; `null` when there were no attempts.
+    pub failure_rate: Option<f64>,
+}
+
+impl From<LoginBucket> for LoginBucketResponse {
+    fn from(value: LoginBucket) -> Self {
+        Self {
+            bucket_start: value.bucket_start,
+            succeeded: value.succeeded,
+            failed: value.failed,
+            failure_rate: failure_rate(value.succeeded, value.failed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginStatsResponse {
+    pub succeeded: i64,
+    pub failed: i64,
+    pub failure_rate: Option<f64>,
+    pub buckets: Vec<LoginBucketResponse>,
+}
+
+impl From<Vec<LoginBucket>> for LoginStatsResponse {
+    fn from(value: Vec<LoginBucket>) -> Self {
+        let succeeded = value.iter().map(|bucket| bucket.succeeded).sum();
+        let failed = value.iter().map(|bucket| bucket.failed).sum();
+
+        Self {
+            succeeded,
+            failed,
+            failure_rate: failure_rate(succeeded, failed),
+            buckets: value.into_iter().map(LoginBucketResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminStatsResponse {
+    pub generated_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket: StatsBucket,
+    pub users: UserTotalsResponse,
+    pub active_sessions: i64,
+    pub registrations: Vec<RegistrationBucketResponse>,
+    pub logins: LoginStatsResponse,
+}
+
+fn failure_rate(succeeded: i64, failed: i64) -> Option<f64> {
+    let attempts = succeeded + failed;
+    (attempts > 0).then(|| failed as f64 / attempts as f64)
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagResponse {
+    pub key: String,
+    pub description: String,
+    pub enabled: bool,
+    pub rollout_percentage: i16,
+    pub user_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<FeatureFlagRecord> for FeatureFlagResponse {
+    fn from(value: FeatureFlagRecord) -> Self {
+        Self {
+            key: value.key,
+            description: value.description,
+            enabled: value.enabled,
+            rollout_percentage: value.rollout_percentage,
+            user_ids: value.user_ids,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeaturesResponse {
+    pub features: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingResponse {
+    pub key: &'static str,
+    /// Effective value: the stored override, or `default` when there is none.
+    pub value: serde_json::Value,
+    pub default: serde_json::Value,
+    pub overridden: bool,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingChangeResponse {
+    pub id: i64,
+    pub key: String,
+    pub old_value: Option<serde_json::Value>,
+    pub new_value: Option<serde_json::Value>,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl From<SettingChangeRecord> for SettingChangeResponse {
+    fn from(value: SettingChangeRecord) -> Self {
+        Self {
+            id: value.id,
+            key: value.key,
+            old_value: value.old_value,
+            new_value: value.new_value,
+            changed_by: value.changed_by,
+            changed_at: value.changed_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingChangeListResponse {
+    pub changes: Vec<SettingChangeResponse>,
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublicSettingsResponse {
+    pub registration_mode: RegistrationMode,
+    pub maintenance_banner: Option<String>,
+}
+
+/// An organization together with the caller's role in it.
+#[derive(Debug, Serialize)]
+pub struct OrganizationResponse {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub role: String,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl From<MembershipRecord> for OrganizationResponse {
+    fn from(value: MembershipRecord) -> Self {
+        Self {
+            id: value.organization.id,
+            slug: value.organization.slug,
+            name: value.organization.name,
+            role: value.role,
+            created_by: value.organization.created_by,
+            created_at: value.organization.created_at,
+            joined_at: value.joined_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationMemberResponse {
+    pub user_id: Uuid,
+    pub nickname: String,
+    pub email: String,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl From<MemberRecord> for OrganizationMemberResponse {
+    fn from(value: MemberRecord) -> Self {
+        Self {
+            user_id: value.user_id,
+            nickname: value.nickname,
+            email: value.email,
+            role: value.role,
+            joined_at: value.joined_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrganizationInviteResponse {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub code: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OrganizationInviteRecord> for OrganizationInviteResponse {
+    fn from(value: OrganizationInviteRecord) -> Self {
+        Self {
+            id: value.id,
+            organization_id: value.organization_id,
+            code: value.code,
+            email: value.email,
+            role: value.role,
+            expires_at: value.expires_at,
+            created_by: value.created_by,
+            created_at: value.created_at,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Runtime settings: env-derived defaults from `AppConfig`, overridden by rows
+//! in the `settings` table and reloaded whenever Postgres notifies `SETTINGS_CHANNEL`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+use crate::{
+    config::{AppConfig, RegistrationMode},
+    db::{
+        notify,
+        settings::{self, SETTINGS_CHANNEL},
+    },
+    error::AppError,
+};
+
+const MAX_BANNER_CHARS: usize = 500;
+const MIN_JWT_TTL_SECONDS: i64 = 60;
+const MAX_JWT_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingKey {
+    RegistrationMode,
+    MaintenanceBanner,
+    JwtTtlSeconds,
+}
+
+impl SettingKey {
+    pub const ALL: [Self; 3] = [Self::RegistrationMode, Self::MaintenanceBanner, Self::JwtTtlSeconds];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RegistrationMode => "registration_mode",
+            Self::MaintenanceBanner => "maintenance_banner",
+            Self::JwtTtlSeconds => "jwt_ttl_seconds",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|key| key.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSettings {
+    pub registration_mode: RegistrationMode,
+    pub maintenance_banner: Option<String>,
+    pub jwt_ttl_seconds: i64,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            registration_mode: config.registration_mode,
+            maintenance_banner: None,
+            jwt_ttl_seconds: config.jwt_ttl_seconds,
+        }
+    }
+
+    pub fn value(&self, key: SettingKey) -> Value {
+        match key {
+            SettingKey::RegistrationMode => serde_json::to_value(self.registration_mode).unwrap_or(Value::Null),
+            SettingKey::MaintenanceBanner => self
+                .maintenance_banner
+                .clone()
+                .map_or(Value::Null, Value::String),
+            SettingKey::JwtTtlSeconds => Value::from(self.jwt_ttl_seconds),
+        }
+    }
+
+    /// Validates `value` for `key` and applies it.
+    pub fn apply(&mut self, key: SettingKey, value: &Value) -> Result<(), AppError> {
+        match key {
+            SettingKey::RegistrationMode => {
+                self.registration_mode = value
+                    .as_str()
+                    .and_then(RegistrationMode::parse)
+                    .ok_or_else(|| {
+                        AppError::BadRequest(
+                            "registration_mode must be one of: open, invite_only, closed".to_string(),
+                        )
+                    })?;
+            }
+            SettingKey::MaintenanceBanner => {
+                let banner = match value {
+                    Value::Null => None,
+                    Value::String(banner) => Some(banner.trim()).filter(|banner| !banner.is_empty()),
+                    _ => {
+                        return Err(AppError::BadRequest(
+                            "maintenance_banner must be a string or null".to_string(),
+                        ));
+                    }
+                };
+
+                if banner.is_some_and(|banner| banner.chars().count() > MAX_BANNER_CHARS) {
+                    return Err(AppError::BadRequest(format!(
+                        "maintenance_banner must be at most {MAX_BANNER_CHARS} characters"
+                    )));
+                }
+
+                self.maintenance_banner = banner.map(str::to_string);
+            }
+            SettingKey::JwtTtlSeconds => {
+                self.jwt_ttl_seconds = value
+                    .as_i64()
+                    .filter(|ttl| (MIN_JWT_TTL_SECONDS..=MAX_JWT_TTL_SECONDS).contains(ttl))
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "jwt_ttl_seconds must be an integer between {MIN_JWT_TTL_SECONDS} and {MAX_JWT_TTL_SECONDS}"
+                        ))
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Cheap to clone; every clone shares the same snapshot.
+#[derive(Clone)]
+pub struct Settings {
+    defaults: Arc<RuntimeSettings>,
+    current: Arc<RwLock<Arc<RuntimeSettings>>>,
+}
+
+impl Settings {
+    pub fn new(defaults: RuntimeSettings) -> Self {
+        let defaults = Arc::new(defaults);
+
+        Self {
+            current: Arc::new(RwLock::new(defaults.clone())),
+            defaults,
+        }
+    }
+
+    pub fn current(&self) -> Arc<RuntimeSettings> {
+        self.current
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn defaults(&self) -> &RuntimeSettings {
+        &self.defaults
+    }
+
+    /// Rebuilds the snapshot from the defaults plus stored overrides. Unknown
+    /// keys and invalid stored values are skipped with a warning.
+    pub async fn reload(&self, pool: &PgPool) -> Result<(), AppError> {
+        let records = settings::list_settings(pool).await?;
+
+        let mut current = RuntimeSettings::clone(&self.defaults);
+        for record in records {
+            let Some(key) = SettingKey::parse(&record.key) else {
+                warn!("ignoring unknown setting '{}'", record.key);
+                continue;
+            };
+
+            if let Err(error) = current.apply(key, &record.value) {
+                warn!("ignoring stored value of setting '{}': {error}", record.key);
+            }
+        }
+
+        *self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(current);
+
+        Ok(())
+    }
+
+    /// Keeps the snapshot fresh for the lifetime of the process.
+    pub async fn listen_for_changes(self, pool: PgPool) {
+        notify::watch(pool.clone(), SETTINGS_CHANNEL, || async {
+            if let Err(error) = self.reload(&pool).await {
+                warn!("failed to reload settings: {error}");
+            }
+        })
+        .await;
+    }
+}
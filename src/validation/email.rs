@@ -0,0 +1,253 @@
+use std::{
+    collections::HashSet,
+    fs,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+
+use crate::error::AppError;
+
+const MAX_EMAIL_OCTETS: usize = 254;
+const MAX_LOCAL_PART_OCTETS: usize = 64;
+
+/// Characters allowed in an unquoted local part besides letters and digits (RFC 5322 `atext`).
+const ATEXT_SPECIALS: &str = "!#$%&'*+-/=?^_`{|}~";
+
+/// Parses an RFC 5321/5322 `addr-spec` and returns it in canonical form: the local
+/// part is kept as typed (it is case-sensitive by the RFC) and the domain is
+/// converted to lowercase ASCII, with IDN labels encoded as punycode.
+pub fn normalize_and_validate_email(value: &str) -> Result<String, AppError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(invalid("address is empty"));
+    }
+
+    let (local_part, domain) = split_address(value)?;
+
+    validate_local_part(local_part)?;
+    let domain = normalize_domain(domain)?;
+
+    let email = format!("{local_part}@{domain}");
+    if email.len() > MAX_EMAIL_OCTETS {
+        return Err(invalid(&format!(
+            "address must not exceed {MAX_EMAIL_OCTETS} octets"
+        )));
+    }
+
+    Ok(email)
+}
+
+/// Domain part of an address returned by `normalize_and_validate_email`.
+pub fn email_domain(email: &str) -> &str {
+    email.rsplit_once('@').map_or("", |(_, domain)| domain)
+}
+
+/// Blocklist of disposable email providers loaded from `DISPOSABLE_EMAIL_DOMAINS_FILE`.
+#[derive(Debug, Default)]
+pub struct EmailDomainPolicy {
+    blocked_domains: HashSet<String>,
+}
+
+impl EmailDomainPolicy {
+    /// Reads one domain per line; blank lines and `#` comments are ignored.
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let contents = fs::read_to_string(path).map_err(|error| {
+            AppError::Internal(format!(
+                "unable to read disposable email domains from {}: {error}",
+                path.display()
+            ))
+        })?;
+
+        let mut blocked_domains = HashSet::new();
+        for (index, line) in contents.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            if entry.is_empty() {
+                continue;
+            }
+
+            let domain = idna::domain_to_ascii(entry).map_err(|_| {
+                AppError::Internal(format!(
+                    "{}:{}: '{entry}' is not a valid domain",
+                    path.display(),
+                    index + 1
+                ))
+            })?;
+            blocked_domains.insert(domain);
+        }
+
+        Ok(Self { blocked_domains })
+    }
+
+    pub fn blocked_domain_count(&self) -> usize {
+        self.blocked_domains.len()
+    }
+
+    /// Rejects addresses whose domain, or any parent domain, is on the blocklist.
+    pub fn ensure_allowed(&self, email: &str) -> Result<(), AppError> {
+        let mut domain = email_domain(email);
+
+        loop {
+            if self.blocked_domains.contains(domain) {
+                return Err(AppError::BadRequest(
+                    "email addresses from disposable providers are not accepted".to_string(),
+                ));
+            }
+
+            match domain.split_once('.') {
+                Some((_, parent)) if parent.contains('.') => domain = parent,
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Splits on the single `@` that is not inside a quoted local part.
+fn split_address(value: &str) -> Result<(&str, &str), AppError> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    let mut separator = None;
+
+    for (index, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match c {
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '@' if !in_quotes => {
+                if separator.is_some() {
+                    return Err(invalid("address must contain exactly one '@' outside quotes"));
+                }
+                separator = Some(index);
+            }
+            _ => {}
+        }
+    }
+
+    if in_quotes {
+        return Err(invalid("quoted local part is not terminated"));
+    }
+
+    let separator = separator.ok_or_else(|| invalid("address must contain '@'"))?;
+    let (local_part, domain) = (&value[..separator], &value[separator + 1..]);
+
+    if local_part.is_empty() {
+        return Err(invalid("local part before '@' is empty"));
+    }
+    if domain.is_empty() {
+        return Err(invalid("domain after '@' is empty"));
+    }
+
+    Ok((local_part, domain))
+}
+
+fn validate_local_part(local_part: &str) -> Result<(), AppError> {
+    if local_part.len() > MAX_LOCAL_PART_OCTETS {
+        return Err(invalid(&format!(
+            "local part must not exceed {MAX_LOCAL_PART_OCTETS} octets"
+        )));
+    }
+
+    if local_part.starts_with('"') {
+        return validate_quoted_local_part(local_part);
+    }
+
+    if local_part.starts_with('.') || local_part.ends_with('.') {
+        return Err(invalid("local part must not start or end with '.'"));
+    }
+    if local_part.contains("..") {
+        return Err(invalid("local part must not contain consecutive dots"));
+    }
+
+    if let Some(c) = local_part.chars().find(|c| !is_atext(*c) && *c != '.') {
+        return Err(invalid(&format!(
+            "local part contains {c:?}, which is only allowed inside quotes"
+        )));
+    }
+
+    Ok(())
+}
+
+fn validate_quoted_local_part(local_part: &str) -> Result<(), AppError> {
+    let inner = local_part
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|inner| !inner.is_empty())
+        .ok_or_else(|| invalid("quoted local part must be a single non-empty quoted string"))?;
+
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) if (' '..='~').contains(&escaped) => {}
+                _ => return Err(invalid("invalid escape sequence in quoted local part")),
+            },
+            '"' => return Err(invalid("unescaped '\"' inside quoted local part")),
+            c if c.is_control() => {
+                return Err(invalid("quoted local part must not contain control characters"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn normalize_domain(domain: &str) -> Result<String, AppError> {
+    if let Some(literal) = domain.strip_prefix('[') {
+        return normalize_domain_literal(literal);
+    }
+
+    let ascii = idna::domain_to_ascii_strict(domain)
+        .map_err(|_| invalid("domain is not a valid hostname"))?;
+
+    let labels: Vec<&str> = ascii.split('.').collect();
+    if labels.len() < 2 {
+        return Err(invalid("domain must contain at least one '.'"));
+    }
+
+    for label in &labels {
+        if label.is_empty() {
+            return Err(invalid("domain must not contain empty labels"));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(invalid("domain labels must not start or end with '-'"));
+        }
+    }
+
+    let top_level = labels.last().copied().unwrap_or_default();
+    if top_level.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("top-level domain must not be numeric"));
+    }
+
+    Ok(ascii)
+}
+
+fn normalize_domain_literal(literal: &str) -> Result<String, AppError> {
+    let address = literal
+        .strip_suffix(']')
+        .ok_or_else(|| invalid("domain literal must end with ']'"))?;
+
+    if let Some(ipv6) = address.strip_prefix("IPv6:") {
+        let parsed = ipv6
+            .parse::<Ipv6Addr>()
+            .map_err(|_| invalid("domain literal is not a valid IPv6 address"))?;
+        return Ok(format!("[IPv6:{parsed}]"));
+    }
+
+    let parsed = address
+        .parse::<Ipv4Addr>()
+        .map_err(|_| invalid("domain literal is not a valid IPv4 address"))?;
+
+    Ok(format!("[{parsed}]"))
+}
+
+fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || ATEXT_SPECIALS.contains(c) || (!c.is_ascii() && c.is_alphanumeric())
+}
+
+fn invalid(reason: &str) -> AppError {
+    AppError::BadRequest(format!("email must be a valid address: {reason}"))
+}
@@ -0,0 +1,18 @@
+pub mod email;
+pub mod nickname;
+pub mod organization;
+
+use crate::error::AppError;
+
+pub use email::normalize_and_validate_email;
+pub use nickname::validate_nickname;
+
+pub fn validate_password(value: &str) -> Result<(), AppError> {
+    if value.len() < 8 {
+        return Err(AppError::BadRequest(
+            "password must contain at least 8 characters".to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{skeleton, GeneralSecurityProfile, MixedScript};
+
+use crate::error::AppError;
+
+const MIN_NICKNAME_CHARS: usize = 3;
+const MAX_NICKNAME_CHARS: usize = 32;
+const NICKNAME_SEPARATORS: [char; 3] = ['_', '-', '.'];
+
+/// Names that could be mistaken for staff or system accounts. They are compared
+/// by confusable skeleton, so lookalikes such as "аdmin" (Cyrillic "а") are
+/// rejected as well.
+const RESERVED_NICKNAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "api",
+    "moderator",
+    "null",
+    "owner",
+    "root",
+    "security",
+    "staff",
+    "support",
+    "swarm",
+    "system",
+    "undefined",
+];
+
+/// Returns the display form of a nickname: NFKC-normalized and trimmed.
+pub fn validate_nickname(value: &str) -> Result<String, AppError> {
+    let nickname: String = value.trim().nfkc().collect();
+
+    let length = nickname.chars().count();
+    if !(MIN_NICKNAME_CHARS..=MAX_NICKNAME_CHARS).contains(&length) {
+        return Err(AppError::BadRequest(format!(
+            "nickname length must be between {MIN_NICKNAME_CHARS} and {MAX_NICKNAME_CHARS} characters"
+        )));
+    }
+
+    if let Some(invalid) = nickname.chars().find(|c| !is_allowed_char(*c)) {
+        return Err(AppError::BadRequest(format!(
+            "nickname contains a disallowed character {invalid:?}; use letters, digits, '_', '-' or '.'"
+        )));
+    }
+
+    let starts_and_ends_alphanumeric = nickname.chars().next().is_some_and(char::is_alphanumeric)
+        && nickname.chars().last().is_some_and(char::is_alphanumeric);
+    if !starts_and_ends_alphanumeric {
+        return Err(AppError::BadRequest(
+            "nickname must start and end with a letter or digit".to_string(),
+        ));
+    }
+
+    let has_repeated_separators = nickname
+        .chars()
+        .zip(nickname.chars().skip(1))
+        .any(|(a, b)| NICKNAME_SEPARATORS.contains(&a) && NICKNAME_SEPARATORS.contains(&b));
+    if has_repeated_separators {
+        return Err(AppError::BadRequest(
+            "nickname must not contain consecutive '_', '-' or '.'".to_string(),
+        ));
+    }
+
+    if !nickname.as_str().is_single_script() {
+        return Err(AppError::BadRequest(
+            "nickname must not mix letters from different scripts".to_string(),
+        ));
+    }
+
+    let skeleton = nickname_skeleton(&canonical_nickname(&nickname));
+    let is_reserved = RESERVED_NICKNAMES
+        .iter()
+        .any(|reserved| nickname_skeleton(reserved) == skeleton);
+    if is_reserved {
+        return Err(AppError::BadRequest("nickname is reserved".to_string()));
+    }
+
+    Ok(nickname)
+}
+
+/// Case-insensitive form used for uniqueness and lookups (`users.nickname_canonical`).
+pub fn canonical_nickname(value: &str) -> String {
+    let normalized: String = value.trim().nfkc().collect();
+    normalized.to_lowercase().nfkc().collect()
+}
+
+/// UTS #39 confusable skeleton of a canonical nickname (`users.nickname_skeleton`).
+/// Two nicknames with the same skeleton look alike, e.g. "paypal" and "paypa1".
+pub fn nickname_skeleton(canonical: &str) -> String {
+    let skeleton: String = skeleton(canonical).collect();
+    skeleton.to_lowercase()
+}
+
+fn is_allowed_char(c: char) -> bool {
+    NICKNAME_SEPARATORS.contains(&c) || (c.is_alphanumeric() && c.identifier_allowed())
+}
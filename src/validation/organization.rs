@@ -0,0 +1,35 @@
+use crate::error::AppError;
+
+const MIN_SLUG_LEN: usize = 3;
+const MAX_SLUG_LEN: usize = 40;
+const MAX_NAME_LEN: usize = 100;
+
+/// Lowercase ASCII letters, digits and `-`, starting with a letter, e.g. `platform-team`.
+pub fn validate_slug(value: &str) -> Result<String, AppError> {
+    let slug = value.trim();
+
+    let valid = (MIN_SLUG_LEN..=MAX_SLUG_LEN).contains(&slug.len())
+        && slug.starts_with(|c: char| c.is_ascii_lowercase())
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(AppError::BadRequest(format!(
+            "slug must start with a lowercase letter and contain {MIN_SLUG_LEN} to {MAX_SLUG_LEN} lowercase letters, digits or '-'"
+        )));
+    }
+
+    Ok(slug.to_string())
+}
+
+pub fn validate_name(value: &str) -> Result<String, AppError> {
+    let name = value.trim();
+
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return Err(AppError::BadRequest(format!(
+            "name must contain between 1 and {MAX_NAME_LEN} characters"
+        )));
+    }
+
+    Ok(name.to_string())
+}